@@ -0,0 +1,70 @@
+// cargo run -p bot --example dice -- host:port [name]
+use bot::{Bot, Command, Context};
+use rand::Rng;
+use std::time::Duration;
+
+const HELP: &str = "!roll [NdM] rolls dice, 1d6 by default - !remind MINUTES TEXT says TEXT later - !help";
+
+struct Dice;
+
+impl Bot for Dice {
+    fn on_join(&mut self, ctx: &mut Context) {
+        ctx.say("Dice bot here, !help for what I can do");
+    }
+
+    fn on_user_join(&mut self, ctx: &mut Context, name: &str) {
+        ctx.say(&format!("Welcome {}!", name));
+    }
+
+    fn on_command(&mut self, ctx: &mut Context, from: &str, command: &Command) {
+        match command.name.as_str() {
+            "help" => ctx.say(HELP),
+            "roll" => match roll(command.arg(0).unwrap_or("1d6")) {
+                Some((rolls, total)) => ctx.say(&format!("{} rolled {} ({})", from, total, rolls.join(" + "))),
+                None => ctx.say("Dice are written NdM, at most 20 dice of up to 1000 sides"),
+            },
+            "remind" => match (command.arg(0).and_then(|minutes| minutes.parse::<u64>().ok()), command.args.get(1..)) {
+                (Some(minutes), Some(text)) if !text.is_empty() => {
+                    ctx.scheduler.after(Duration::from_secs(minutes * 60), format!("{}: {}", from, text.join(" ")));
+                    ctx.say(&format!("I will remind you in {} minute(s), {}", minutes, from));
+                }
+                _ => ctx.say("Usage: !remind MINUTES TEXT"),
+            },
+            _ => (),
+        }
+    }
+}
+
+// each roll and their sum
+fn roll(dice: &str) -> Option<(Vec<String>, u32)> {
+    let (count, sides) = dice.to_lowercase().split_once('d').map(|(count, sides)| (count.to_owned(), sides.to_owned()))?;
+    let count = match count.as_str() {
+        "" => 1,
+        count => count.parse().ok()?,
+    };
+    let sides: u32 = sides.parse().ok()?;
+
+    if !(1..=20).contains(&count) || !(2..=1000).contains(&sides) {
+        return None;
+    }
+
+    let rolls: Vec<u32> = (0..count).map(|_| rand::thread_rng().gen_range(1..=sides)).collect();
+    Some((rolls.iter().map(u32::to_string).collect(), rolls.iter().sum()))
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let server = match args.next() {
+        Some(server) => server,
+        None => {
+            println!("usage: dice host:port [name]");
+            return;
+        }
+    };
+    let name = args.next().unwrap_or_else(|| String::from("dice"));
+
+    if let Err(err) = bot::run(&mut Dice, &server, &name) {
+        println!("{}", err);
+    }
+}
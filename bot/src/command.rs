@@ -0,0 +1,62 @@
+// `!name args`, quotes keep spaces inside a single argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    pub args: Vec<String>,
+    // everything after the name, as it was typed
+    pub rest: String,
+}
+
+impl Command {
+    pub fn parse(content: &str) -> Option<Self> {
+        let content = content.trim().strip_prefix('!')?;
+        let (name, rest) = content.split_once(char::is_whitespace).unwrap_or((content, ""));
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_lowercase(),
+            args: split_args(rest),
+            rest: rest.trim().to_owned(),
+        })
+    }
+
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+}
+
+fn split_args(rest: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut quoted = false;
+    // "" is still an argument
+    let mut started = false;
+
+    for c in rest.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut arg));
+                    started = false;
+                }
+            }
+            c => {
+                arg.push(c);
+                started = true;
+            }
+        }
+    }
+
+    if started {
+        args.push(arg);
+    }
+
+    args
+}
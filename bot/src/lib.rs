@@ -0,0 +1,220 @@
+// automated participants: implement Bot, then hand it to `run` with a server to join
+mod command;
+mod scheduler;
+
+pub use command::Command;
+pub use scheduler::{Scheduler, MIN_INTERVAL};
+
+use client_core::connection::{self, Connection};
+use client_core::identity;
+use client_core::state::{ChatState, Update};
+use protocol::network::{refusal, NetworkMessage, MAX_MESSAGE_LEN};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+// how long to wait for the server when nothing is scheduled
+const IDLE: Duration = Duration::from_secs(60);
+
+// every hook does nothing by default, a bot overrides the ones it cares about
+pub trait Bot {
+    // the user list is known by then
+    fn on_join(&mut self, _ctx: &mut Context) {}
+
+    // the default looks for a command and passes it on to `on_command`
+    fn on_message(&mut self, ctx: &mut Context, from: &str, content: &str) {
+        if let Some(command) = Command::parse(content) {
+            self.on_command(ctx, from, &command);
+        }
+    }
+
+    fn on_command(&mut self, _ctx: &mut Context, _from: &str, _command: &Command) {}
+
+    fn on_user_join(&mut self, _ctx: &mut Context, _name: &str) {}
+
+    fn on_user_leave(&mut self, _ctx: &mut Context, _name: &str) {}
+}
+
+// what a bot can see and do from its hooks
+pub struct Context<'a> {
+    pub state: &'a ChatState,
+    pub scheduler: &'a mut Scheduler,
+    connection: &'a Connection,
+    quit: &'a mut bool,
+}
+
+impl Context<'_> {
+    pub fn say(&self, text: &str) {
+        say(self.connection, self.state, text);
+    }
+
+    // leaves once the hook returns
+    pub fn quit(&mut self) {
+        *self.quit = true;
+    }
+}
+
+// errors are only reported, a bot keeps going until the server is gone
+fn say(connection: &Connection, state: &ChatState, text: &str) {
+    if text.len() > MAX_MESSAGE_LEN {
+        println!("Not sent, messages are at most {} byte and this one is {}", MAX_MESSAGE_LEN, text.len());
+        return;
+    }
+
+    if let Err(err) = connection.send(NetworkMessage::message(0, state.personal_id, text.to_owned(), Vec::new())) {
+        println!("{}", err);
+    }
+}
+
+// connects as a bot and blocks until the server closes the connection or the bot quits
+pub fn run(bot: &mut impl Bot, server: &str, name: &str) -> Result<(), String> {
+    let addr = connection::resolve(server)?;
+
+    // each bot is recognized on its own, apart from the others and from its owner
+    let file: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    let (secret, error) = identity::load(&format!("bot-{}", file));
+    if let Some(err) = error {
+        println!("{}", err);
+    }
+
+    let connection = Connection::open(&addr, name, true, secret).map_err(|err| format!("{}: {}", server, err))?;
+    let incoming = connection.incoming().map_err(|err| err.to_string())?;
+
+    // read on a thread of its own so scheduled messages go out on time
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for msg in incoming {
+            if sender.send(msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut state = ChatState::new(name.to_owned());
+    let mut scheduler = Scheduler::default();
+    let mut quit = false;
+    let mut joined = false;
+
+    let result = loop {
+        let timeout = scheduler.next_due().map_or(IDLE, |due| due.saturating_duration_since(Instant::now()));
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(msg)) => {
+                let update = state.receive(msg);
+
+                // a bot has no log to show, only the latest message is looked at
+                let msg = match update {
+                    Update::Logged => state.messages.pop(),
+                    _ => None,
+                };
+
+                let mut ctx = Context { state: &state, scheduler: &mut scheduler, connection: &connection, quit: &mut quit };
+
+                match msg {
+                    Some((NetworkMessage::UserList(_), _)) if !joined => {
+                        joined = true;
+                        bot.on_join(&mut ctx);
+                    }
+                    Some((NetworkMessage::UserJoin(join), _)) => bot.on_user_join(&mut ctx, join.name()),
+                    Some((NetworkMessage::UserLeave(_), from)) => bot.on_user_leave(&mut ctx, &from),
+                    // our own messages come back too
+                    Some((NetworkMessage::Message(m), from)) if m.from() != ctx.state.personal_id => bot.on_message(&mut ctx, &from, m.content()),
+                    Some((NetworkMessage::RateLimited(limited), _)) => println!("Rate limited, retry in {}ms", limited.retry_after()),
+                    Some((NetworkMessage::Refused(refused), _)) if refused.reason() == refusal::NAME_TAKEN => {
+                        println!("Somebody online already has that name");
+                    }
+                    Some((NetworkMessage::Refused(refused), _)) => break Err(match refused.reason() {
+                        refusal::SERVER_FULL => format!("{}: the server is full", server),
+                        reason => format!("{}: refused, reason {:#04X}", server, reason),
+                    }),
+                    _ => (),
+                }
+            }
+            Ok(Err(err)) => break Err(err),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break Ok(()),
+        }
+
+        for message in scheduler.due(Instant::now()) {
+            say(&connection, &state, &message);
+        }
+
+        if quit {
+            break Ok(());
+        }
+    };
+
+    connection.close();
+    result
+}
+
+#[cfg(test)]
+mod parse {
+    use crate::Command;
+
+    #[test]
+    fn parses_name_and_args() {
+        let command = Command::parse("!Roll 2d6 +1").unwrap();
+
+        assert_eq!(command.name, "roll");
+        assert_eq!(command.args, ["2d6", "+1"]);
+        assert_eq!(command.rest, "2d6 +1");
+        assert_eq!(command.arg(1), Some("+1"));
+        assert_eq!(command.arg(2), None);
+    }
+
+    #[test]
+    fn keeps_quoted_args_together() {
+        let command = Command::parse("!remind 10 \"stand up\" \"\"").unwrap();
+
+        assert_eq!(command.args, ["10", "stand up", ""]);
+    }
+
+    #[test]
+    fn ignores_plain_messages() {
+        assert_eq!(Command::parse("hello !roll"), None);
+        assert_eq!(Command::parse("!"), None);
+        assert_eq!(Command::parse("! roll"), None);
+        assert_eq!(Command::parse("  !help  ").unwrap().args, Vec::<String>::new());
+    }
+}
+
+#[cfg(test)]
+mod schedule {
+    use crate::{Scheduler, MIN_INTERVAL};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn fires_once_after_the_delay() {
+        let mut scheduler = Scheduler::default();
+        let now = Instant::now();
+        scheduler.after(Duration::from_secs(5), String::from("later"));
+        scheduler.after(Duration::from_secs(2), String::from("sooner"));
+
+        assert!(scheduler.due(now).is_empty());
+        assert_eq!(scheduler.due(now + Duration::from_secs(10)), ["sooner", "later"]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn repeats_at_its_interval() {
+        let mut scheduler = Scheduler::default();
+        let now = Instant::now();
+        scheduler.every(Duration::from_secs(10), String::from("tick"));
+
+        assert_eq!(scheduler.due(now + Duration::from_secs(35)), ["tick"]);
+        assert!(scheduler.next_due().unwrap() > now + Duration::from_secs(35));
+        assert!(!scheduler.is_empty());
+    }
+
+    #[test]
+    fn cancels_and_limits_the_pace() {
+        let mut scheduler = Scheduler::default();
+        let now = Instant::now();
+        let id = scheduler.every(Duration::ZERO, String::from("spam"));
+
+        assert!(scheduler.next_due().unwrap() >= now + MIN_INTERVAL);
+        assert!(scheduler.cancel(id));
+        assert!(!scheduler.cancel(id));
+        assert!(scheduler.is_empty());
+    }
+}
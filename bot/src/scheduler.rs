@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+// anything faster would only trip the rate limit of the server
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Task {
+    id: u32,
+    due: Instant,
+    // repeating tasks are due again this long after they fire
+    every: Option<Duration>,
+    message: String,
+}
+
+// messages to send later, once or at a fixed interval
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    next_id: u32,
+}
+
+impl Scheduler {
+    pub fn after(&mut self, delay: Duration, message: String) -> u32 {
+        self.add(Instant::now() + delay, None, message)
+    }
+
+    pub fn every(&mut self, interval: Duration, message: String) -> u32 {
+        let interval = interval.max(MIN_INTERVAL);
+        self.add(Instant::now() + interval, Some(interval), message)
+    }
+
+    fn add(&mut self, due: Instant, every: Option<Duration>, message: String) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.tasks.push(Task { id, due, every, message });
+
+        id
+    }
+
+    // false when nothing with this id was waiting
+    pub fn cancel(&mut self, id: u32) -> bool {
+        let len = self.tasks.len();
+        self.tasks.retain(|task| task.id != id);
+
+        self.tasks.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.tasks.iter().map(|task| task.due).min()
+    }
+
+    // messages due by `now`, earliest first; repeating ones are scheduled again
+    pub fn due(&mut self, now: Instant) -> Vec<String> {
+        let mut due: Vec<_> = self.tasks.iter().filter(|task| task.due <= now).map(|task| (task.due, task.message.to_owned())).collect();
+        due.sort_by_key(|(due, _)| *due);
+
+        self.tasks.retain_mut(|task| match (task.due <= now, task.every) {
+            (false, _) => true,
+            // a late tick is not made up for, the next one keeps its pace
+            (true, Some(every)) => {
+                while task.due <= now {
+                    task.due += every;
+                }
+
+                true
+            }
+            (true, None) => false,
+        });
+
+        due.into_iter().map(|(_, message)| message).collect()
+    }
+}
@@ -0,0 +1,133 @@
+# messaging, English
+# every other catalog falls back to this one for what it lacks
+
+## names in a sentence
+list-and = { $init } and { $last }
+others = { $count ->
+    [one] one other
+   *[other] { $count } others
+}
+
+## home
+username = Username
+login = Login
+
+## servers
+address = host:port
+connect = Connect
+refresh = Refresh
+no-servers = No server found
+users-online = { $current } online
+users-online-max = { $current }/{ $max } online
+encrypted = encrypted
+password = password
+protocol-mismatch = Protocol v{ $version }, this client speaks v{ $ours }
+
+## chat
+message-placeholder = Send a message
+search-placeholder = Search, from:name before:2021-12-31 after:2021-12-01
+attach = Attach
+export = Export
+preview = Preview
+bytes-left = { $count ->
+    [one] 1 byte left
+   *[other] { $count } bytes left
+}
+bytes-over = { $count ->
+    [one] 1 byte too many
+   *[other] { $count } bytes too many
+}
+message-too-long = Messages are at most { $max } bytes, this one is { $len }
+pin = Pin
+unpin = Unpin
+away-label = { $name } (away)
+bot-label = { $name } (bot)
+earlier-messages = Earlier messages are from your last visit
+new-messages = New messages
+seen-by = Seen by { $names }
+already-here = { $count ->
+    [one] { $names } is already here
+   *[other] { $names } are already here
+}
+users-joined = { $names } joined the server
+users-left = { $names } left the server
+user-renamed = { $old } is now known as { $new }
+user-away = { $name } is away
+user-away-message = { $name } is away: { $message }
+user-back = { $name } is back
+muted = { $seconds ->
+    [one] You are muted for one second for flooding
+   *[other] You are muted for { $seconds } seconds for flooding
+}
+rate-limited = You are sending messages too fast, your last message was dropped
+refused-full = The server is full, try again later
+refused-name-taken = Somebody online already has that name
+refused = The server refused the connection
+who = { $count } online: { $names }
+open-link-failed = Could not open { $url }: { $error }
+export-failed = Could not export the conversation: { $error }
+send-failed = Could not send to the server: { $error }
+disconnected = Disconnected from the server
+show-rules = Show rules
+hide-rules = Hide rules
+show-pinned = Show pinned ({ $count })
+hide-pinned = Hide pinned ({ $count })
+
+## commands
+command-usage = Usage: { $usage }
+name-too-long = Names are 1 to { $max } bytes long
+action-too-long = Actions are at most { $max } bytes
+away-too-long = Away messages are at most { $max } bytes
+unknown-command = Unknown command /{ $name }, type /help for the list
+not-online = Nobody named { $name } is online
+help-nick = change your name
+help-me = say what you are doing
+help-msg = send a message only USER sees
+help-join = leave for another server
+help-leave = go back to the server list
+help-clear = empty the chat log
+help-away = mark yourself away, or back when you are
+help-who = list the users online
+help-seen = show or hide who has read up to where
+help-rules = show the server rules
+help-help = list the commands
+help-quit = leave and close the client
+
+## search
+search-empty = Type words to search for
+search-too-long = Searches are at most { $max } bytes
+search-name-too-long = Usernames are at most { $max } bytes
+invalid-date = Expected a date like 2021-12-31, found { $date }
+searching = Searching...
+search-results = { $total ->
+    [one] 1 result
+   *[other] { $total } results
+}
+search-most-recent = { $shown } most recent of { $total } results
+not-in-history = This message isn't in the local history, only the search result is available
+close = Close
+
+## transfers
+download = Download
+cancel = Cancel
+save = Save
+transfer-cancelled = Transfer cancelled
+file-unreadable = Could not read { $path }: { $error }
+file-too-large = { $path } is { $size } bytes, files are at most { $max }
+save-failed = Could not save { $path }: { $error }
+
+## notifications
+notify-private = { $name } (private)
+notify-mention = { $name } mentioned you
+
+## settings
+settings = Settings
+theme = Theme
+reload-themes = Reload themes
+text-size = Text size
+language = Language
+language-system = System ({ $language })
+notifications = Notifications
+notifications-on = Mentions and private messages
+notifications-off = Off
+back = Back
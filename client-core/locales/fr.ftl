@@ -0,0 +1,138 @@
+# messaging, français
+
+## noms dans une phrase
+list-and = { $init } et { $last }
+others = { $count ->
+    [one] une autre personne
+   *[other] { $count } autres personnes
+}
+
+## accueil
+username = Nom d'utilisateur
+login = Connexion
+
+## serveurs
+address = hôte:port
+connect = Se connecter
+refresh = Actualiser
+no-servers = Aucun serveur trouvé
+users-online = { $current } en ligne
+users-online-max = { $current }/{ $max } en ligne
+encrypted = chiffré
+password = mot de passe
+protocol-mismatch = Protocole v{ $version }, ce client parle la v{ $ours }
+
+## discussion
+message-placeholder = Envoyez un message
+search-placeholder = Rechercher, from:nom before:2021-12-31 after:2021-12-01
+attach = Joindre
+export = Exporter
+preview = Aperçu
+bytes-left = { $count ->
+    [one] 1 octet restant
+   *[other] { $count } octets restants
+}
+bytes-over = { $count ->
+    [one] 1 octet en trop
+   *[other] { $count } octets en trop
+}
+message-too-long = Les messages font au plus { $max } octets, celui-ci en fait { $len }
+pin = Épingler
+unpin = Désépingler
+away-label = { $name } (absent)
+bot-label = { $name } (bot)
+earlier-messages = Les messages précédents datent de votre dernière visite
+new-messages = Nouveaux messages
+seen-by = Vu par { $names }
+already-here = { $count ->
+    [one] { $names } est déjà là
+   *[other] { $names } sont déjà là
+}
+users-joined = { $count ->
+    [one] { $names } a rejoint le serveur
+   *[other] { $names } ont rejoint le serveur
+}
+users-left = { $count ->
+    [one] { $names } a quitté le serveur
+   *[other] { $names } ont quitté le serveur
+}
+user-renamed = { $old } s'appelle désormais { $new }
+user-away = { $name } est absent
+user-away-message = { $name } est absent : { $message }
+user-back = { $name } est de retour
+muted = { $seconds ->
+    [one] Vous êtes réduit au silence pendant une seconde pour flood
+   *[other] Vous êtes réduit au silence pendant { $seconds } secondes pour flood
+}
+rate-limited = Vous envoyez des messages trop vite, votre dernier message a été ignoré
+refused-full = Le serveur est plein, réessayez plus tard
+refused-name-taken = Quelqu'un en ligne porte déjà ce nom
+refused = Le serveur a refusé la connexion
+who = { $count } en ligne : { $names }
+open-link-failed = Impossible d'ouvrir { $url } : { $error }
+export-failed = Impossible d'exporter la conversation : { $error }
+send-failed = Impossible d'envoyer au serveur : { $error }
+disconnected = Déconnecté du serveur
+show-rules = Afficher les règles
+hide-rules = Masquer les règles
+show-pinned = Afficher les messages épinglés ({ $count })
+hide-pinned = Masquer les messages épinglés ({ $count })
+
+## commandes
+command-usage = Utilisation : { $usage }
+name-too-long = Les noms font de 1 à { $max } octets
+action-too-long = Les actions font au plus { $max } octets
+away-too-long = Les messages d'absence font au plus { $max } octets
+unknown-command = Commande /{ $name } inconnue, tapez /help pour la liste
+not-online = Personne du nom de { $name } n'est en ligne
+help-nick = changer de nom
+help-me = dire ce que vous faites
+help-msg = envoyer un message que seul USER voit
+help-join = partir pour un autre serveur
+help-leave = revenir à la liste des serveurs
+help-clear = vider la conversation
+help-away = vous marquer absent, ou de retour si vous l'êtes
+help-who = lister les utilisateurs en ligne
+help-seen = afficher ou masquer qui a lu jusqu'où
+help-rules = afficher les règles du serveur
+help-help = lister les commandes
+help-quit = partir et fermer le client
+
+## recherche
+search-empty = Tapez des mots à rechercher
+search-too-long = Les recherches font au plus { $max } octets
+search-name-too-long = Les noms d'utilisateur font au plus { $max } octets
+invalid-date = Date attendue au format 2021-12-31, trouvé { $date }
+searching = Recherche...
+search-results = { $total ->
+    [one] 1 résultat
+   *[other] { $total } résultats
+}
+search-most-recent = Les { $shown } plus récents sur { $total } résultats
+not-in-history = Ce message n'est pas dans l'historique local, seul le résultat de la recherche est disponible
+close = Fermer
+
+## transferts
+download = Télécharger
+cancel = Annuler
+save = Enregistrer
+transfer-cancelled = Transfert annulé
+file-unreadable = Impossible de lire { $path } : { $error }
+file-too-large = { $path } fait { $size } octets, les fichiers font au plus { $max }
+save-failed = Impossible d'enregistrer { $path } : { $error }
+
+## notifications
+notify-private = { $name } (privé)
+notify-mention = { $name } vous a mentionné
+
+## paramètres
+settings = Paramètres
+theme = Thème
+reload-themes = Recharger les thèmes
+text-size = Taille du texte
+language = Langue
+language-system = Système ({ $language })
+notifications = Notifications
+notifications-on = Mentions et messages privés
+notifications-off = Désactivées
+back = Retour
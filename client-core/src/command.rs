@@ -0,0 +1,206 @@
+use crate::i18n::Translator;
+use crate::state::ChatState;
+use protocol::network::{NetworkMessage, MAX_AWAY_LEN, MAX_MESSAGE_LEN, MAX_NAME_LEN};
+
+// sent as a plain message, clients show the rest as something the sender does
+pub const ACTION_PREFIX: &str = "/me ";
+
+// (name, arguments, id of what it does in the catalogs), in the order /help lists them
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    ("nick", "NAME", "help-nick"),
+    ("me", "ACTION", "help-me"),
+    ("msg", "USER TEXT", "help-msg"),
+    ("join", "HOST:PORT", "help-join"),
+    ("leave", "", "help-leave"),
+    ("clear", "", "help-clear"),
+    ("away", "[MESSAGE]", "help-away"),
+    ("who", "", "help-who"),
+    ("seen", "", "help-seen"),
+    ("rules", "", "help-rules"),
+    ("help", "", "help-help"),
+    ("quit", "", "help-quit"),
+];
+
+// why a command was not run, the client words it in its language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    // name of the command that was missing arguments
+    Usage(&'static str),
+    NameTooLong,
+    ActionTooLong,
+    AwayTooLong,
+    // length of the message
+    MessageTooLong(usize),
+    Unknown(String),
+    NotOnline(String),
+}
+
+impl CommandError {
+    pub fn message(&self, tr: &Translator) -> String {
+        match self {
+            Self::Usage(name) => {
+                let usage = COMMANDS.iter()
+                    .find(|(command, ..)| command == name)
+                    .map(|(command, usage, _)| format!("/{} {}", command, usage))
+                    .unwrap_or_default();
+
+                tr.format("command-usage", [("usage", usage.into())])
+            }
+            Self::NameTooLong => tr.format("name-too-long", [("max", MAX_NAME_LEN.into())]),
+            Self::ActionTooLong => tr.format("action-too-long", [("max", (MAX_MESSAGE_LEN - ACTION_PREFIX.len()).into())]),
+            Self::AwayTooLong => tr.format("away-too-long", [("max", MAX_AWAY_LEN.into())]),
+            Self::MessageTooLong(len) => tr.format("message-too-long", [("max", MAX_MESSAGE_LEN.into()), ("len", (*len).into())]),
+            Self::Unknown(name) => tr.format("unknown-command", [("name", name.as_str().into())]),
+            Self::NotOnline(name) => tr.format("not-online", [("name", name.as_str().into())]),
+        }
+    }
+}
+
+// typed as `/name args` in the chat input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlashCommand {
+    Nick(String),
+    Me(String),
+    // user and text, told apart once the names online are known
+    Msg(String),
+    Join(String),
+    Leave,
+    Clear,
+    Away(String),
+    Who,
+    Seen,
+    Rules,
+    Help,
+    Quit,
+}
+
+impl SlashCommand {
+    // None for anything that is not a command, to be sent as a message
+    pub fn parse(input: &str) -> Option<Result<Self, CommandError>> {
+        let input = input.trim().strip_prefix('/')?;
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let (name, args) = (name.to_lowercase(), args.trim());
+
+        Some(match (name.as_str(), args) {
+            ("nick", "") => Err(CommandError::Usage("nick")),
+            ("me", "") => Err(CommandError::Usage("me")),
+            ("msg", "") => Err(CommandError::Usage("msg")),
+            ("join", "") => Err(CommandError::Usage("join")),
+            ("nick", name) if name.len() > MAX_NAME_LEN => Err(CommandError::NameTooLong),
+            ("nick", name) => Ok(Self::Nick(name.to_owned())),
+            ("me", action) if ACTION_PREFIX.len() + action.len() > MAX_MESSAGE_LEN => Err(CommandError::ActionTooLong),
+            ("me", action) => Ok(Self::Me(action.to_owned())),
+            ("msg", args) => Ok(Self::Msg(args.to_owned())),
+            ("join", server) => Ok(Self::Join(server.to_owned())),
+            ("away", message) if message.len() > MAX_AWAY_LEN => Err(CommandError::AwayTooLong),
+            ("away", message) => Ok(Self::Away(message.to_owned())),
+            // the TUI used to go back with /servers
+            ("leave" | "servers", _) => Ok(Self::Leave),
+            ("clear", _) => Ok(Self::Clear),
+            ("who", _) => Ok(Self::Who),
+            ("seen", _) => Ok(Self::Seen),
+            ("rules", _) => Ok(Self::Rules),
+            ("help", _) => Ok(Self::Help),
+            ("quit", _) => Ok(Self::Quit),
+            _ => Err(CommandError::Unknown(name)),
+        })
+    }
+
+    // what to send the server, None when the client handles the command itself
+    pub fn message(&self, state: &ChatState) -> Result<Option<NetworkMessage>, CommandError> {
+        Ok(Some(match self {
+            Self::Nick(name) => NetworkMessage::user_rename(0, name.to_owned()),
+            Self::Me(action) => NetworkMessage::message(0, state.personal_id, format!("{}{}", ACTION_PREFIX, action), Vec::new()),
+            Self::Msg(args) => {
+                let (to, text) = recipient(args, state)?;
+
+                if text.len() > MAX_MESSAGE_LEN {
+                    return Err(CommandError::MessageTooLong(text.len()));
+                }
+
+                NetworkMessage::private_message(state.personal_id, to, text.to_owned())
+            }
+            // a second /away without a message comes back
+            Self::Away(message) if message.is_empty() && state.is_away(state.personal_id) => NetworkMessage::away(0, false, String::new()),
+            Self::Away(message) => NetworkMessage::away(0, true, message.to_owned()),
+            _ => return Ok(None),
+        }))
+    }
+}
+
+// names may contain spaces, the longest one the arguments start with wins
+fn recipient<'a>(args: &'a str, state: &ChatState) -> Result<(u32, &'a str), CommandError> {
+    let found = state.users.iter()
+        .filter_map(|(id, name)| {
+            let text = args.get(name.len()..)?;
+            let prefix = &args[..name.len()];
+
+            match prefix.eq_ignore_ascii_case(name) && text.starts_with(char::is_whitespace) {
+                true => Some((name.len(), *id, text.trim())),
+                false => None,
+            }
+        })
+        .max_by_key(|(len, ..)| *len);
+
+    match found {
+        Some((_, id, text)) if !text.is_empty() => Ok((id, text)),
+        Some(_) => Err(CommandError::Usage("msg")),
+        None => {
+            let name = args.split_whitespace().next().unwrap_or_default();
+            Err(CommandError::NotOnline(name.to_owned()))
+        }
+    }
+}
+
+// the message without its prefix when it is an action
+pub fn action(content: &str) -> Option<&str> {
+    content.strip_prefix(ACTION_PREFIX)
+}
+
+// completes the word before the end of `input`: command names after a lone slash, user names anywhere else
+// mentions keep their @
+// returns the new input and, when several matched, what they were
+pub fn complete(input: &str, state: &ChatState) -> (String, Vec<String>) {
+    let start = input.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    let (head, word) = input.split_at(start);
+
+    let candidates: Vec<String> = match (head.is_empty(), word.strip_prefix('/')) {
+        (true, Some(command)) => COMMANDS.iter()
+            .filter(|(name, ..)| name.starts_with(&command.to_lowercase()))
+            .map(|(name, ..)| format!("/{}", name))
+            .collect(),
+        _ if word.is_empty() => Vec::new(),
+        _ => {
+            let (at, word) = match word.strip_prefix('@') {
+                Some(word) => ("@", word),
+                None => ("", word),
+            };
+
+            let mut names: Vec<_> = state.users.values()
+                .filter(|name| name.to_lowercase().starts_with(&word.to_lowercase()))
+                .map(|name| format!("{}{}", at, name))
+                .collect();
+
+            names.sort_by_key(|name| name.to_lowercase());
+            names.dedup();
+            names
+        }
+    };
+
+    match candidates.as_slice() {
+        [] => (input.to_owned(), candidates),
+        [only] => (format!("{}{} ", head, only), Vec::new()),
+        [first, rest @ ..] => {
+            // as far as they all agree, case aside
+            let common = rest.iter().fold(first.chars().count(), |len, other| {
+                first.chars().zip(other.chars()).take(len).take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase())).count()
+            });
+            let common: String = first.chars().take(common).collect();
+
+            match common.chars().count() > word.chars().count() {
+                true => (format!("{}{}", head, common), candidates),
+                false => (input.to_owned(), candidates),
+            }
+        }
+    }
+}
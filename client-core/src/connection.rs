@@ -0,0 +1,134 @@
+use futures::channel::mpsc;
+use futures::{FutureExt, Stream, StreamExt};
+use protocol::network::{FrameReader, NetworkMessage, MAX_FRAME_LEN, SECRET_LEN};
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// host:port as typed, names are looked up
+pub fn resolve(addr: &str) -> Result<SocketAddr, String> {
+    let mut addrs = addr.to_socket_addrs().map_err(|err| format!("{}: {}", addr, err))?;
+
+    addrs.next().ok_or_else(|| format!("{}: no address found", addr))
+}
+
+// messages of a connection, a stream for async code that others can poll with `ready`
+pub struct Events(mpsc::UnboundedReceiver<Result<NetworkMessage, String>>);
+
+impl Events {
+    // without waiting: None until something arrives, Some(None) once the connection is closed
+    pub fn ready(&mut self) -> Option<Option<Result<NetworkMessage, String>>> {
+        self.0.next().now_or_never()
+    }
+}
+
+impl Stream for Events {
+    type Item = Result<NetworkMessage, String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
+// joined server, every handle shares the same socket
+#[derive(Debug)]
+pub struct Connection {
+    socket: TcpStream,
+}
+
+impl Connection {
+    // the server answers our identity with PersonalId then the user list
+    // `secret` comes from `identity::load`, the same one every time
+    pub fn open(addr: &SocketAddr, username: &str, bot: bool, secret: [u8; SECRET_LEN]) -> std::io::Result<Self> {
+        let identity = NetworkMessage::client_identity(username.to_owned(), bot, secret)
+            .into_vec()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+
+        let mut socket = TcpStream::connect_timeout(addr, CONNECT_TIMEOUT)?;
+        socket.write_all(&identity)?;
+
+        Ok(Self { socket })
+    }
+
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        self.socket.try_clone().map(|socket| Self { socket })
+    }
+
+    pub fn send(&self, msg: NetworkMessage) -> Result<(), String> {
+        let buf = msg.into_vec()?;
+        (&self.socket).write_all(&buf).map_err(|err| err.to_string())
+    }
+
+    // blocks on every read, see `events` to wait without blocking
+    pub fn incoming(&self) -> std::io::Result<Incoming> {
+        self.socket.try_clone().map(Incoming::new)
+    }
+
+    // read on a thread of its own, the stream ends with the connection
+    pub fn events(&self) -> std::io::Result<Events> {
+        let incoming = self.incoming()?;
+        let (sender, receiver) = mpsc::unbounded();
+
+        std::thread::spawn(move || {
+            for msg in incoming {
+                if sender.unbounded_send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Events(receiver))
+    }
+
+    // for every handle, and unblocks the thread reading events
+    pub fn close(&self) {
+        let _ = self.socket.shutdown(Shutdown::Both);
+    }
+}
+
+// messages read from the server until it closes the connection or sends garbage
+pub struct Incoming {
+    stream: TcpStream,
+    reader: FrameReader,
+    closed: bool,
+}
+
+impl Incoming {
+    pub fn new(stream: TcpStream) -> Self {
+        Self { stream, reader: FrameReader::new(), closed: false }
+    }
+}
+
+impl Iterator for Incoming {
+    type Item = Result<NetworkMessage, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0; MAX_FRAME_LEN];
+
+        while !self.closed {
+            match self.reader.next_message() {
+                Ok(Some(msg)) => return Some(Ok(msg)),
+                Ok(None) => (),
+                Err(err) => {
+                    self.closed = true;
+                    return Some(Err(err));
+                }
+            }
+
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.closed = true,
+                Ok(len) => self.reader.extend(&buf[..len]),
+                Err(err) => {
+                    self.closed = true;
+                    return Some(Err(err.to_string()));
+                }
+            }
+        }
+
+        None
+    }
+}
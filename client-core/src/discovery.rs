@@ -0,0 +1,204 @@
+use protocol::multicast::{Announcement, Discovery, MulticastMessage};
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// servers not heard from for this long are dropped from the list
+pub const SERVER_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    Announced(Announcement, SocketAddr),
+    Goodbye(Announcement, SocketAddr),
+    // lets the list expire silent servers
+    Tick,
+    // a group that could not be joined or pinged, or an announcement that could not be read
+    Failed(String),
+}
+
+// pings the groups then listens for announcements until the receiver is dropped
+pub fn browse(discovery: Discovery) -> Receiver<ServerEvent> {
+    let (sender, receiver) = mpsc::channel();
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    // joined to the groups for unsolicited announcements
+    let mut sockets: Vec<_> = [discovery.listen(), discovery.listen_v6()]
+        .into_iter()
+        .filter_map(|socket| socket.map_err(|err| {
+            let _ = sender.send(ServerEvent::Failed(format!("Discovery: {}", err)));
+        }).ok())
+        .collect();
+
+    // replies to our pings come back on the probing sockets
+    sockets.extend(ping(&discovery, &sender));
+
+    for socket in sockets {
+        let sender = sender.clone();
+        let stopped = stopped.clone();
+
+        std::thread::spawn(move || {
+            socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+            let mut buf = [0; 1024];
+            // newer servers answer both pings, their old layout reply says less than the other one
+            let mut extended = HashSet::new();
+
+            while !stopped.load(Ordering::Relaxed) {
+                let (buf_len, addr) = match socket.recv_from(&mut buf) {
+                    Ok(infos) => infos,
+                    Err(_) => continue,
+                };
+
+                let message: Result<MulticastMessage, _> = buf[..buf_len].try_into();
+
+                let event = match message {
+                    Err(err) => ServerEvent::Failed(format!("{}: {:?}", addr, err)),
+                    Ok(message) => match message.content() {
+                        Some(_) if message.is_legacy() && extended.contains(&addr) => continue,
+                        Some(announcement) if message.is_goodbye() => {
+                            ServerEvent::Goodbye(announcement.clone(), endpoint(announcement, addr))
+                        }
+                        Some(announcement) => {
+                            if !message.is_legacy() {
+                                extended.insert(addr);
+                            }
+
+                            ServerEvent::Announced(announcement.clone(), endpoint(announcement, addr))
+                        }
+                        // other clients pinging
+                        None => continue,
+                    },
+                };
+
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // also notices the receiver is gone when nothing is announced
+    std::thread::spawn(move || {
+        while sender.send(ServerEvent::Tick).is_ok() {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+
+        stopped.store(true, Ordering::Relaxed);
+    });
+
+    receiver
+}
+
+pub fn update_server_list(servers: &mut Vec<(Announcement, SocketAddr, Instant)>, event: ServerEvent) {
+    // same server heard on several interfaces or address families
+    let position = |servers: &[(Announcement, SocketAddr, Instant)], announcement: &Announcement, addr: &SocketAddr| {
+        servers.iter().position(|(known, known_addr, _)| match (known.server_id, announcement.server_id) {
+            (Some(known), Some(server_id)) => known == server_id,
+            _ => known_addr == addr,
+        })
+    };
+
+    match event {
+        ServerEvent::Announced(announcement, addr) => {
+            match position(servers, &announcement, &addr) {
+                Some(index) => servers[index] = (announcement, addr, Instant::now()),
+                None => servers.push((announcement, addr, Instant::now())),
+            }
+
+            sort_servers(servers);
+        }
+        ServerEvent::Goodbye(announcement, addr) => {
+            if let Some(index) = position(servers, &announcement, &addr) {
+                servers.remove(index);
+            }
+        }
+        ServerEvent::Tick => servers.retain(|(.., seen)| seen.elapsed() < SERVER_TIMEOUT),
+        ServerEvent::Failed(_) => (),
+    }
+}
+
+fn sort_servers(servers: &mut [(Announcement, SocketAddr, Instant)]) {
+    servers.sort_by(|(lhs, ..), (rhs, ..)| {
+        let mut lhs = lhs.name.chars();
+        let mut rhs = rhs.name.chars();
+
+        loop {
+            let cple = (lhs.next(), rhs.next());
+
+            match cple {
+                (Some(l), Some(r)) => {
+                    let ord = l.to_lowercase().cmp(r.to_lowercase());
+                    if !ord.is_eq() {
+                        break ord;
+                    }
+                }
+                (Some(_), None) => break std::cmp::Ordering::Greater,
+                (None, Some(_)) => break std::cmp::Ordering::Less,
+                (None, None) => break std::cmp::Ordering::Equal,
+            }
+        }
+    });
+}
+
+// address to connect to for an announcement received from `addr`
+fn endpoint(announcement: &Announcement, mut addr: SocketAddr) -> SocketAddr {
+    addr.set_port(announcement.port);
+
+    // an advertised endpoint of the same family beats the replying address
+    match announcement.endpoints.iter().find(|endpoint| endpoint.is_ipv6() == addr.is_ipv6()) {
+        Some(endpoint) => *endpoint,
+        None => addr,
+    }
+}
+
+// ping the groups from every interface, unless one was configured, what fails is reported to `sender`
+fn ping(discovery: &Discovery, sender: &Sender<ServerEvent>) -> Vec<UdpSocket> {
+    let mut interfaces: Vec<Ipv4Addr> = match discovery.interface.is_unspecified() {
+        false => vec![discovery.interface],
+        true => if_addrs::get_if_addrs()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|interface| !interface.is_loopback())
+            .filter_map(|interface| match interface.ip() {
+                IpAddr::V4(ip) => Some(ip),
+                IpAddr::V6(_) => None,
+            })
+            .collect(),
+    };
+
+    // let the system pick a route when no interface could be listed
+    if interfaces.is_empty() {
+        interfaces.push(Ipv4Addr::UNSPECIFIED);
+    }
+
+    // servers from before the extended layout only answer the first kind, newer ones answer the extended one first
+    let pings: [Vec<u8>; 2] = [MulticastMessage::extended_ping().into(), MulticastMessage::ping().into()];
+    let send = |socket: UdpSocket, group: SocketAddr| {
+        pings.iter().try_for_each(|ping| socket.send_to(ping, group).map(|_| ())).map(|_| socket)
+    };
+
+    let mut sockets: Vec<_> = interfaces.into_iter().filter_map(|interface| {
+        let socket = discovery.probe(interface).and_then(|socket| send(socket, discovery.group_addr()));
+
+        match socket {
+            Ok(socket) => Some(socket),
+            Err(err) => {
+                let _ = sender.send(ServerEvent::Failed(format!("{}: {}", interface, err)));
+                None
+            }
+        }
+    }).collect();
+
+    let socket = discovery.probe_v6().and_then(|socket| send(socket, discovery.group_addr_v6()));
+
+    match socket {
+        Ok(socket) => sockets.push(socket),
+        Err(err) => {
+            let _ = sender.send(ServerEvent::Failed(format!("{}: {}", discovery.group_addr_v6(), err)));
+        }
+    }
+
+    sockets
+}
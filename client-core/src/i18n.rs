@@ -0,0 +1,113 @@
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue;
+
+// catalogs built into every client: (language, its name in that language, Fluent source)
+pub const LOCALES: &[(&str, &str, &str)] = &[
+    ("en", "English", include_str!("../locales/en.ftl")),
+    ("fr", "Français", include_str!("../locales/fr.ftl")),
+];
+
+// what a catalog lacks comes from this one
+const FALLBACK: &str = "en";
+
+// names listed before the rest are only counted
+const MAX_LISTED: usize = 3;
+
+// the system's language when there is a catalog for it
+pub fn detect() -> &'static str {
+    sys_locale::get_locale().map_or(FALLBACK, |locale| supported(&locale))
+}
+
+// the catalog for `locale`, as in "fr", "fr-CA" or "fr_CA.UTF-8"
+pub fn supported(locale: &str) -> &'static str {
+    let language = locale.split(['-', '_', '.', '@']).next().unwrap_or_default();
+
+    LOCALES.iter()
+        .find(|(code, ..)| code.eq_ignore_ascii_case(language))
+        .map_or(FALLBACK, |(code, ..)| code)
+}
+
+// the name a language goes by in itself
+pub fn name(locale: &str) -> &'static str {
+    LOCALES.iter()
+        .find(|(code, ..)| *code == supported(locale))
+        .map_or(FALLBACK, |(_, name, _)| name)
+}
+
+pub struct Translator {
+    locale: &'static str,
+    // the locale's catalog, then the fallback
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Translator {
+    pub fn new(locale: &str) -> Self {
+        let locale = supported(locale);
+
+        let bundles = LOCALES.iter()
+            .filter(|(code, ..)| *code == locale)
+            .chain(LOCALES.iter().filter(|(code, ..)| *code == FALLBACK && *code != locale))
+            .map(|(code, _, source)| bundle(code, source))
+            .collect();
+
+        Self { locale, bundles }
+    }
+
+    pub fn locale(&self) -> &'static str {
+        self.locale
+    }
+
+    pub fn tr(&self, id: &str) -> String {
+        self.format(id, [])
+    }
+
+    // `args` fill the message's placeables, numbers among them also pick its plural form
+    pub fn format<'a>(&self, id: &str, args: impl IntoIterator<Item = (&'a str, FluentValue<'a>)>) -> String {
+        let args: FluentArgs = args.into_iter().collect();
+
+        for bundle in &self.bundles {
+            if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+                let mut errors = Vec::new();
+                return bundle.format_pattern(pattern, Some(&args), &mut errors).into_owned();
+            }
+        }
+
+        // a missing string shows as its id rather than as nothing
+        id.to_owned()
+    }
+
+    // "a", "a and b", "a, b and c", "a, b, c and 2 others"
+    pub fn list(&self, names: &[&str]) -> String {
+        let (init, last) = match names {
+            [] => return String::new(),
+            [name] => return name.to_string(),
+            names if names.len() > MAX_LISTED => (
+                names[..MAX_LISTED].join(", "),
+                self.format("others", [("count", (names.len() - MAX_LISTED).into())]),
+            ),
+            [init @ .., last] => (init.join(", "), last.to_string()),
+        };
+
+        self.format("list-and", [("init", init.into()), ("last", last.into())])
+    }
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Self::new(FALLBACK)
+    }
+}
+
+fn bundle(code: &str, source: &str) -> FluentBundle<FluentResource> {
+    let resource = FluentResource::try_new(source.to_owned()).expect("built-in catalogs are valid Fluent");
+    let locale: LanguageIdentifier = code.parse().expect("built-in catalogs have valid language codes");
+
+    let mut bundle = FluentBundle::new(vec![locale]);
+    // the marks around arguments are meant for bidirectional text, clients would draw them
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).expect("built-in catalogs define each message once");
+
+    bundle
+}
@@ -0,0 +1,37 @@
+use protocol::file;
+use protocol::network::SECRET_LEN;
+use rand::Rng;
+use std::fs;
+use std::path::Path;
+
+// the secret sent when joining, kept as `messaging/<name>` in the config directory
+// one that can't be read or kept only lasts until the client quits, the reason comes with it
+pub fn load(name: &str) -> ([u8; SECRET_LEN], Option<String>) {
+    let path = match dirs_next::config_dir() {
+        Some(dir) => dir.join("messaging").join(name),
+        None => return (rand::thread_rng().gen(), Some(String::from("No config directory, servers won't recognize this client next time"))),
+    };
+
+    match load_from(&path) {
+        Ok(secret) => (secret, None),
+        Err(err) => (rand::thread_rng().gen(), Some(err)),
+    }
+}
+
+// hexadecimal, made up the first time
+pub fn load_from(path: &Path) -> Result<[u8; SECRET_LEN], String> {
+    match fs::read_to_string(path) {
+        Ok(hex) => file::from_hex(hex.trim()).ok_or_else(|| format!("{}: expected {} hexadecimal digits", path.display(), SECRET_LEN * 2)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let secret: [u8; SECRET_LEN] = rand::thread_rng().gen();
+
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, file::to_hex(&secret)))
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+
+            Ok(secret)
+        }
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
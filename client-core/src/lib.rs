@@ -0,0 +1,434 @@
+// what every client needs whatever draws it: finding servers and talking to them
+pub mod command;
+pub mod connection;
+pub mod discovery;
+pub mod i18n;
+pub mod identity;
+pub mod markdown;
+pub mod notify;
+pub mod state;
+pub mod theme;
+
+#[cfg(test)]
+mod chat_state {
+    use crate::state::{ChatState, Update};
+    use protocol::network::NetworkMessage;
+
+    #[test]
+    fn names_senders() {
+        let mut state = ChatState::new(String::from("alice"));
+
+        state.receive(NetworkMessage::personal_id(1, false));
+        state.receive(NetworkMessage::user_list(vec![(2, String::from("bob"), false)]));
+        state.receive(NetworkMessage::message(10, 1, String::from("hi"), Vec::new()));
+        state.receive(NetworkMessage::message(11, 2, String::from("hello"), Vec::new()));
+
+        let from: Vec<_> = state.messages.iter().map(|(_, from)| from.as_str()).collect();
+        assert_eq!(from, ["", "alice", "bob"]);
+    }
+
+    #[test]
+    fn merges_user_list_chunks() {
+        let mut state = ChatState::new(String::from("alice"));
+
+        let first = state.receive(NetworkMessage::user_list_chunk(vec![(2, String::from("bob"), false)], true));
+        assert!(matches!(first, Update::Changed));
+        assert!(state.users.is_empty());
+
+        let last = state.receive(NetworkMessage::user_list_chunk(vec![(3, String::from("carol"), true)], false));
+        assert!(matches!(last, Update::Logged));
+        assert_eq!(state.users.len(), 2);
+        assert!(state.is_bot(3));
+        assert_eq!(state.messages.len(), 1);
+    }
+
+    #[test]
+    fn tracks_joins_and_leaves() {
+        let mut state = ChatState::new(String::from("alice"));
+
+        state.receive(NetworkMessage::user_join(String::from("bob"), 2, false));
+        assert_eq!(state.name(2), Some("bob"));
+
+        state.receive(NetworkMessage::user_leave(2));
+        assert_eq!(state.name(2), None);
+        assert_eq!(state.messages.last().map(|(_, from)| from.as_str()), Some("bob"));
+    }
+
+    #[test]
+    fn leaves_the_rest_to_the_client() {
+        let mut state = ChatState::new(String::from("alice"));
+
+        assert!(matches!(state.receive(NetworkMessage::unpin(4)), Update::Unhandled(_)));
+        assert!(state.messages.is_empty());
+    }
+
+    #[test]
+    fn follows_renames_and_away() {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+        state.receive(NetworkMessage::user_join(String::from("bob"), 2, false));
+
+        state.receive(NetworkMessage::user_rename(2, String::from("robert")));
+        assert_eq!(state.name(2), Some("robert"));
+        assert_eq!(state.messages.last().map(|(_, from)| from.as_str()), Some("bob"));
+
+        state.receive(NetworkMessage::user_rename(1, String::from("alicia")));
+        assert_eq!(state.username, "alicia");
+
+        state.receive(NetworkMessage::away(2, true, String::from("lunch")));
+        assert_eq!(state.away.get(&2).map(String::as_str), Some("lunch"));
+
+        state.receive(NetworkMessage::away(2, false, String::new()));
+        assert!(!state.is_away(2));
+    }
+}
+
+#[cfg(test)]
+mod read_markers {
+    use crate::state::ChatState;
+    use protocol::network::NetworkMessage;
+
+    #[test]
+    fn counts_what_came_in_unseen() {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+        state.receive(NetworkMessage::user_list(vec![(2, String::from("bob"), false), (3, String::from("carol"), false)]));
+        state.receive(NetworkMessage::message(10, 2, String::from("hi"), Vec::new()));
+
+        assert_eq!(state.set_reading(true), Some(NetworkMessage::read_marker(0, 10)));
+        assert_eq!(state.set_reading(true), None);
+        assert_eq!(state.set_reading(false), None);
+
+        state.receive(NetworkMessage::message(11, 2, String::from("still there?"), Vec::new()));
+        state.receive(NetworkMessage::message(12, 3, String::from("yes"), Vec::new()));
+        state.receive(NetworkMessage::private_message(2, 1, String::from("psst")));
+
+        assert_eq!(state.unread(), 2);
+        assert_eq!(state.divider, Some(2));
+        assert_eq!(state.unread_private.get(&2), Some(&1));
+
+        assert_eq!(state.set_reading(true), Some(NetworkMessage::read_marker(0, 12)));
+        assert_eq!(state.unread(), 0);
+        assert!(state.unread_private.is_empty());
+        assert_eq!(state.divider, Some(2));
+
+        state.receive(NetworkMessage::message(13, 1, String::from("back"), Vec::new()));
+        assert_eq!(state.divider, None);
+    }
+
+    #[test]
+    fn knows_who_read_what() {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+        state.receive(NetworkMessage::user_list(vec![(2, String::from("bob"), false), (3, String::from("carol"), false)]));
+        state.receive(NetworkMessage::read_marker(3, 10));
+        state.receive(NetworkMessage::read_marker(2, 10));
+        state.receive(NetworkMessage::read_marker(1, 10));
+        state.receive(NetworkMessage::read_marker(2, 9));
+
+        assert_eq!(state.seen_by(10), ["bob", "carol"]);
+
+        state.receive(NetworkMessage::user_leave(3));
+        assert_eq!(state.seen_by(10), ["bob"]);
+    }
+}
+
+#[cfg(test)]
+mod slash_commands {
+    use crate::command::{self, CommandError, SlashCommand, COMMANDS};
+    use crate::i18n::Translator;
+    use crate::state::ChatState;
+    use protocol::network::NetworkMessage;
+
+    fn state() -> ChatState {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+        state.receive(NetworkMessage::user_list(vec![
+            (2, String::from("bob"), false),
+            (3, String::from("bob smith"), false),
+            (4, String::from("Boris"), true),
+        ]));
+
+        state
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(SlashCommand::parse("hello"), None);
+        assert_eq!(SlashCommand::parse("/NICK  carol "), Some(Ok(SlashCommand::Nick(String::from("carol")))));
+        assert_eq!(SlashCommand::parse("/servers"), Some(Ok(SlashCommand::Leave)));
+        assert_eq!(SlashCommand::parse("/away"), Some(Ok(SlashCommand::Away(String::new()))));
+        assert_eq!(SlashCommand::parse("/nick"), Some(Err(CommandError::Usage("nick"))));
+        assert_eq!(SlashCommand::parse("/dance"), Some(Err(CommandError::Unknown(String::from("dance")))));
+    }
+
+    #[test]
+    fn words_errors_in_the_language() {
+        let en = Translator::new("en");
+        let fr = Translator::new("fr");
+
+        assert_eq!(CommandError::Usage("nick").message(&en), "Usage: /nick NAME");
+        assert_eq!(CommandError::NotOnline(String::from("carol")).message(&fr), "Personne du nom de carol n'est en ligne");
+
+        for (_, _, what) in COMMANDS {
+            assert_ne!(en.tr(what), *what);
+            assert_ne!(fr.tr(what), *what);
+        }
+    }
+
+    #[test]
+    fn maps_to_network_messages() {
+        let mut state = state();
+        let message = |input: &str, state: &ChatState| SlashCommand::parse(input).unwrap().unwrap().message(state);
+
+        assert_eq!(message("/me waves", &state), Ok(Some(NetworkMessage::message(0, 1, String::from("/me waves"), Vec::new()))));
+        assert_eq!(message("/msg bob smith hi there", &state), Ok(Some(NetworkMessage::private_message(1, 3, String::from("hi there")))));
+        assert_eq!(message("/msg BOB hi", &state), Ok(Some(NetworkMessage::private_message(1, 2, String::from("hi")))));
+        assert_eq!(message("/msg carol hi", &state), Err(CommandError::NotOnline(String::from("carol"))));
+        assert!(message("/msg bob", &state).is_err());
+        assert_eq!(message("/who", &state), Ok(None));
+
+        assert_eq!(message("/away", &state), Ok(Some(NetworkMessage::away(0, true, String::new()))));
+        state.receive(NetworkMessage::away(1, true, String::new()));
+        assert_eq!(message("/away", &state), Ok(Some(NetworkMessage::away(0, false, String::new()))));
+
+        assert_eq!(command::action("/me waves"), Some("waves"));
+    }
+
+    #[test]
+    fn completes_commands_and_names() {
+        let state = state();
+
+        assert_eq!(command::complete("/ni", &state), (String::from("/nick "), vec![]));
+        assert_eq!(command::complete("/msg bor", &state), (String::from("/msg Boris "), vec![]));
+        assert_eq!(command::complete("hi b", &state), (String::from("hi bo"), vec![
+            String::from("bob"),
+            String::from("bob smith"),
+            String::from("Boris"),
+        ]));
+        assert_eq!(command::complete("hi zed", &state), (String::from("hi zed"), vec![]));
+        assert_eq!(command::complete("thanks @bor", &state), (String::from("thanks @Boris "), vec![]));
+        assert_eq!(command::complete("@bob", &state).1, [String::from("@bob"), String::from("@bob smith")]);
+    }
+}
+
+#[cfg(test)]
+mod notifications {
+    use crate::i18n::Translator;
+    use crate::notify::{self, Notifier, Silent};
+    use crate::state::ChatState;
+    use protocol::network::NetworkMessage;
+    use std::cell::RefCell;
+
+    // keeps what it was asked to show
+    #[derive(Default)]
+    struct Shown(RefCell<Vec<(String, String)>>);
+
+    impl Notifier for Shown {
+        fn notify(&self, title: &str, body: &str) {
+            self.0.borrow_mut().push((title.to_owned(), body.to_owned()));
+        }
+    }
+
+    #[test]
+    fn mentions_and_private_messages_only() {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+
+        let mentioned = NetworkMessage::message(10, 2, String::from("hi @alice"), vec![1]);
+        let others = NetworkMessage::message(11, 2, String::from("hi @carol"), vec![3]);
+        let ours = NetworkMessage::message(12, 1, String::from("hi @alice"), vec![1]);
+        let private = NetworkMessage::private_message(2, 1, String::from("psst"));
+        let sent = NetworkMessage::private_message(1, 2, String::from("psst"));
+
+        let tr = Translator::default();
+
+        assert!(notify::message(&Silent, &tr, &mentioned, "bob", &state, false));
+        assert!(notify::message(&Silent, &tr, &private, "bob", &state, false));
+        assert!(!notify::message(&Silent, &tr, &mentioned, "bob", &state, true));
+        assert!(!notify::message(&Silent, &tr, &others, "bob", &state, false));
+        assert!(!notify::message(&Silent, &tr, &ours, "alice", &state, false));
+        assert!(!notify::message(&Silent, &tr, &sent, "alice", &state, false));
+    }
+
+    #[test]
+    fn titles_in_the_language() {
+        let mut state = ChatState::new(String::from("alice"));
+        state.receive(NetworkMessage::personal_id(1, false));
+
+        let mentioned = NetworkMessage::message(10, 2, String::from("hi @alice"), vec![1]);
+        let private = NetworkMessage::private_message(2, 1, String::from("psst"));
+        let shown = Shown::default();
+
+        notify::message(&shown, &Translator::default(), &private, "bob", &state, false);
+        notify::message(&shown, &Translator::new("fr"), &mentioned, "bob", &state, false);
+
+        assert_eq!(*shown.0.borrow(), [
+            (String::from("bob (private)"), String::from("psst")),
+            (String::from("bob vous a mentionné"), String::from("hi @alice")),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod rich_text {
+    use crate::markdown::{self, Block, Span, Style};
+
+    fn span(text: &str, style: Style) -> Span {
+        Span { text: String::from(text), style, link: None }
+    }
+
+    const BOLD: Style = Style { bold: true, italic: false, code: false };
+    const ITALIC: Style = Style { bold: false, italic: true, code: false };
+    const CODE: Style = Style { bold: false, italic: false, code: true };
+    const PLAIN: Style = Style { bold: false, italic: false, code: false };
+
+    #[test]
+    fn emphasis_and_code() {
+        assert_eq!(markdown::parse("a **b** *c `d*`* snake_case_name"), [Block::Paragraph(vec![
+            span("a ", PLAIN),
+            span("b", BOLD),
+            span(" ", PLAIN),
+            span("c ", ITALIC),
+            span("d*", Style { italic: true, ..CODE }),
+            span(" snake_case_name", PLAIN),
+        ])]);
+
+        // nothing to close them, left as typed
+        assert_eq!(markdown::parse("2 * 3 = **6 \\*x\\*"), [Block::Paragraph(vec![span("2 * 3 = **6 *x*", PLAIN)])]);
+    }
+
+    #[test]
+    fn safe_links_only() {
+        let link = |text: &str, url: &str| Span { text: String::from(text), style: PLAIN, link: Some(String::from(url)) };
+
+        assert_eq!(markdown::parse("[docs](https://example.com/a) or https://example.com."), [Block::Paragraph(vec![
+            link("docs", "https://example.com/a"),
+            span(" or ", PLAIN),
+            link("https://example.com", "https://example.com"),
+            span(".", PLAIN),
+        ])]);
+
+        assert_eq!(markdown::parse("[click](javascript:alert(1))"), [Block::Paragraph(vec![span("[click](javascript:alert(1))", PLAIN)])]);
+        assert!(!markdown::is_safe_link("file:///etc/passwd"));
+        assert!(markdown::is_safe_link("mailto:someone@example.com"));
+    }
+
+    #[test]
+    fn blocks() {
+        let blocks = markdown::parse("look:\n```rust\nfn main() {}\n```\n- one\n- two\n3. three\nafter");
+
+        assert_eq!(blocks, [
+            Block::Paragraph(vec![span("look:", PLAIN)]),
+            Block::Code(String::from("fn main() {}")),
+            Block::List { start: None, items: vec![vec![span("one", PLAIN)], vec![span("two", PLAIN)]] },
+            Block::List { start: Some(3), items: vec![vec![span("three", PLAIN)]] },
+            Block::Paragraph(vec![span("after", PLAIN)]),
+        ]);
+
+        // an unclosed block runs to the end
+        assert_eq!(markdown::parse("```\na\n\nb"), [Block::Code(String::from("a\n\nb"))]);
+    }
+}
+
+#[cfg(test)]
+mod themes {
+    use crate::theme::{self, Rgb, Theme};
+
+    #[test]
+    fn parses_colours() {
+        assert_eq!(Rgb::parse("#6FFFE9"), Some(Rgb(0x6F, 0xFF, 0xE9)));
+        assert_eq!(Rgb::parse("#6fffe9"), Some(Rgb(0x6F, 0xFF, 0xE9)));
+        assert_eq!(Rgb::parse("6FFFE9"), None);
+        assert_eq!(Rgb::parse("#+1+1+1"), None);
+        assert_eq!(Rgb::parse("#FFF"), None);
+    }
+
+    #[test]
+    fn builds_on_the_built_in_ones() {
+        let themes = theme::parse(r##"
+            [[theme]]
+            name = "Night"
+            background = "#000000"
+
+            [[theme]]
+            name = " Paper "
+            base = "light"
+            link = "#0000EE"
+        "##).unwrap();
+
+        assert_eq!(themes, [
+            Theme { name: String::from("Night"), background: Rgb(0, 0, 0), ..Theme::dark() },
+            Theme { name: String::from("Paper"), link: Rgb(0, 0, 0xEE), ..Theme::light() },
+        ]);
+
+        assert_eq!(theme::parse("").unwrap(), []);
+    }
+
+    #[test]
+    fn explains_mistakes() {
+        assert_eq!(theme::parse("[[theme]]\nbackground = \"#000000\""), Err(String::from("a theme has no name")));
+        assert_eq!(theme::parse("[[theme]]\nname = \"a\"\nbase = \"sepia\""), Err(String::from("a: no built-in theme is called sepia")));
+        assert_eq!(theme::parse("[[theme]]\nname = \"a\"\nglow = \"#000000\""), Err(String::from("a: unknown colour glow")));
+        assert_eq!(theme::parse("[[theme]]\nname = \"a\"\ntext = \"white\""), Err(String::from("a: text should look like \"#RRGGBB\"")));
+        assert!(theme::parse("[[theme]\n").is_err());
+    }
+}
+
+#[cfg(test)]
+mod localization {
+    use crate::i18n::{self, Translator};
+
+    #[test]
+    fn picks_a_catalog() {
+        assert_eq!(i18n::supported("fr_CA.UTF-8"), "fr");
+        assert_eq!(i18n::supported("FR"), "fr");
+        assert_eq!(i18n::supported("de-DE"), "en");
+        assert_eq!(i18n::name("fr-BE"), "Français");
+    }
+
+    #[test]
+    fn plural_forms() {
+        let en = Translator::new("en");
+        let fr = Translator::new("fr");
+        let joined = |tr: &Translator, names: &[&str]| tr.format("users-joined", [
+            ("names", tr.list(names).into()),
+            ("count", names.len().into()),
+        ]);
+
+        assert_eq!(joined(&en, &["bob"]), "bob joined the server");
+        assert_eq!(joined(&en, &["bob", "carol", "dave"]), "bob, carol and dave joined the server");
+        assert_eq!(joined(&fr, &["bob"]), "bob a rejoint le serveur");
+        assert_eq!(joined(&fr, &["bob", "carol"]), "bob et carol ont rejoint le serveur");
+        assert_eq!(fr.list(&["a", "b", "c", "d"]), "a, b, c et une autre personne");
+        assert_eq!(en.list(&["a", "b", "c", "d", "e"]), "a, b, c and 2 others");
+    }
+
+    #[test]
+    fn falls_back() {
+        let fr = Translator::new("fr");
+
+        assert_eq!(fr.tr("no-such-message"), "no-such-message");
+        assert_eq!(Translator::new("de").tr("login"), "Login");
+    }
+}
+
+#[cfg(test)]
+mod secrets {
+    use crate::identity;
+
+    #[test]
+    fn keeps_the_same_secret() {
+        let dir = std::env::temp_dir().join(format!("messaging-identity-{}", std::process::id()));
+        let path = dir.join("identity");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let secret = identity::load_from(&path).unwrap();
+        assert_eq!(identity::load_from(&path), Ok(secret));
+
+        std::fs::write(&path, "not hexadecimal").unwrap();
+        assert!(identity::load_from(&path).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,257 @@
+// the Markdown clients render: emphasis, code, links and lists, no HTML, no images,
+// and links only to the web or mail so nothing runs on a click
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    // line breaks are kept as they were typed
+    Paragraph(Vec<Span>),
+    Code(String),
+    // numbered from `start` when ordered
+    List { start: Option<u32>, items: Vec<Vec<Span>> },
+}
+
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // a list only continues on the very next line
+    let mut in_list = false;
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let item = list_item(line);
+
+        if !paragraph.is_empty() && (item.is_some() || line.trim().is_empty() || line.trim_start().starts_with("```")) {
+            blocks.push(Block::Paragraph(inline(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+
+        if line.trim_start().starts_with("```") {
+            // an unclosed block runs to the end of the message
+            let code: Vec<_> = lines.by_ref().take_while(|line| !line.trim_start().starts_with("```")).collect();
+            blocks.push(Block::Code(code.join("\n")));
+            in_list = false;
+            continue;
+        }
+
+        match (item, blocks.last_mut()) {
+            (Some((number, item)), Some(Block::List { start, items })) if in_list && number.is_some() == start.is_some() => {
+                items.push(inline(item));
+            }
+            (Some((number, item)), _) => {
+                blocks.push(Block::List { start: number, items: vec![inline(item)] });
+                in_list = true;
+            }
+            (None, _) => {
+                if !line.trim().is_empty() {
+                    paragraph.push(line);
+                }
+
+                in_list = false;
+            }
+        }
+    }
+
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(inline(&paragraph.join("\n"))));
+    }
+
+    blocks
+}
+
+pub fn is_safe_link(url: &str) -> bool {
+    let lower = url.to_lowercase();
+
+    ["http://", "https://", "mailto:"].iter().any(|scheme| lower.starts_with(scheme) && lower.len() > scheme.len())
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+// `- item`, `* item`, `+ item` or `1. item`
+fn list_item(line: &str) -> Option<(Option<u32>, &str)> {
+    let line = line.trim_start();
+
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|bullet| line.strip_prefix(bullet)) {
+        return Some((None, item.trim()));
+    }
+
+    let digits = line.find(|c: char| !c.is_ascii_digit())?;
+    let item = line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))?;
+
+    Some((Some(line[..digits].parse().ok()?), item.trim()))
+}
+
+fn inline(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    emphasis(text, Style::default(), None, &mut spans);
+
+    spans
+}
+
+fn emphasis(text: &str, style: Style, link: Option<&str>, spans: &mut Vec<Span>) {
+    let mut plain = String::new();
+    let mut prev: Option<char> = None;
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+
+        // a backslash keeps the punctuation after it as it is
+        if let Some(escaped) = after.chars().next().filter(|next| c == '\\' && next.is_ascii_punctuation()) {
+            plain.push(escaped);
+            prev = Some(escaped);
+            rest = &after[1..];
+            continue;
+        }
+
+        if c == '`' {
+            if let Some(end) = after.find('`').filter(|end| *end > 0) {
+                push(spans, std::mem::take(&mut plain), style, link);
+                push(spans, after[..end].to_owned(), Style { code: true, ..style }, link);
+                prev = Some('`');
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+
+        if c == '[' && link.is_none() {
+            if let Some((label, url, len)) = labelled_link(rest) {
+                push(spans, std::mem::take(&mut plain), style, link);
+                emphasis(label, style, Some(url), spans);
+                prev = Some(')');
+                rest = &rest[len..];
+                continue;
+            }
+        }
+
+        if link.is_none() && !prev.is_some_and(char::is_alphanumeric) {
+            if let Some(url) = bare_link(rest) {
+                push(spans, std::mem::take(&mut plain), style, link);
+                push(spans, url.to_owned(), style, Some(url));
+                prev = url.chars().last();
+                rest = &rest[url.len()..];
+                continue;
+            }
+        }
+
+        let delimiter = ["**", "__", "*", "_"].iter().find(|delimiter| rest.starts_with(**delimiter));
+
+        // underscores inside words are part of them, as in snake_case
+        if let Some(delimiter) = delimiter.filter(|delimiter| !delimiter.starts_with('_') || !prev.is_some_and(char::is_alphanumeric)) {
+            let inner = &rest[delimiter.len()..];
+
+            if let Some(end) = closing(inner, delimiter) {
+                let emphasised = match delimiter.len() {
+                    2 => Style { bold: true, ..style },
+                    _ => Style { italic: true, ..style },
+                };
+
+                push(spans, std::mem::take(&mut plain), style, link);
+                emphasis(&inner[..end], emphasised, link, spans);
+                prev = delimiter.chars().last();
+                rest = &inner[end + delimiter.len()..];
+                continue;
+            }
+        }
+
+        plain.push(c);
+        prev = Some(c);
+        rest = after;
+    }
+
+    push(spans, plain, style, link);
+}
+
+// where the delimiter closing `inner` starts
+fn closing(inner: &str, delimiter: &str) -> Option<usize> {
+    if inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let single = delimiter.len() == 1;
+    let mut indices = inner.char_indices();
+    // nothing inside code closes anything
+    let mut code_end = 0;
+
+    while let Some((index, c)) = indices.next() {
+        if index < code_end {
+            continue;
+        }
+
+        if c == '`' {
+            code_end = inner[index + 1..].find('`').map_or(0, |end| index + end + 2);
+            continue;
+        }
+
+        if c == '\\' {
+            indices.next();
+            continue;
+        }
+
+        if !inner[index..].starts_with(delimiter) {
+            continue;
+        }
+
+        // a single star never closes on half of a double one
+        if single && inner[index + 1..].starts_with(delimiter) {
+            indices.next();
+            continue;
+        }
+
+        let before = inner[..index].chars().last();
+        let after = inner[index + delimiter.len()..].chars().next();
+
+        if index > 0 && !before.is_none_or(char::is_whitespace) && !(c == '_' && after.is_some_and(char::is_alphanumeric)) {
+            return Some(index);
+        }
+    }
+
+    None
+}
+
+// `[label](url)`, the whole of it is left as text when the url is not safe
+fn labelled_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    let url = &text[url_start..url_end];
+
+    match !label.is_empty() && !label.contains('[') && is_safe_link(url) {
+        true => Some((label, url, url_end + 1)),
+        false => None,
+    }
+}
+
+// trailing punctuation is more likely the end of the sentence than of the address
+fn bare_link(text: &str) -> Option<&str> {
+    ["http://", "https://"].iter().find(|scheme| text.get(..scheme.len()).is_some_and(|start| start.eq_ignore_ascii_case(scheme)))?;
+
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let url = text[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'', '"']);
+
+    Some(url).filter(|url| is_safe_link(url))
+}
+
+// merged with the previous span when nothing tells them apart
+fn push(spans: &mut Vec<Span>, text: String, style: Style, link: Option<&str>) {
+    if text.is_empty() {
+        return;
+    }
+
+    match spans.last_mut() {
+        Some(last) if last.style == style && last.link.as_deref() == link => last.text.push_str(&text),
+        _ => spans.push(Span { text, style, link: link.map(str::to_owned) }),
+    }
+}
@@ -0,0 +1,112 @@
+use crate::command;
+use crate::i18n::Translator;
+use crate::state::ChatState;
+use protocol::network::NetworkMessage;
+
+// longer messages are cut in notifications, the log has the rest
+const MAX_BODY_LEN: usize = 200;
+
+// where notifications go, clients pick one when they start
+pub trait Notifier {
+    fn notify(&self, title: &str, body: &str);
+}
+
+// for tests, and for whoever would rather not be disturbed
+#[derive(Debug, Default)]
+pub struct Silent;
+
+impl Notifier for Silent {
+    fn notify(&self, _title: &str, _body: &str) {}
+}
+
+// nothing is shown until a client picks where notifications go
+impl Default for Box<dyn Notifier> {
+    fn default() -> Self {
+        Box::new(Silent)
+    }
+}
+
+// through whatever the desktop shows notifications with, nothing happens when it is missing
+#[derive(Debug, Default)]
+pub struct Desktop;
+
+impl Notifier for Desktop {
+    fn notify(&self, title: &str, body: &str) {
+        let mut command = match std::env::consts::OS {
+            "macos" => {
+                let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+                let mut command = std::process::Command::new("osascript");
+                command.arg("-e").arg(format!("display notification \"{}\" with title \"{}\"", quote(body), quote(title)));
+                command
+            }
+            // a toast through the WinRT API, shown under PowerShell's name since ours is not registered
+            "windows" => {
+                let mut command = std::process::Command::new("powershell");
+                command.args(["-NoProfile", "-NonInteractive", "-Command", WINDOWS_TOAST])
+                    .env("NOTIFY_TITLE", title)
+                    .env("NOTIFY_BODY", body);
+                no_window(&mut command);
+                command
+            }
+            _ => {
+                let mut command = std::process::Command::new("notify-send");
+                command.arg("--").arg(title).arg(body);
+                command
+            }
+        };
+
+        // waited for elsewhere so the chat never stalls on it
+        if let Ok(mut child) = command.spawn() {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+// the text comes through the environment, nothing in it is ever parsed as a script
+const WINDOWS_TOAST: &str = "\
+    [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+    $toast = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+    $text = $toast.GetElementsByTagName('text'); \
+    $text.Item(0).AppendChild($toast.CreateTextNode($env:NOTIFY_TITLE)) > $null; \
+    $text.Item(1).AppendChild($toast.CreateTextNode($env:NOTIFY_BODY)) > $null; \
+    $app = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe'; \
+    [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($app).Show([Windows.UI.Notifications.ToastNotification]::new($toast))";
+
+// keeps a console from flashing up with every notification
+#[cfg(windows)]
+fn no_window(command: &mut std::process::Command) {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(not(windows))]
+fn no_window(_command: &mut std::process::Command) {}
+
+// tells `notifier` about `msg` when it mentions us or was sent to us alone, and the client is not in focus
+// returns whether it did, the title is in the client's language
+pub fn message(notifier: &dyn Notifier, tr: &Translator, msg: &NetworkMessage, from: &str, state: &ChatState, focused: bool) -> bool {
+    if focused || !state.mentions_us(msg) {
+        return false;
+    }
+
+    let (title, content) = match msg {
+        NetworkMessage::PrivateMessage(msg) => (tr.format("notify-private", [("name", from.into())]), msg.content()),
+        NetworkMessage::Message(msg) => (tr.format("notify-mention", [("name", from.into())]), msg.content()),
+        _ => return false,
+    };
+
+    let body = match command::action(content) {
+        Some(action) => format!("* {} {}", from, action),
+        None => content.to_owned(),
+    };
+
+    let body = match body.char_indices().nth(MAX_BODY_LEN) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    };
+
+    notifier.notify(&title, &body);
+    true
+}
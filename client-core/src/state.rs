@@ -0,0 +1,216 @@
+use protocol::network::NetworkMessage;
+use std::collections::{HashMap, HashSet};
+
+// what a message did to the state
+#[derive(Debug)]
+pub enum Update {
+    // pushed at the end of `messages`
+    Logged,
+    // users, our own id or read markers changed
+    Changed,
+    // not tracked here, left to the client
+    Unhandled(NetworkMessage),
+}
+
+// what every client knows about the server it joined
+#[derive(Debug, Default)]
+pub struct ChatState {
+    pub username: String,
+    pub personal_id: u32,
+    pub moderator: bool,
+    // everybody but us
+    pub users: HashMap<u32, String>,
+    pub bots: HashSet<u32>,
+    // away message of whoever is away, us included
+    pub away: HashMap<u32, String>,
+    // id of the last message each user has read, us included
+    pub read: HashMap<u32, u32>,
+    // private messages that came in while we were not reading, by sender
+    pub unread_private: HashMap<u32, usize>,
+    // index in `messages` of the first one that came in while we were not reading, until we speak
+    pub divider: Option<usize>,
+    // whether the log is in sight, as the client tells
+    reading: bool,
+    pending_users: Vec<(u32, String, bool)>,
+    // chat log, next to the name of who it is from
+    pub messages: Vec<(NetworkMessage, String)>,
+}
+
+impl ChatState {
+    pub fn new(username: String) -> Self {
+        Self { username, reading: true, ..Default::default() }
+    }
+
+    pub fn name(&self, id: u32) -> Option<&str> {
+        match id == self.personal_id {
+            true => Some(&self.username),
+            false => self.users.get(&id).map(String::as_str),
+        }
+    }
+
+    pub fn is_bot(&self, id: u32) -> bool {
+        self.bots.contains(&id)
+    }
+
+    pub fn is_away(&self, id: u32) -> bool {
+        self.away.contains_key(&id)
+    }
+
+    // named with an @ or sent to us alone, by someone else
+    pub fn mentions_us(&self, msg: &NetworkMessage) -> bool {
+        match msg {
+            NetworkMessage::Message(m) => m.from() != self.personal_id && m.mentions().contains(&self.personal_id),
+            NetworkMessage::PrivateMessage(m) => m.from() != self.personal_id && m.to() == self.personal_id,
+            _ => false,
+        }
+    }
+
+    // messages from others after the last one we read
+    pub fn unread(&self) -> usize {
+        let read = self.read.get(&self.personal_id).copied().unwrap_or_default();
+
+        self.messages.iter().filter(|(msg, _)| match msg {
+            NetworkMessage::Message(m) => m.from() != self.personal_id && m.id() > read,
+            _ => false,
+        }).count()
+    }
+
+    // names of the others who stopped reading at this message
+    pub fn seen_by(&self, message_id: u32) -> Vec<&str> {
+        let mut names: Vec<_> = self.read.iter()
+            .filter(|(user, read)| **read == message_id && **user != self.personal_id)
+            .filter_map(|(user, _)| self.name(*user))
+            .collect();
+
+        names.sort_unstable();
+        names
+    }
+
+    // to be called whenever the log comes in or goes out of sight, and after each message while it is in sight
+    // returns the marker to send when there is something new to mark read
+    pub fn set_reading(&mut self, reading: bool) -> Option<NetworkMessage> {
+        self.reading = reading;
+
+        if !reading {
+            return None;
+        }
+
+        self.unread_private.clear();
+
+        let latest = self.messages.iter().rev().find_map(|(msg, _)| match msg {
+            NetworkMessage::Message(m) => Some(m.id()),
+            _ => None,
+        })?;
+
+        match self.read.get(&self.personal_id) {
+            Some(read) if *read >= latest => None,
+            _ => {
+                self.read.insert(self.personal_id, latest);
+                Some(NetworkMessage::read_marker(0, latest))
+            }
+        }
+    }
+
+    fn add_user(&mut self, id: u32, name: String, bot: bool) {
+        self.users.insert(id, name);
+
+        if bot {
+            self.bots.insert(id);
+        }
+    }
+
+    pub fn receive(&mut self, msg: NetworkMessage) -> Update {
+        // large user lists are sent in several chunks, wait for the last one
+        let msg = match msg {
+            NetworkMessage::UserListChunk(chunk) => {
+                self.pending_users.extend(chunk.users().iter().cloned());
+
+                if chunk.more() {
+                    return Update::Changed;
+                }
+
+                NetworkMessage::user_list(std::mem::take(&mut self.pending_users))
+            }
+            msg => msg,
+        };
+
+        match &msg {
+            NetworkMessage::PersonalId(pid) => {
+                self.personal_id = pid.id();
+                self.moderator = pid.moderator();
+
+                return Update::Changed;
+            }
+            NetworkMessage::UserList(list) => {
+                for (id, user, bot) in list.users() {
+                    self.add_user(*id, user.to_owned(), *bot);
+                }
+
+                self.messages.push((msg, String::default()));
+            }
+            NetworkMessage::UserJoin(join) => {
+                self.add_user(join.id(), join.name().to_owned(), join.bot());
+                self.messages.push((msg, String::default()));
+            }
+            NetworkMessage::UserLeave(leave) => {
+                let user = self.users.remove(&leave.id()).unwrap_or_default();
+                self.bots.remove(&leave.id());
+                self.away.remove(&leave.id());
+                self.read.remove(&leave.id());
+                self.unread_private.remove(&leave.id());
+                self.messages.push((msg, user));
+            }
+            // logged under the old name
+            NetworkMessage::UserRename(rename) => {
+                let name = rename.name().to_owned();
+                let old = match rename.id() == self.personal_id {
+                    true => std::mem::replace(&mut self.username, name),
+                    false => self.users.insert(rename.id(), name).unwrap_or_default(),
+                };
+
+                self.messages.push((msg, old));
+            }
+            NetworkMessage::Away(away) => {
+                match away.away() {
+                    true => self.away.insert(away.id(), away.message().to_owned()),
+                    false => self.away.remove(&away.id()),
+                };
+
+                let user = self.name(away.id()).unwrap_or_default().to_owned();
+                self.messages.push((msg, user));
+            }
+            NetworkMessage::Message(m) => {
+                match m.from() == self.personal_id {
+                    true => self.divider = None,
+                    false if !self.reading && self.divider.is_none() => self.divider = Some(self.messages.len()),
+                    false => (),
+                }
+
+                let user = self.name(m.from()).unwrap_or_default().to_owned();
+                self.messages.push((msg, user));
+            }
+            NetworkMessage::PrivateMessage(m) => {
+                if !self.reading && m.from() != self.personal_id {
+                    *self.unread_private.entry(m.from()).or_default() += 1;
+                }
+
+                let user = self.name(m.from()).unwrap_or_default().to_owned();
+                self.messages.push((msg, user));
+            }
+            NetworkMessage::ReadMarker(marker) => {
+                let read = self.read.entry(marker.user()).or_default();
+                *read = marker.message_id().max(*read);
+
+                return Update::Changed;
+            }
+            // shown with the messages, the connection stays open after a warning, a mute or a taken name
+            // and closes right after any other refusal
+            NetworkMessage::RateLimited(_) | NetworkMessage::Refused(_) => {
+                self.messages.push((msg, String::default()));
+            }
+            _ => return Update::Unhandled(msg),
+        }
+
+        Update::Logged
+    }
+}
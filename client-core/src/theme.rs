@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // #RRGGBB
+    pub fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))?;
+        let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+
+        Some(Self(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+// everything a client draws with, the built-in themes and those users write are the same thing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Rgb,
+    // inputs, banners and anything else raised above the background
+    pub surface: Rgb,
+    pub text: Rgb,
+    pub dim: Rgb,
+    pub border: Rgb,
+    pub accent: Rgb,
+    // buttons, then buttons under the pointer
+    pub active: Rgb,
+    pub hovered: Rgb,
+    pub button_text: Rgb,
+    // our own name in the user list
+    pub own: Rgb,
+    // who sent a message
+    pub name_text: Rgb,
+    pub bot: Rgb,
+    pub private: Rgb,
+    pub bold: Rgb,
+    pub italic: Rgb,
+    pub code: Rgb,
+    pub code_background: Rgb,
+    pub link: Rgb,
+    pub error: Rgb,
+    // behind messages that mention us
+    pub mention: Rgb,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: String::from("Dark"),
+            background: Rgb(0x00, 0x0D, 0x1A),
+            surface: Rgb(0x0D, 0x1A, 0x33),
+            text: Rgb(0xFF, 0xFF, 0xFF),
+            dim: Rgb(0x99, 0x99, 0x99),
+            border: Rgb(0x80, 0x80, 0x80),
+            accent: Rgb(0x6F, 0xFF, 0xE9),
+            active: Rgb(0x64, 0x95, 0xED),
+            hovered: Rgb(0x41, 0x69, 0xE1),
+            button_text: Rgb(0xFF, 0xFF, 0xFF),
+            own: Rgb(0x3C, 0xB4, 0x6E),
+            name_text: Rgb(0x00, 0xFF, 0xFF),
+            bot: Rgb(0xB0, 0x9C, 0xFF),
+            private: Rgb(0xFF, 0xC8, 0x6F),
+            bold: Rgb(0xFF, 0xD5, 0x80),
+            italic: Rgb(0xB3, 0xCC, 0xE6),
+            code: Rgb(0xE6, 0xDB, 0x74),
+            code_background: Rgb(0x05, 0x0A, 0x14),
+            link: Rgb(0x66, 0xB2, 0xFF),
+            error: Rgb(0xE6, 0x66, 0x66),
+            mention: Rgb(0x4A, 0x3A, 0x10),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: String::from("Light"),
+            background: Rgb(0xF5, 0xF5, 0xF7),
+            surface: Rgb(0xE3, 0xE7, 0xEE),
+            text: Rgb(0x1A, 0x1A, 0x1A),
+            dim: Rgb(0x6B, 0x6B, 0x6B),
+            border: Rgb(0xB0, 0xB0, 0xB0),
+            accent: Rgb(0x00, 0x77, 0xCC),
+            active: Rgb(0x3B, 0x6F, 0xD8),
+            hovered: Rgb(0x2A, 0x56, 0xB0),
+            button_text: Rgb(0xFF, 0xFF, 0xFF),
+            own: Rgb(0x2E, 0x8B, 0x57),
+            name_text: Rgb(0x00, 0x70, 0x7A),
+            bot: Rgb(0x6A, 0x4F, 0xC8),
+            private: Rgb(0xB3, 0x66, 0x1A),
+            bold: Rgb(0x8A, 0x5A, 0x00),
+            italic: Rgb(0x4A, 0x5A, 0x6A),
+            code: Rgb(0x7A, 0x4E, 0x00),
+            code_background: Rgb(0xEE, 0xEA, 0xE0),
+            link: Rgb(0x00, 0x55, 0xCC),
+            error: Rgb(0xC6, 0x28, 0x28),
+            mention: Rgb(0xFF, 0xF1, 0xCC),
+        }
+    }
+
+    // pure colours on black, nothing told apart by a shade alone
+    pub fn high_contrast() -> Self {
+        Self {
+            name: String::from("High contrast"),
+            background: Rgb(0x00, 0x00, 0x00),
+            surface: Rgb(0x00, 0x00, 0x00),
+            text: Rgb(0xFF, 0xFF, 0xFF),
+            dim: Rgb(0xD0, 0xD0, 0xD0),
+            border: Rgb(0xFF, 0xFF, 0xFF),
+            accent: Rgb(0xFF, 0xFF, 0x00),
+            active: Rgb(0x00, 0x33, 0x99),
+            hovered: Rgb(0x00, 0x55, 0xFF),
+            button_text: Rgb(0xFF, 0xFF, 0xFF),
+            own: Rgb(0x00, 0x64, 0x00),
+            name_text: Rgb(0x00, 0xFF, 0xFF),
+            bot: Rgb(0xFF, 0x80, 0xFF),
+            private: Rgb(0xFF, 0xA5, 0x00),
+            bold: Rgb(0xFF, 0xFF, 0x00),
+            italic: Rgb(0x80, 0xFF, 0xFF),
+            code: Rgb(0x00, 0xFF, 0x00),
+            code_background: Rgb(0x00, 0x00, 0x00),
+            link: Rgb(0x80, 0xC0, 0xFF),
+            error: Rgb(0xFF, 0x50, 0x50),
+            mention: Rgb(0x4D, 0x33, 0x00),
+        }
+    }
+
+    // the default one first
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::dark(), Self::light(), Self::high_contrast()]
+    }
+
+    // by the key it has in a themes file
+    fn colour_mut(&mut self, key: &str) -> Option<&mut Rgb> {
+        Some(match key {
+            "background" => &mut self.background,
+            "surface" => &mut self.surface,
+            "text" => &mut self.text,
+            "dim" => &mut self.dim,
+            "border" => &mut self.border,
+            "accent" => &mut self.accent,
+            "active" => &mut self.active,
+            "hovered" => &mut self.hovered,
+            "button_text" => &mut self.button_text,
+            "own" => &mut self.own,
+            "name_text" => &mut self.name_text,
+            "bot" => &mut self.bot,
+            "private" => &mut self.private,
+            "bold" => &mut self.bold,
+            "italic" => &mut self.italic,
+            "code" => &mut self.code,
+            "code_background" => &mut self.code_background,
+            "link" => &mut self.link,
+            "error" => &mut self.error,
+            "mention" => &mut self.mention,
+            _ => return None,
+        })
+    }
+}
+
+// [[theme]] tables, each with a `name`, the built-in theme it starts from as `base` (dark when left out)
+// and the colours it changes as "#RRGGBB"
+pub fn parse(content: &str) -> Result<Vec<Theme>, String> {
+    let content: toml::Value = content.parse().map_err(|err: toml::de::Error| err.to_string())?;
+
+    let tables = match content.get("theme") {
+        Some(toml::Value::Array(tables)) => tables,
+        Some(_) => return Err(String::from("themes should be [[theme]] tables")),
+        None => return Ok(Vec::new()),
+    };
+
+    tables.iter().map(|table| {
+        let table = table.as_table().ok_or("themes should be [[theme]] tables")?;
+
+        let name = match table.get("name").and_then(toml::Value::as_str).map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Err(String::from("a theme has no name")),
+        };
+
+        let mut theme = match table.get("base").map(toml::Value::as_str) {
+            None => Theme::dark(),
+            Some(Some(base)) => Theme::builtin()
+                .into_iter()
+                .find(|theme| theme.name.eq_ignore_ascii_case(base))
+                .ok_or_else(|| format!("{}: no built-in theme is called {}", name, base))?,
+            Some(None) => return Err(format!("{}: base should be the name of a built-in theme", name)),
+        };
+        theme.name = name.to_owned();
+
+        for (key, value) in table.iter().filter(|(key, _)| !matches!(key.as_str(), "name" | "base")) {
+            let colour = theme.colour_mut(key).ok_or_else(|| format!("{}: unknown colour {}", name, key))?;
+            *colour = value.as_str()
+                .and_then(Rgb::parse)
+                .ok_or_else(|| format!("{}: {} should look like \"#RRGGBB\"", name, key))?;
+        }
+
+        Ok(theme)
+    }).collect()
+}
+
+// no file, no themes
+pub fn load(path: &Path) -> Result<Vec<Theme>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
@@ -0,0 +1,416 @@
+use client_core::command::{self, SlashCommand, COMMANDS};
+use client_core::connection::{self, Connection, Events};
+use client_core::discovery::{self, ServerEvent};
+use client_core::i18n::Translator;
+use client_core::notify::{self, Notifier};
+use client_core::state::{ChatState, Update};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use protocol::multicast::{Announcement, Discovery};
+use protocol::network::{NetworkMessage, MAX_MESSAGE_LEN, SECRET_LEN};
+use std::net::SocketAddr;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
+
+const KEYS: &str = "Tab completes commands and names, F2 previews the formatting, PageUp/PageDown scroll the log, End follows new messages again";
+
+pub enum Entry {
+    // index in the messages of the state
+    Received(usize),
+    Info(String),
+    Error(String),
+}
+
+pub struct Chat {
+    // as it was typed or picked
+    pub server: String,
+    connection: Connection,
+    events: Events,
+    connected: bool,
+    pub state: ChatState,
+    // what the server sent among our own notices
+    pub entries: Vec<Entry>,
+    // rows scrolled up from the bottom, 0 follows new messages
+    pub scroll: usize,
+    // rows of the log on screen, set while drawing
+    pub page: usize,
+    pub name: Option<String>,
+    rules: String,
+    // who has read up to each message, shown under it
+    pub seen: bool,
+}
+
+pub enum Screen {
+    Browse {
+        servers: Vec<(Announcement, SocketAddr, Instant)>,
+        selected: usize,
+        events: Receiver<ServerEvent>,
+    },
+    Chat(Box<Chat>),
+}
+
+pub struct App {
+    pub username: String,
+    secret: [u8; SECRET_LEN],
+    discovery: Discovery,
+    pub screen: Screen,
+    pub input: String,
+    // the input as it will be formatted, drawn above it
+    pub preview: bool,
+    // why the last connection attempt or server discovery failed
+    pub error: Option<String>,
+    pub quit: bool,
+    notifier: Box<dyn Notifier>,
+    // words what client-core reports, the terminal client is in English
+    tr: Translator,
+    // terminals that don't report focus are taken to always have it
+    focused: bool,
+}
+
+impl App {
+    pub fn new(username: String, secret: [u8; SECRET_LEN], discovery: Discovery, server: Option<String>, notifier: Box<dyn Notifier>) -> Self {
+        let mut app = Self {
+            username,
+            secret,
+            discovery,
+            screen: Screen::Browse {
+                servers: Vec::new(),
+                selected: 0,
+                events: discovery::browse(discovery),
+            },
+            input: String::new(),
+            preview: false,
+            error: None,
+            quit: false,
+            notifier,
+            tr: Translator::default(),
+            focused: true,
+        };
+
+        if let Some(server) = server {
+            app.join(server);
+        }
+
+        app
+    }
+
+    fn browse(&mut self) {
+        self.screen = Screen::Browse {
+            servers: Vec::new(),
+            selected: 0,
+            events: discovery::browse(self.discovery),
+        };
+    }
+
+    // blocks while the name resolves, nothing else is going on meanwhile
+    fn join(&mut self, server: String) {
+        match connection::resolve(&server) {
+            Ok(addr) => self.connect(addr, server),
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn connect(&mut self, addr: SocketAddr, server: String) {
+        let connection = Connection::open(&addr, &self.username, false, self.secret)
+            .and_then(|connection| connection.events().map(|events| (connection, events)));
+
+        let (connection, events) = match connection {
+            Ok(connection) => connection,
+            Err(err) => {
+                self.error = Some(format!("{}: {}", server, err));
+                return;
+            }
+        };
+
+        self.error = None;
+        self.input.clear();
+        self.screen = Screen::Chat(Box::new(Chat {
+            entries: vec![Entry::Info(format!("Connected to {}, type /help for commands", server))],
+            server,
+            connection,
+            events,
+            connected: true,
+            state: ChatState::new(self.username.to_owned()),
+            scroll: 0,
+            page: 0,
+            name: None,
+            rules: String::new(),
+            seen: true,
+        }));
+    }
+
+    // takes in what the network threads received since the last frame
+    pub fn poll(&mut self) {
+        match &mut self.screen {
+            Screen::Browse { servers, selected, events } => {
+                while let Ok(event) = events.try_recv() {
+                    match event {
+                        ServerEvent::Failed(err) => self.error = Some(err),
+                        event => discovery::update_server_list(servers, event),
+                    }
+                }
+
+                *selected = (*selected).min(servers.len().saturating_sub(1));
+            }
+            Screen::Chat(chat) => {
+                loop {
+                    match chat.events.ready() {
+                        Some(Some(Ok(msg))) => chat.receive(msg, &*self.notifier, &self.tr, self.focused),
+                        Some(Some(Err(err))) => chat.entries.push(Entry::Error(err)),
+                        Some(None) if chat.connected => {
+                            chat.connected = false;
+                            chat.entries.push(Entry::Error(String::from("Disconnected, /servers to pick another server")));
+                        }
+                        _ => break,
+                    }
+                }
+
+                // read when following the log in a terminal in focus
+                if let Some(marker) = chat.state.set_reading(self.focused && chat.scroll == 0) {
+                    if chat.connected {
+                        chat.send(marker);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn handle(&mut self, event: Event) {
+        let key = match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::FocusGained => return self.focused = true,
+            Event::FocusLost => return self.focused = false,
+            _ => return,
+        };
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            _ => match self.screen {
+                Screen::Browse { .. } => self.handle_browse(key),
+                Screen::Chat(_) => self.handle_chat(key),
+            },
+        }
+    }
+
+    fn handle_browse(&mut self, key: KeyEvent) {
+        let (servers, selected) = match &mut self.screen {
+            Screen::Browse { servers, selected, .. } => (servers, selected),
+            _ => return,
+        };
+
+        match key.code {
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(servers.len().saturating_sub(1)),
+            KeyCode::F(5) => self.browse(),
+            KeyCode::Esc => self.quit = true,
+            // a typed address wins over the selected server
+            KeyCode::Enter => match (self.input.trim(), servers.get(*selected)) {
+                ("", Some((_, addr, _))) => {
+                    let addr = *addr;
+                    self.connect(addr, addr.to_string());
+                }
+                ("", None) => (),
+                (typed, _) => self.join(typed.to_owned()),
+            },
+            _ => (),
+        }
+    }
+
+    fn handle_chat(&mut self, key: KeyEvent) {
+        let chat = match &mut self.screen {
+            Screen::Chat(chat) => chat,
+            _ => return,
+        };
+
+        match key.code {
+            KeyCode::Up => chat.scroll += 1,
+            KeyCode::Down => chat.scroll = chat.scroll.saturating_sub(1),
+            KeyCode::PageUp => chat.scroll += chat.page.max(1),
+            KeyCode::PageDown => chat.scroll = chat.scroll.saturating_sub(chat.page.max(1)),
+            KeyCode::End => chat.scroll = 0,
+            KeyCode::Esc => self.input.clear(),
+            KeyCode::F(2) => self.preview = !self.preview,
+            KeyCode::Tab => {
+                let (input, candidates) = command::complete(&self.input, &chat.state);
+                self.input = input;
+
+                if !candidates.is_empty() {
+                    chat.entries.push(Entry::Info(candidates.join("  ")));
+                }
+            }
+            KeyCode::Enter => self.submit(),
+            _ => (),
+        }
+    }
+
+    fn submit(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        let input = input.trim();
+
+        let chat = match &mut self.screen {
+            Screen::Chat(chat) => chat,
+            _ => return,
+        };
+
+        match SlashCommand::parse(input) {
+            Some(Ok(command)) => return self.command(command),
+            // kept in the input to be fixed
+            Some(Err(err)) => {
+                chat.entries.push(Entry::Error(err.message(&self.tr)));
+                self.input = input.to_owned();
+                return;
+            }
+            None => (),
+        }
+
+        if input.is_empty() {
+            return;
+        }
+
+        // kept in the input to be shortened
+        if input.len() > MAX_MESSAGE_LEN {
+            chat.entries.push(Entry::Error(format!("Messages are at most {} byte, this one is {}", MAX_MESSAGE_LEN, input.len())));
+            self.input = input.to_owned();
+            return;
+        }
+
+        chat.scroll = 0;
+        chat.send(NetworkMessage::message(0, chat.state.personal_id, input.to_owned(), Vec::new()));
+    }
+
+    fn command(&mut self, command: SlashCommand) {
+        let chat = match &mut self.screen {
+            Screen::Chat(chat) => chat,
+            _ => return,
+        };
+
+        match command.message(&chat.state) {
+            Ok(Some(msg)) => {
+                chat.scroll = 0;
+                return chat.send(msg);
+            }
+            Ok(None) => (),
+            Err(err) => return chat.entries.push(Entry::Error(err.message(&self.tr))),
+        }
+
+        match command {
+            SlashCommand::Help => {
+                let help = COMMANDS.iter().map(|(name, args, what)| Entry::Info(format!("{:<20} {}", format!("/{} {}", name, args), self.tr.tr(what))));
+                chat.entries.extend(help);
+                chat.entries.push(Entry::Info(String::from(KEYS)));
+            }
+            SlashCommand::Who => {
+                let users: Vec<_> = chat.online().into_iter().map(|(user, bot, away, _)| match (bot, away) {
+                    (true, _) => format!("{} (bot)", user),
+                    (_, true) => format!("{} (away)", user),
+                    _ => user.to_owned(),
+                }).collect();
+
+                chat.entries.push(Entry::Info(format!("{} online: {}", users.len(), users.join(", "))));
+            }
+            SlashCommand::Rules => match chat.rules.is_empty() {
+                true => chat.entries.push(Entry::Info(String::from("This server has no rules"))),
+                false => {
+                    let rules: Vec<_> = chat.rules.lines().map(|line| Entry::Info(line.to_owned())).collect();
+                    chat.entries.extend(rules);
+                }
+            },
+            SlashCommand::Seen => {
+                chat.seen = !chat.seen;
+                chat.entries.push(Entry::Info(String::from(match chat.seen {
+                    true => "Showing who has read up to where",
+                    false => "Hiding who has read up to where",
+                })));
+            }
+            SlashCommand::Clear => {
+                chat.entries.clear();
+                chat.state.messages.clear();
+                chat.state.divider = None;
+                chat.scroll = 0;
+            }
+            SlashCommand::Join(server) => {
+                self.join(server);
+
+                // still here when the other server could not be reached
+                if let (Some(err), Screen::Chat(chat)) = (self.error.take(), &mut self.screen) {
+                    chat.entries.push(Entry::Error(err));
+                }
+            }
+            SlashCommand::Leave => self.browse(),
+            SlashCommand::Quit => self.quit = true,
+            // sent above
+            SlashCommand::Nick(_) | SlashCommand::Me(_) | SlashCommand::Msg(_) | SlashCommand::Away(_) => (),
+        }
+    }
+}
+
+impl Chat {
+    // (name, bot, away, unread private messages), us first then everybody else by name
+    pub fn online(&self) -> Vec<(&str, bool, bool, usize)> {
+        let mut users: Vec<_> = self.state.users.iter()
+            .map(|(id, user)| {
+                let unread = self.state.unread_private.get(id).copied().unwrap_or_default();
+                (user.as_str(), self.state.is_bot(*id), self.state.is_away(*id), unread)
+            })
+            .collect();
+
+        users.sort_by_key(|(user, ..)| user.to_lowercase());
+        users.insert(0, (&self.state.username, false, self.state.is_away(self.state.personal_id), 0));
+
+        users
+    }
+
+    fn send(&mut self, msg: NetworkMessage) {
+        if let Err(err) = self.connection.send(msg) {
+            self.entries.push(Entry::Error(err));
+        }
+    }
+
+    fn receive(&mut self, msg: NetworkMessage, notifier: &dyn Notifier, tr: &Translator, focused: bool) {
+        let msg = match self.state.receive(msg) {
+            Update::Logged => {
+                if let Some((msg, from)) = self.state.messages.last() {
+                    notify::message(notifier, tr, msg, from, &self.state, focused);
+                }
+
+                return self.entries.push(Entry::Received(self.state.messages.len() - 1));
+            }
+            Update::Changed => return,
+            Update::Unhandled(msg) => msg,
+        };
+
+        match msg {
+            // sent on join and again whenever the owner edits it
+            NetworkMessage::ServerInfo(info) => {
+                if !info.motd().is_empty() {
+                    self.entries.push(Entry::Info(info.motd().to_owned()));
+                }
+
+                self.name = Some(info.name().to_owned());
+                self.rules = info.rules().to_owned();
+            }
+            NetworkMessage::Pinned(pin) => {
+                self.entries.push(Entry::Info(format!("Pinned, {}: {}", pin.from(), pin.content())));
+            }
+            NetworkMessage::FileOffer(offer) => {
+                let from = self.state.name(offer.from()).unwrap_or_default();
+                self.entries.push(Entry::Info(format!("{} shared {}, files are received in the graphical client", from, offer.name())));
+            }
+            _ => (),
+        }
+    }
+}
+
+// also ends the thread reading events
+impl Drop for Chat {
+    fn drop(&mut self) {
+        self.connection.close();
+    }
+}
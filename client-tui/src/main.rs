@@ -0,0 +1,114 @@
+mod app;
+mod ui;
+
+use app::App;
+use client_core::identity;
+use client_core::notify::Desktop;
+use crossterm::{event, execute, terminal};
+use protocol::multicast::Discovery;
+use protocol::network::MAX_NAME_LEN;
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::io::Stdout;
+use std::time::Duration;
+
+const USAGE: &str = "usage: client-tui NAME [host:port] [--multicast-group ADDR] [--multicast-port N] [--multicast-ttl N] [--multicast-interface ADDR] [--multicast-group-v6 ADDR] [--multicast-interface-v6 INDEX]";
+
+pub fn main() {
+    let mut discovery = match Discovery::from_env() {
+        Ok(discovery) => discovery,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let mut username = None;
+    let mut server = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let set = match arg.starts_with("--") {
+            true => match args.next() {
+                Some(value) => discovery.set(&arg, &value),
+                None => Err(format!("{}: missing value", arg)),
+            },
+            false if username.is_none() => {
+                username = Some(arg);
+                continue;
+            }
+            false if server.is_some() => Err(format!("{}: only one server can be given", arg)),
+            false => {
+                server = Some(arg);
+                continue;
+            }
+        };
+
+        if let Err(err) = set {
+            println!("{}", err);
+            println!("{}", USAGE);
+            return;
+        }
+    }
+
+    let username = match username {
+        Some(username) if !username.is_empty() && username.len() <= MAX_NAME_LEN => username,
+        Some(_) => {
+            println!("Usernames are 1 to {} byte long", MAX_NAME_LEN);
+            return;
+        }
+        None => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    // said before the terminal is taken over
+    let (secret, error) = identity::load("identity");
+    if let Some(err) = error {
+        println!("{}", err);
+    }
+
+    let mut app = App::new(username, secret, discovery, server, Box::new(Desktop));
+
+    if let Err(err) = run(&mut app) {
+        println!("{}", err);
+    }
+}
+
+fn run(app: &mut App) -> std::io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(std::io::stdout(), terminal::EnterAlternateScreen, event::EnableFocusChange)?;
+
+    // leave the terminal usable when something panics
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    let result = event_loop(&mut terminal, app);
+
+    restore()?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> std::io::Result<()> {
+    while !app.quit {
+        app.poll();
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        // short enough for incoming messages to show up promptly
+        if event::poll(Duration::from_millis(100))? {
+            app.handle(event::read()?);
+        }
+    }
+
+    Ok(())
+}
+
+fn restore() -> std::io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(std::io::stdout(), event::DisableFocusChange, terminal::LeaveAlternateScreen)
+}
@@ -0,0 +1,367 @@
+use super::app::{App, Chat, Entry, Screen};
+use client_core::command;
+use client_core::markdown;
+use client_core::state::ChatState;
+use protocol::network::{refusal, NetworkMessage, PROTOCOL_VERSION};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+const ACCENT: Color = Color::Rgb(0x6F, 0xFF, 0xE9);
+const ERROR: Color = Color::Rgb(0xE0, 0x50, 0x50);
+const DIM: Color = Color::Rgb(0xA0, 0xA0, 0xA0);
+const BOT: Color = Color::Rgb(0xB0, 0x9C, 0xFF);
+const PRIVATE: Color = Color::Rgb(0xFF, 0xC8, 0x6F);
+const CODE: Color = Color::Rgb(0xE6, 0xDB, 0x74);
+const LINK: Color = Color::Rgb(0x66, 0xB2, 0xFF);
+const MENTION: Color = Color::Rgb(0x4A, 0x3A, 0x10);
+
+// rendered text, cut in rows later
+type Segments = Vec<(String, Style)>;
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, input] = split(frame.size());
+
+    match &mut app.screen {
+        Screen::Browse { servers, selected, .. } => {
+            let items: Vec<_> = servers.iter().map(|(announcement, addr, _)| {
+                let mut details = vec![addr.to_string()];
+
+                match announcement.users {
+                    Some((current, 0)) => details.push(format!("{} online", current)),
+                    Some((current, max)) => details.push(format!("{}/{} online", current, max)),
+                    None => (),
+                }
+
+                if !announcement.description.is_empty() {
+                    details.push(announcement.description.to_owned());
+                }
+
+                let mut lines = vec![
+                    Line::from(Span::styled(announcement.name.to_owned(), Style::default().add_modifier(Modifier::BOLD))),
+                    Line::from(Span::styled(details.join(" - "), Style::default().fg(DIM))),
+                ];
+
+                // servers announcing no version predate versioning
+                if let Some(version) = announcement.version.filter(|version| *version != PROTOCOL_VERSION) {
+                    lines.push(Line::from(Span::styled(
+                        format!("Protocol v{}, this client speaks v{}", version, PROTOCOL_VERSION),
+                        Style::default().fg(ERROR),
+                    )));
+                }
+
+                ListItem::new(lines)
+            }).collect();
+
+            let title = match items.is_empty() {
+                true => " Looking for servers... ",
+                false => " Servers ",
+            };
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(Style::default().fg(ACCENT))
+                .highlight_symbol("> ");
+
+            let mut state = ListState::default().with_selected(Some(*selected));
+            frame.render_stateful_widget(list, main, &mut state);
+
+            let title = match &app.error {
+                Some(err) => Span::styled(format!(" {} ", err), Style::default().fg(ERROR)),
+                None => Span::raw(" Address, or Enter to join the selected server - F5 refresh - Esc quit "),
+            };
+
+            draw_input(frame, input, &app.input, title);
+        }
+        Screen::Chat(chat) => {
+            let [main, users] = split_users(main);
+            let name = chat.state.username.to_owned();
+
+            // commands have nothing to preview
+            let log = match app.preview && !app.input.trim().is_empty() && !app.input.starts_with('/') {
+                true => {
+                    let mut segments = vec![(format!("{}: ", name), Style::default().fg(ACCENT))];
+                    segments.extend(rich(&app.input, Style::default()));
+
+                    let rows = wrap(segments, main.width.saturating_sub(2) as usize);
+                    let [log, preview] = split_preview(main, rows.len().min(8) as u16 + 2);
+
+                    let block = Block::default().borders(Borders::ALL).title(" Preview - F2 to hide ");
+                    frame.render_widget(Paragraph::new(rows).block(block), preview);
+
+                    log
+                }
+                false => main,
+            };
+
+            draw_log(frame, log, chat);
+            draw_users(frame, users, chat);
+
+            let title = Span::raw(format!(" {} ", name));
+            draw_input(frame, input, &app.input, title);
+        }
+    }
+}
+
+fn split(area: Rect) -> [Rect; 2] {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+
+    [chunks[0], chunks[1]]
+}
+
+fn split_preview(area: Rect, height: u16) -> [Rect; 2] {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(height)])
+        .split(area);
+
+    [chunks[0], chunks[1]]
+}
+
+fn split_users(area: Rect) -> [Rect; 2] {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(20), Constraint::Length(24)])
+        .split(area);
+
+    [chunks[0], chunks[1]]
+}
+
+fn draw_input(frame: &mut Frame, area: Rect, input: &str, title: Span) {
+    let width = area.width.saturating_sub(2) as usize;
+    let len = input.chars().count();
+
+    // the end of long inputs stays visible
+    let visible: String = input.chars().skip((len + 1).saturating_sub(width)).collect();
+    let cursor = visible.chars().count() as u16;
+
+    frame.render_widget(Paragraph::new(visible).block(Block::default().borders(Borders::ALL).title(title)), area);
+    frame.set_cursor(area.x + 1 + cursor, area.y + 1);
+}
+
+fn draw_log(frame: &mut Frame, area: Rect, chat: &mut Chat) {
+    let width = area.width.saturating_sub(2) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+
+    let rows: Vec<Line> = chat.entries.iter().flat_map(|entry| {
+        let mut rows = Vec::new();
+
+        if let Entry::Received(index) = entry {
+            if chat.state.divider == Some(*index) {
+                let label = " New messages ";
+                let side = "-".repeat(width.saturating_sub(label.len()) / 2);
+                rows.push(Line::from(Span::styled(format!("{}{}{}", side, label, side), Style::default().fg(ERROR))));
+            }
+        }
+
+        let segments = match entry {
+            Entry::Received(index) => match chat.state.messages.get(*index) {
+                Some((msg, from)) if chat.state.mentions_us(msg) => describe(msg, from, &chat.state)
+                    .into_iter()
+                    .map(|(text, style)| (text, style.bg(MENTION)))
+                    .collect(),
+                Some((msg, from)) => describe(msg, from, &chat.state),
+                None => return Vec::new(),
+            },
+            Entry::Info(info) => vec![(info.to_owned(), Style::default().fg(DIM))],
+            Entry::Error(err) => vec![(err.to_owned(), Style::default().fg(ERROR))],
+        };
+
+        rows.extend(wrap(segments, width));
+
+        if let (true, Entry::Received(index)) = (chat.seen, entry) {
+            if let Some((NetworkMessage::Message(msg), _)) = chat.state.messages.get(*index) {
+                let seen = chat.state.seen_by(msg.id());
+
+                if !seen.is_empty() {
+                    rows.extend(wrap(vec![(format!("  Seen by {}", seen.join(", ")), Style::default().fg(DIM))], width));
+                }
+            }
+        }
+
+        rows
+    }).collect();
+
+    chat.page = height;
+    chat.scroll = chat.scroll.min(rows.len().saturating_sub(height));
+
+    let end = rows.len() - chat.scroll;
+    let start = end.saturating_sub(height);
+
+    let name = match chat.state.unread() {
+        0 => chat.name.as_deref().unwrap_or(&chat.server).to_owned(),
+        unread => format!("{} ({} unread)", chat.name.as_deref().unwrap_or(&chat.server), unread),
+    };
+    let title = match chat.scroll {
+        0 => format!(" {} ", name),
+        scroll => format!(" {} - {} more below, End to follow ", name, scroll),
+    };
+
+    let log = Paragraph::new(rows[start..end].to_vec()).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(log, area);
+}
+
+fn draw_users(frame: &mut Frame, area: Rect, chat: &Chat) {
+    let items: Vec<_> = chat.online().into_iter().enumerate().map(|(index, (user, bot, away, unread))| {
+        let item = match (index, bot, unread) {
+            (0, ..) => ListItem::new(user.to_owned()).style(Style::default().fg(ACCENT)),
+            (_, true, _) => ListItem::new(format!("{} (bot)", user)).style(Style::default().fg(BOT)),
+            (_, false, 0) => ListItem::new(user.to_owned()),
+            (_, false, unread) => ListItem::new(format!("{} ({})", user, unread)).style(Style::default().fg(PRIVATE)),
+        };
+
+        match away {
+            true => item.style(Style::default().fg(DIM).add_modifier(Modifier::ITALIC)),
+            false => item,
+        }
+    }).collect();
+    let title = format!(" Online ({}) ", items.len());
+
+    frame.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), area);
+}
+
+// a received message as it reads in the log
+fn describe(msg: &NetworkMessage, from: &str, state: &ChatState) -> Segments {
+    let info = |text| vec![(text, Style::default().fg(DIM))];
+    let said = |prefix: String, content: &str, style: Style| {
+        let mut segments = vec![(prefix, Style::default().fg(ACCENT))];
+        segments.extend(rich(content, style));
+        segments
+    };
+
+    if let Some(action) = match msg {
+        NetworkMessage::Message(msg) => command::action(msg.content()),
+        _ => None,
+    } {
+        return vec![(format!("* {} {}", from, action), Style::default().add_modifier(Modifier::ITALIC))];
+    }
+
+    match msg {
+        NetworkMessage::Message(msg) if state.is_bot(msg.from()) => said(format!("{} (bot): ", from), msg.content(), Style::default().fg(BOT)),
+        NetworkMessage::Message(msg) => said(format!("{}: ", from), msg.content(), Style::default()),
+        NetworkMessage::UserList(list) => info(format!("{} online", list.users().len() + 1)),
+        NetworkMessage::UserJoin(join) => info(format!("{} joined", join.name())),
+        NetworkMessage::UserLeave(_) => info(format!("{} left", from)),
+        NetworkMessage::UserRename(rename) => info(format!("{} is now known as {}", from, rename.name())),
+        NetworkMessage::Away(away) => info(match (away.away(), away.message().is_empty()) {
+            (true, true) => format!("{} is away", from),
+            (true, false) => format!("{} is away: {}", from, away.message()),
+            (false, _) => format!("{} is back", from),
+        }),
+        NetworkMessage::PrivateMessage(msg) => {
+            let to = state.name(msg.to()).unwrap_or_default();
+            said(format!("{} -> {}: ", from, to), msg.content(), Style::default().fg(PRIVATE))
+        }
+        NetworkMessage::RateLimited(limited) => vec![(match limited.muted() {
+            true => format!("You are muted for {}s for flooding", limited.retry_after().div_ceil(1000)),
+            false => String::from("You are sending messages too fast, your last message was dropped"),
+        }, Style::default().fg(ERROR))],
+        NetworkMessage::Refused(refused) => vec![(match refused.reason() {
+            refusal::SERVER_FULL => String::from("The server is full, try again later"),
+            refusal::NAME_TAKEN => String::from("Somebody online already has that name"),
+            _ => String::from("The server refused the connection"),
+        }, Style::default().fg(ERROR))],
+        _ => info(msg.to_string()),
+    }
+}
+
+// Markdown drawn with terminal styles over `base`, lists and code start on a row of their own
+fn rich(content: &str, base: Style) -> Segments {
+    let mut segments = Vec::new();
+
+    for (index, block) in markdown::parse(content).into_iter().enumerate() {
+        if index > 0 || !matches!(block, markdown::Block::Paragraph(_)) {
+            segments.push((String::from("\n"), base));
+        }
+
+        match block {
+            markdown::Block::Paragraph(spans) => segments.extend(spans.into_iter().flat_map(|span| styled(span, base))),
+            markdown::Block::Code(code) => {
+                let lines: Vec<_> = code.lines().map(|line| format!("  {}", line)).collect();
+                segments.push((lines.join("\n"), base.fg(CODE)));
+            }
+            markdown::Block::List { start, items } => for (number, item) in items.into_iter().enumerate() {
+                let bullet = match start {
+                    Some(start) => format!("  {}. ", start as usize + number),
+                    None => String::from("  - "),
+                };
+
+                if number > 0 {
+                    segments.push((String::from("\n"), base));
+                }
+
+                segments.push((bullet, base));
+                segments.extend(item.into_iter().flat_map(|span| styled(span, base)));
+            },
+        }
+    }
+
+    segments
+}
+
+// terminals can't be clicked, links that hide their address show it after them
+fn styled(span: markdown::Span, base: Style) -> Segments {
+    let mut style = base;
+
+    if span.style.bold {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+
+    if span.style.italic {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+
+    if span.style.code {
+        style = style.fg(CODE);
+    }
+
+    match span.link {
+        Some(link) if link != span.text => vec![
+            (span.text, style.fg(LINK).add_modifier(Modifier::UNDERLINED)),
+            (format!(" <{}>", link), base.fg(DIM)),
+        ],
+        Some(_) => vec![(span.text, style.fg(LINK).add_modifier(Modifier::UNDERLINED))],
+        None => vec![(span.text, style)],
+    }
+}
+
+// cut into rows of at most `width` characters, styles carry over from row to row
+fn wrap(segments: Segments, width: usize) -> Vec<Line<'static>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut len = 0;
+
+    for (text, style) in segments {
+        let mut part = String::new();
+
+        for c in text.chars() {
+            if c == '\n' || len >= width.max(1) {
+                if !part.is_empty() {
+                    row.push(Span::styled(std::mem::take(&mut part), style));
+                }
+
+                rows.push(Line::from(std::mem::take(&mut row)));
+                len = 0;
+
+                if c == '\n' {
+                    continue;
+                }
+            }
+
+            part.push(c);
+            len += 1;
+        }
+
+        if !part.is_empty() {
+            row.push(Span::styled(part, style));
+        }
+    }
+
+    rows.push(Line::from(row));
+    rows
+}
@@ -1,29 +1,125 @@
 use super::incoming_messages::IncomingMessages;
-use super::{Client, View, ClientMessage};
+use super::message_log::{LoggedMessage, MessageLog, RESTORED_MESSAGES};
+use super::search::Search;
+use super::server_browser::ServerBrowser;
+use super::settings::{self, MAX_TEXT_SIZE, MIN_TEXT_SIZE};
+use super::transfer::{Transfer, TransferState};
+use super::{Client, View, ClientMessage, PinnedMessage};
 
-use protocol::network::NetworkMessage;
+use client_core::command::{self, SlashCommand, COMMANDS};
+use client_core::connection::{self, Connection};
+use client_core::discovery::{self, ServerEvent};
+use client_core::i18n::{self, Translator};
+use client_core::markdown;
+use client_core::notify;
+use client_core::state::{ChatState, Update};
+use protocol::network::{NetworkMessage, MAX_MESSAGE_LEN, MAX_NAME_LEN, SECRET_LEN};
 
-use std::io::Write;
-use std::time::Duration;
 use std::collections::HashMap;
-use std::net::{UdpSocket, TcpStream, SocketAddr};
+use std::net::SocketAddr;
+use std::sync::Arc;
 
 use iced::{
     Application, Subscription, Clipboard, Command,
 };
+use iced_native::{keyboard, window, Event};
 
 impl Client {
     pub fn get_subscription(&self) -> Subscription<<Self as Application>::Message> {
-        if let View::Chat { socket, .. } = &self.view {
-            Subscription::from_recipe(IncomingMessages {
-                stream: socket.try_clone().unwrap(),
-            }).map(ClientMessage::IncomingMessages)
-        } else {
-            Subscription::none()
+        // whatever the screen
+        let window = iced_native::subscription::events_with(|event, _| match event {
+            Event::Window(window::Event::Focused) => Some(ClientMessage::WindowFocused(true)),
+            Event::Window(window::Event::Unfocused) => Some(ClientMessage::WindowFocused(false)),
+            Event::Window(window::Event::Resized { width, height }) => Some(ClientMessage::WindowResized(width, height)),
+            Event::Window(window::Event::CloseRequested) => Some(ClientMessage::CloseRequested),
+            _ => None,
+        });
+
+        let view = match &self.view {
+            View::Chat { connection, .. } => Subscription::batch(vec![
+                Subscription::from_recipe(IncomingMessages {
+                    connection: connection.try_clone().map_err(|err| err.to_string()),
+                }),
+                // the text input has no use for Tab, it completes instead
+                iced_native::subscription::events_with(|event, _| match event {
+                    Event::Keyboard(keyboard::Event::KeyPressed { key_code: keyboard::KeyCode::Tab, .. }) => Some(ClientMessage::CompleteMessage),
+                    _ => None,
+                }),
+            ]),
+            View::SelectServer { generation, .. } => Subscription::from_recipe(ServerBrowser {
+                discovery: self.discovery,
+                generation: *generation,
+            }).map(ClientMessage::ServerEvent),
+            _ => Subscription::none(),
+        };
+
+        Subscription::batch(vec![window, view])
+    }
+
+    // connecting may take up to CONNECT_TIMEOUT, keep it off the UI thread
+    // `saved_as` is what ends up in the recent servers, as the user typed it
+    fn connect(&self, addr: SocketAddr, saved_as: String) -> Command<ClientMessage> {
+        Command::perform(open(addr, self.username.to_owned(), self.secret), move |opened| {
+            ClientMessage::Connected(saved_as.clone(), opened)
+        })
+    }
+
+    fn join(&mut self, connection: Connection, saved_as: String) {
+
+        self.preferences.servers.add_recent(&saved_as);
+        self.preferences.save();
+
+        let mut state = ChatState::new(self.username.to_owned());
+        state.messages.reserve(50);
+
+        self.view = View::Chat {
+            state,
+            scroll_view: iced::scrollable::State::default(),
+            input: iced::text_input::State::default(),
+            attach: iced::button::State::default(),
+            export: iced::button::State::default(),
+            log: MessageLog::default(),
+            joined_as: saved_as,
+            restored: 0,
+            transfers: HashMap::default(),
+            next_transfer: 0,
+            connection,
+            message: String::default(),
+            server_info: None,
+            rules_button: iced::button::State::default(),
+            show_rules: false,
+            pinned: Vec::new(),
+            pinned_button: iced::button::State::default(),
+            show_pinned: true,
+            pin_buttons: HashMap::default(),
+            link_buttons: HashMap::default(),
+            preview_button: iced::button::State::default(),
+            preview: false,
+            seen: true,
+            search: Search::default(),
+            notices: Vec::new(),
+        };
+    }
+
+    // leaves the server we are on, if any; `address` is filled in the address field
+    fn select_server(&mut self, address: String) {
+        if let View::Chat { connection, .. } = &self.view {
+            connection.close();
         }
+
+        self.view = View::SelectServer {
+            buttons: vec![],
+            servers: vec![],
+            generation: 0,
+            address_input: iced::text_input::State::default(),
+            address,
+            connect: iced::button::State::default(),
+            saved_buttons: vec![],
+            error: None,
+        };
     }
 
-    pub fn update_ui(&mut self, message: <Self as Application>::Message, _clipboard: &mut Clipboard) -> Command<<Self as Application>::Message> {
+    pub fn update_ui(&mut self, message: <Self as Application>::Message, clipboard: &mut Clipboard) -> Command<<Self as Application>::Message> {
         match message {
             ClientMessage::UpdateUsername(new_username) => {
                 if let View::Home { .. } = &self.view {
@@ -32,146 +128,613 @@ impl Client {
             }
             ClientMessage::SubmitUsername => {
                 if let View::Home { .. } = &self.view {
-                    if self.username.is_empty() {
+                    if self.username.is_empty() || self.username.len() > MAX_NAME_LEN {
                         return Command::none();
                     }
 
-                    let mut servers = vec![];
-                    update_server_list(&mut servers);
+                    self.preferences.username = self.username.to_owned();
+                    self.preferences.save();
 
-                    self.view = View::SelectServer {
-                        buttons: vec![],
-                        servers,
-                    };
+                    let autoconnect = self.autoconnect.take();
+                    self.select_server(autoconnect.clone().unwrap_or_default());
+
+                    if let Some(addr) = autoconnect {
+                        return self.update_ui(ClientMessage::ConnectAddress(addr), clipboard);
+                    }
                 }
             }
             ClientMessage::RefreshServerList => {
-                if let View::SelectServer { servers, .. } = &mut self.view {
-                    update_server_list(servers);
+                if let View::SelectServer { servers, generation, .. } = &mut self.view {
+                    servers.clear();
+                    *generation += 1;
+                }
+            }
+            ClientMessage::ServerEvent(event) => {
+                if let View::SelectServer { servers, error, .. } = &mut self.view {
+                    match event {
+                        ServerEvent::Failed(err) => *error = Some(err),
+                        event => discovery::update_server_list(servers, event),
+                    }
+                }
+            }
+            ClientMessage::SelectServer(addr) => {
+                if let View::SelectServer { error, .. } = &mut self.view {
+                    *error = None;
+                    return self.connect(addr, addr.to_string());
                 }
             }
-            ClientMessage::SelectServer(socket) => {
-                let buf = NetworkMessage::client_identity(self.username.to_owned()).into_vec();
-                let mut socket = TcpStream::connect(socket).unwrap();
-                socket.write_all(&buf).unwrap();
+            ClientMessage::UpdateAddress(new_address) => {
+                if let View::SelectServer { address, .. } = &mut self.view {
+                    *address = new_address;
+                }
+            }
+            ClientMessage::ConnectAddress(addr) => {
+                if let View::SelectServer { error, .. } = &mut self.view {
+                    let addr = addr.trim().to_owned();
+                    if addr.is_empty() {
+                        return Command::none();
+                    }
+
+                    *error = None;
 
-                self.view = View::Chat {
-                    messages: Vec::with_capacity(50),
-                    users: HashMap::default(),
-                    scroll_view: iced::scrollable::State::default(),
-                    input: iced::text_input::State::default(),
-                    socket,
-                    personal_id: 0,
-                    message: String::default(),
-                };
+                    // name resolution may block, keep it off the UI thread
+                    return Command::perform(resolve(addr.clone()), move |resolved| {
+                        ClientMessage::AddressResolved(addr.clone(), resolved)
+                    });
+                }
+            }
+            ClientMessage::AddressResolved(addr, resolved) => {
+                match resolved {
+                    Ok(resolved) => return self.connect(resolved, addr),
+                    Err(err) => {
+                        if let View::SelectServer { error, .. } = &mut self.view {
+                            *error = Some(err);
+                        }
+                    }
+                }
+            }
+            // only joined when still picking a server
+            ClientMessage::Connected(saved_as, opened) => {
+                if let View::SelectServer { error, .. } = &mut self.view {
+                    match opened.and_then(|connection| connection.try_clone().map_err(|err| err.to_string())) {
+                        Ok(connection) => self.join(connection, saved_as),
+                        Err(err) => *error = Some(format!("{}: {}", saved_as, err)),
+                    }
+                }
+            }
+            ClientMessage::ToggleFavourite(addr) => {
+                self.preferences.servers.toggle_favourite(&addr);
+                self.preferences.save();
             }
             ClientMessage::UpdateMessage(msg) => {
                 if let View::Chat { message, .. } = &mut self.view {
                     *message = msg;
                 }
             }
+            ClientMessage::WindowFocused(focused) => {
+                self.focused = focused;
+
+                if let View::Chat { state, connection, notices, .. } = &mut self.view {
+                    if let Some(marker) = state.set_reading(focused) {
+                        check_sent(connection.send(marker), &self.tr, notices, state.messages.len());
+                    }
+                }
+            }
+            ClientMessage::CompleteMessage => {
+                if let View::Chat { message, input, state, notices, .. } = &mut self.view {
+                    let (completed, candidates) = command::complete(message, state);
+                    *message = completed;
+                    input.move_cursor_to_end();
+
+                    if !candidates.is_empty() {
+                        notices.push((state.messages.len(), candidates.join("  "), false));
+                    }
+                }
+            }
+            ClientMessage::TogglePreview => {
+                if let View::Chat { preview, .. } = &mut self.view {
+                    *preview = !*preview;
+                }
+            }
+            ClientMessage::OpenLink(url) => {
+                if let View::Chat { state, notices, .. } = &mut self.view {
+                    // only safe links get a button, checked again all the same
+                    if !markdown::is_safe_link(&url) {
+                        return Command::none();
+                    }
+
+                    if let Err(err) = open_link(&url) {
+                        let text = self.tr.format("open-link-failed", [("url", url.as_str().into()), ("error", err.to_string().into())]);
+                        notices.push((state.messages.len(), text, true));
+                    }
+                }
+            }
             ClientMessage::SendMessage => {
-                if let View::Chat { message, socket, personal_id, .. } = &mut self.view {
-                    if message.is_empty() {
+                if let View::Chat { message, connection, state, notices, show_rules, restored, link_buttons, seen, .. } = &mut self.view {
+                    // errors leave the input as it was, to be fixed
+                    let command = match SlashCommand::parse(message) {
+                        Some(Ok(command)) => command,
+                        Some(Err(err)) => {
+                            notices.push((state.messages.len(), err.message(&self.tr), true));
+                            return Command::none();
+                        }
+                        None => {
+                            if message.is_empty() {
+                                return Command::none();
+                            }
+
+                            if message.len() > MAX_MESSAGE_LEN {
+                                let text = self.tr.format("message-too-long", [("max", MAX_MESSAGE_LEN.into()), ("len", message.len().into())]);
+                                notices.push((state.messages.len(), text, true));
+                                return Command::none();
+                            }
+
+                            let mut send = String::with_capacity(50);
+                            std::mem::swap(message, &mut send);
+
+                            check_sent(connection.send(NetworkMessage::message(0, state.personal_id, send, Vec::new())), &self.tr, notices, state.messages.len());
+                            return Command::none();
+                        }
+                    };
+
+                    match command.message(state) {
+                        Ok(Some(msg)) => check_sent(connection.send(msg), &self.tr, notices, state.messages.len()),
+                        Ok(None) => (),
+                        Err(err) => {
+                            notices.push((state.messages.len(), err.message(&self.tr), true));
+                            return Command::none();
+                        }
+                    }
+
+                    message.clear();
+                    let at = state.messages.len();
+
+                    match command {
+                        SlashCommand::Help => notices.extend(COMMANDS.iter().map(|(name, args, what)| {
+                            (at, format!("/{} {} - {}", name, args, self.tr.tr(what)), false)
+                        })),
+                        SlashCommand::Who => {
+                            let mut users: Vec<_> = state.users.iter().map(|(id, user)| match (state.is_bot(*id), state.is_away(*id)) {
+                                (true, _) => self.tr.format("bot-label", [("name", user.as_str().into())]),
+                                (_, true) => self.tr.format("away-label", [("name", user.as_str().into())]),
+                                _ => user.to_owned(),
+                            }).collect();
+                            users.sort_by_key(|user| user.to_lowercase());
+                            users.insert(0, state.username.to_owned());
+
+                            let text = self.tr.format("who", [("count", users.len().into()), ("names", users.join(", ").into())]);
+                            notices.push((at, text, false));
+                        }
+                        SlashCommand::Rules => *show_rules = true,
+                        SlashCommand::Seen => *seen = !*seen,
+                        SlashCommand::Clear => {
+                            state.messages.clear();
+                            state.divider = None;
+                            notices.clear();
+                            link_buttons.clear();
+                            *restored = 0;
+                        }
+                        SlashCommand::Join(server) => {
+                            self.select_server(server.to_owned());
+                            return self.update_ui(ClientMessage::ConnectAddress(server), clipboard);
+                        }
+                        SlashCommand::Leave => self.select_server(String::new()),
+                        SlashCommand::Quit => return self.update_ui(ClientMessage::CloseRequested, clipboard),
+                        // sent above
+                        SlashCommand::Nick(_) | SlashCommand::Me(_) | SlashCommand::Msg(_) | SlashCommand::Away(_) => (),
+                    }
+                }
+            }
+            ClientMessage::AttachFile => {
+                if let View::Chat { state, transfers, next_transfer, connection, notices, .. } = &mut self.view {
+                    let path = match rfd::FileDialog::new().pick_file() {
+                        Some(path) => path,
+                        None => return Command::none(),
+                    };
+
+                    let mut transfer = match Transfer::upload(&path) {
+                        Ok(transfer) => transfer,
+                        Err(err) => {
+                            notices.push((state.messages.len(), err.message(&self.tr), true));
+                            return Command::none();
+                        }
+                    };
+
+                    let id = *next_transfer;
+                    *next_transfer = next_transfer.wrapping_add(1);
+
+                    let offer = NetworkMessage::file_offer(
+                        id,
+                        state.personal_id,
+                        transfer.size,
+                        transfer.hash,
+                        transfer.name.to_owned(),
+                        transfer.mime.to_owned(),
+                    );
+
+                    if let Err(err) = connection.send(offer.clone()) {
+                        check_sent(Err(err), &self.tr, notices, state.messages.len());
                         return Command::none();
                     }
 
-                    let mut send = String::with_capacity(50);
-                    std::mem::swap(message, &mut send);
+                    state.messages.push((offer, self.username.to_owned()));
+
+                    let command = match upload_next(connection, state.personal_id, id, &transfer) {
+                        Ok(command) => command,
+                        Err(err) => {
+                            transfer.cancel();
+                            check_sent(Err(err), &self.tr, notices, state.messages.len());
+                            Command::none()
+                        }
+                    };
+                    transfers.insert(id, transfer);
+
+                    return command;
+                }
+            }
+            ClientMessage::FileChunkSent(id, sent) => {
+                if let View::Chat { transfers, connection, state, notices, .. } = &mut self.view {
+                    let transfer = match transfers.get_mut(&id) {
+                        Some(transfer) if transfer.state == TransferState::InProgress => transfer,
+                        _ => return Command::none(),
+                    };
+
+                    match sent {
+                        Some(len) => {
+                            transfer.progress += len as u64;
+
+                            match upload_next(connection, state.personal_id, id, transfer) {
+                                Ok(command) => return command,
+                                Err(err) => {
+                                    transfer.cancel();
+                                    check_sent(Err(err), &self.tr, notices, state.messages.len());
+                                }
+                            }
+                        }
+                        None => transfer.cancel(),
+                    }
+                }
+            }
+            ClientMessage::CancelUpload(id) => {
+                if let View::Chat { transfers, connection, state, notices, .. } = &mut self.view {
+                    if let Some(transfer) = transfers.get_mut(&id) {
+                        transfer.cancel();
+                        check_sent(connection.send(NetworkMessage::file_cancel(id, state.personal_id)), &self.tr, notices, state.messages.len());
+                    }
+                }
+            }
+            ClientMessage::RequestFile(id) => {
+                if let View::Chat { transfers, connection, state, notices, .. } = &mut self.view {
+                    if let Some(transfer) = transfers.get_mut(&id) {
+                        transfer.request();
+                        check_sent(connection.send(NetworkMessage::file_request(id, transfer.hash)), &self.tr, notices, state.messages.len());
+                    }
+                }
+            }
+            ClientMessage::SaveFile(id) => {
+                if let View::Chat { transfers, state, notices, .. } = &mut self.view {
+                    if let Some(transfer) = transfers.get(&id) {
+                        let path = rfd::FileDialog::new()
+                            .set_file_name(&transfer.name)
+                            .save_file();
+
+                        if let Some(path) = path {
+                            if let Err(err) = std::fs::write(&path, &transfer.data) {
+                                let path = path.display().to_string();
+                                let text = self.tr.format("save-failed", [("path", path.into()), ("error", err.to_string().into())]);
+                                notices.push((state.messages.len(), text, true));
+                            }
+                        }
+                    }
+                }
+            }
+            ClientMessage::ToggleRules => {
+                if let View::Chat { show_rules, .. } = &mut self.view {
+                    *show_rules = !*show_rules;
+                }
+            }
+            ClientMessage::Pin(id) => {
+                if let View::Chat { connection, state, notices, .. } = &mut self.view {
+                    check_sent(connection.send(NetworkMessage::pin(id)), &self.tr, notices, state.messages.len());
+                }
+            }
+            ClientMessage::Unpin(id) => {
+                if let View::Chat { connection, state, notices, .. } = &mut self.view {
+                    check_sent(connection.send(NetworkMessage::unpin(id)), &self.tr, notices, state.messages.len());
+                }
+            }
+            ClientMessage::TogglePinned => {
+                if let View::Chat { show_pinned, .. } = &mut self.view {
+                    *show_pinned = !*show_pinned;
+                }
+            }
+            ClientMessage::ExportLog => {
+                if let View::Chat { log, state, notices, .. } = &mut self.view {
+                    let path = rfd::FileDialog::new()
+                        .add_filter("Text", &["txt"])
+                        .add_filter("JSON", &["json"])
+                        .add_filter("HTML", &["html"])
+                        .set_file_name("conversation.txt")
+                        .save_file();
+
+                    if let Some(path) = path {
+                        if let Err(err) = log.export(&path) {
+                            let text = self.tr.format("export-failed", [("error", err.into())]);
+                            notices.push((state.messages.len(), text, true));
+                        }
+                    }
+                }
+            }
+            ClientMessage::UpdateSearch(query) => {
+                if let View::Chat { search, .. } = &mut self.view {
+                    search.query = query;
+                }
+            }
+            ClientMessage::SubmitSearch => {
+                if let View::Chat { search, connection, state, notices, .. } = &mut self.view {
+                    match search.request() {
+                        Ok(request) => check_sent(connection.send(request), &self.tr, notices, state.messages.len()),
+                        Err(err) => search.error = Some(err.message(&self.tr)),
+                    }
+                }
+            }
+            ClientMessage::CloseSearch => {
+                if let View::Chat { search, .. } = &mut self.view {
+                    search.close();
+                }
+            }
+            // scrolls to the message when it arrived since joining
+            ClientMessage::JumpTo(id) => {
+                if let View::Chat { search, state, scroll_view, .. } = &mut self.view {
+                    let messages = &state.messages;
+                    let position = messages.iter().position(|(msg, _)| match msg {
+                        NetworkMessage::Message(msg) => msg.id() == id,
+                        _ => false,
+                    });
 
-                    let buf = NetworkMessage::message(*personal_id, send).into_vec();
-                    socket.write_all(&buf).unwrap();
+                    match position {
+                        Some(position) => {
+                            search.highlighted = Some(id);
+                            scroll_view.snap_to(position as f32 / messages.len().saturating_sub(1).max(1) as f32);
+                        }
+                        None => search.error = Some(self.tr.tr("not-in-history")),
+                    }
+                }
+            }
+            ClientMessage::ConnectionError(err) => {
+                if let View::Chat { state, notices, .. } = &mut self.view {
+                    notices.push((state.messages.len(), err, true));
+                }
+            }
+            ClientMessage::Disconnected => {
+                if let View::Chat { state, notices, .. } = &mut self.view {
+                    notices.push((state.messages.len(), self.tr.tr("disconnected"), true));
                 }
             }
             ClientMessage::IncomingMessages(msg) => {
-                if let View::Chat { users, messages, personal_id, .. } = &mut self.view {
-                    match &msg {
-                        NetworkMessage::PersonalId(pid) => {
-                            *personal_id = pid.id();
+                if let View::Chat { state, transfers, next_transfer, connection, server_info, pinned, search, log, joined_as, restored, notices, .. } = &mut self.view {
+                    // known before the state forgets who left
+                    if let NetworkMessage::Message(m) = &msg {
+                        let user = state.name(m.from()).unwrap_or_default().to_owned();
+                        log.append(&LoggedMessage::new(m.id(), user, m.content().to_owned()));
+                    }
+
+                    let msg = match state.receive(msg) {
+                        Update::Unhandled(msg) => msg,
+                        Update::Logged => {
+                            if let Some((msg, from)) = state.messages.last() {
+                                notify::message(&*self.notifier, &self.tr, msg, from, state, self.focused);
+                            }
+
+                            // read as it comes while the window is in sight
+                            if let Some(marker) = state.set_reading(self.focused) {
+                                if let Err(err) = connection.send(marker) {
+                                    notices.push((state.messages.len(), err, true));
+                                }
+                            }
+
+                            return Command::none();
                         }
-                        NetworkMessage::UserList(list) => {
-                            for (id, user) in list.users() {
-                                users.insert(*id, user.to_owned());
+                        Update::Changed => return Command::none(),
+                    };
+
+                    match &msg {
+                        // sent on join and again whenever the owner edits it
+                        NetworkMessage::ServerInfo(info) => {
+                            if !log.is_open() {
+                                *log = MessageLog::open(info.server_id(), joined_as);
+                                *restored = restore(log, state, notices);
                             }
 
-                            messages.push((msg, String::default()));
+                            *server_info = Some(msg);
+                        }
+                        NetworkMessage::Pinned(pin) => {
+                            pinned.push(PinnedMessage {
+                                id: pin.message_id(),
+                                from: pin.from().to_owned(),
+                                content: pin.content().to_owned(),
+                                unpin: iced::button::State::default(),
+                            });
                         }
-                        NetworkMessage::UserJoin(join) => {
-                            users.insert(join.id(), join.name().to_owned());
-                            messages.push((msg, String::default()));
+                        NetworkMessage::Unpin(unpin) => {
+                            pinned.retain(|pin| pin.id != unpin.message_id());
                         }
-                        NetworkMessage::UserLeave(leave) => {
-                            let user = users.remove(&leave.id()).unwrap();
-                            messages.push((msg, user));
+                        NetworkMessage::SearchResult(_) | NetworkMessage::SearchEnd(_) => {
+                            search.receive(&msg);
                         }
-                        NetworkMessage::Message(m) => {
-                            let from = m.from();
+                        // transfers are tracked under our own ids, the ones the server answers with
+                        NetworkMessage::FileOffer(offer) => {
+                            let id = *next_transfer;
+                            *next_transfer = next_transfer.wrapping_add(1);
+
+                            let mut transfer = Transfer::download(
+                                offer.size(),
+                                *offer.hash(),
+                                offer.name().to_owned(),
+                                offer.mime().to_owned(),
+                            );
 
-                            let user = if from == *personal_id {
-                                self.username.to_owned()
-                            } else {
-                                users.get(&from).unwrap().to_owned()
-                            };
+                            if transfer.auto_request() {
+                                transfer.request();
+                                check_sent(connection.send(NetworkMessage::file_request(id, transfer.hash)), &self.tr, notices, state.messages.len());
+                            }
+
+                            let user = state.name(offer.from()).unwrap_or_default().to_owned();
+                            let offer = NetworkMessage::file_offer(
+                                id,
+                                offer.from(),
+                                offer.size(),
+                                *offer.hash(),
+                                offer.name().to_owned(),
+                                offer.mime().to_owned(),
+                            );
 
-                            messages.push((msg, user));
+                            transfers.insert(id, transfer);
+                            state.messages.push((offer, user));
+                        }
+                        NetworkMessage::FileChunk(chunk) => {
+                            if let Some(transfer) = transfers.get_mut(&chunk.transfer_id()) {
+                                transfer.receive(chunk.offset(), chunk.data());
+                            }
+                        }
+                        NetworkMessage::FileComplete(end) => {
+                            if let Some(transfer) = transfers.get_mut(&end.transfer_id()) {
+                                match transfer.upload {
+                                    true => transfer.state = TransferState::Complete,
+                                    false => transfer.complete(),
+                                }
+                            }
+                        }
+                        NetworkMessage::FileCancel(end) => {
+                            if let Some(transfer) = transfers.get_mut(&end.transfer_id()) {
+                                transfer.cancel();
+                            }
                         }
                         _ => {}
                     }
                 }
             }
+            ClientMessage::ToggleSettings => {
+                self.settings.open = !self.settings.open;
+            }
+            ClientMessage::SelectTheme(index) => {
+                if index < self.themes.len() {
+                    self.theme = index;
+                    self.preferences.theme = self.themes[index].name.to_owned();
+                    self.preferences.save();
+                }
+            }
+            // picks up changes to the themes file, the current theme stays if it is still there
+            ClientMessage::ReloadThemes => {
+                let name = self.current_theme().name.to_owned();
+                let (themes, error) = settings::load_themes();
+
+                self.theme = themes.iter().position(|theme| theme.name == name).unwrap_or_default();
+                self.themes = themes;
+                self.settings.error = error;
+            }
+            ClientMessage::TextSize(size) => {
+                self.preferences.text_size = size.clamp(MIN_TEXT_SIZE, MAX_TEXT_SIZE);
+                self.preferences.save();
+            }
+            ClientMessage::SelectLocale(locale) => {
+                self.locale = locale;
+                self.tr = Translator::new(locale.unwrap_or_else(i18n::detect));
+                self.preferences.locale = locale.map(String::from);
+                self.preferences.save();
+            }
+            ClientMessage::ToggleNotifications => {
+                self.preferences.notifications = !self.preferences.notifications;
+                self.notifier = super::notifier(self.preferences.notifications);
+                self.preferences.save();
+            }
+            // too frequent to save each time, kept for when something else is
+            ClientMessage::WindowResized(width, height) => {
+                self.preferences.resized(width, height);
+            }
+            ClientMessage::CloseRequested => {
+                if let View::Chat { connection, .. } = &self.view {
+                    connection.close();
+                }
+
+                self.preferences.save();
+                self.quit = true;
+            }
         }
 
         Command::none()
     }
 }
 
-fn update_server_list(servers: &mut Vec<(String, SocketAddr)>) {
-    use protocol::multicast::MulticastMessage;
+// puts the end of the last conversation before what came in since joining, returns how many
+fn restore(log: &MessageLog, state: &mut ChatState, notices: &mut [(usize, String, bool)]) -> usize {
+    let logged = log.load();
+    let restored: Vec<_> = logged.into_iter()
+        .rev()
+        .take(RESTORED_MESSAGES)
+        .rev()
+        .map(|logged| (NetworkMessage::message(logged.id, 0, logged.content, Vec::new()), logged.from))
+        .collect();
+    let count = restored.len();
 
-    servers.clear();
+    state.messages.splice(0..0, restored);
+    state.divider = state.divider.map(|divider| divider + count);
+    for (at, _, _) in notices.iter_mut() {
+        *at += count;
+    }
 
-    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-    let buf: Vec<_> = MulticastMessage::ping().into();
+    count
+}
 
-    let addr = format!("{}:{}", protocol::network::MULTICAST_ADDRESS, protocol::network::MULTICAST_PORT);
+fn upload_next(connection: &Connection, personal_id: u32, id: u32, transfer: &Transfer) -> Result<Command<ClientMessage>, String> {
+    let offset = transfer.progress;
 
-    socket.set_read_timeout(Some(Duration::from_millis(150))).unwrap();
-    socket.send_to(&buf, addr).unwrap();
-    let mut buf = [0; 64];
+    if offset >= transfer.size {
+        connection.send(NetworkMessage::file_complete(id, personal_id))?;
 
-    while let Ok((buf_len, mut addr)) = socket.recv_from(&mut buf) {
-        let message: MulticastMessage = match buf[..buf_len].try_into() {
-            Err(msg) => {
-                println!("{:?}", msg);
-                continue;
-            }
-            Ok(message) => message,
-        };
+        return Ok(Command::none());
+    }
 
-        if let Some((name, port)) = message.content() {
-            addr.set_port(*port);
-            servers.push((name.to_owned(), addr));
-        }
+    let start = offset as usize;
+    let end = transfer.data.len().min(start + protocol::file::CHUNK_LEN);
+    let data = transfer.data[start..end].to_vec();
+    let connection = connection.try_clone().map_err(|err| err.to_string())?;
+
+    Ok(Command::perform(send_chunk(connection, id, offset, data), |msg| msg))
+}
+
+// sends only fail once the connection is gone, which is said among the messages
+fn check_sent(result: Result<(), String>, tr: &Translator, notices: &mut Vec<(usize, String, bool)>, at: usize) {
+    if let Err(err) = result {
+        notices.push((at, tr.format("send-failed", [("error", err.into())]), true));
     }
+}
 
-    servers.sort_by(|(lhs, _), (rhs, _)| {
-        let mut lhs = lhs.chars();
-        let mut rhs = rhs.chars();
+async fn send_chunk(connection: Connection, id: u32, offset: u64, data: Vec<u8>) -> ClientMessage {
+    let len = data.len();
+    let sent = connection.send(NetworkMessage::file_chunk(id, offset, data)).ok();
 
-        loop {
-            let cple = (lhs.next(), rhs.next());
+    ClientMessage::FileChunkSent(id, sent.map(|_| len))
+}
 
-            match cple {
-                (Some(l), Some(r)) => {
-                    let ord = l.to_lowercase().cmp(r.to_lowercase());
-                    if !ord.is_eq() {
-                        break ord;
-                    }
-                }
-                (Some(_), None) => break std::cmp::Ordering::Greater,
-                (None, Some(_)) => break std::cmp::Ordering::Less,
-                (None, None) => break std::cmp::Ordering::Equal,
-            }
-        }
-    });
+// whatever the system opens web and mail links with
+fn open_link(url: &str) -> std::io::Result<std::process::Child> {
+    let (program, args): (&str, &[&str]) = match std::env::consts::OS {
+        "windows" => ("rundll32", &["url.dll,FileProtocolHandler"]),
+        "macos" => ("open", &[]),
+        _ => ("xdg-open", &[]),
+    };
+
+    std::process::Command::new(program).args(args).arg(url).spawn()
+}
+
+async fn resolve(addr: String) -> Result<SocketAddr, String> {
+    connection::resolve(&addr)
+}
+
+// shared so the message can be cloned, the handle is cloned out of it
+async fn open(addr: SocketAddr, username: String, secret: [u8; SECRET_LEN]) -> Result<Arc<Connection>, String> {
+    Connection::open(&addr, &username, false, secret)
+        .map(Arc::new)
+        .map_err(|err| err.to_string())
 }
@@ -1,17 +1,19 @@
-use iced_native::{futures::stream::BoxStream, subscription::Recipe};
-use protocol::network::NetworkMessage;
+use super::ClientMessage;
+
+use client_core::connection::Connection;
+use iced_native::futures::{stream::{self, BoxStream}, StreamExt};
+use iced_native::subscription::Recipe;
 use std::hash::{Hasher};
-use std::io::Read;
 
 pub struct IncomingMessages {
-    pub stream: std::net::TcpStream,
+    pub connection: Result<Connection, String>,
 }
 
 impl<H, I> Recipe<H, I> for IncomingMessages
 where
     H: Hasher,
 {
-    type Output = NetworkMessage;
+    type Output = ClientMessage;
 
     fn hash(&self, _state: &mut H) {
     }
@@ -20,16 +22,17 @@ where
         self: Box<Self>,
         _input: BoxStream<I>,
     ) -> BoxStream<Self::Output> {
-        let stream = self.stream;
-
-        Box::pin(iced_native::futures::stream::unfold(stream, |mut stream| async move {
-            let mut buf = [0; 1024];
-            let len = stream.read(&mut buf).unwrap();
-
-            let slice = &buf[..len];
-            let msg = NetworkMessage::from_slice(slice).unwrap();
+        let events = match self.connection.and_then(|connection| connection.events().map_err(|err| err.to_string())) {
+            Ok(events) => events,
+            Err(err) => {
+                return Box::pin(stream::iter(vec![ClientMessage::ConnectionError(err), ClientMessage::Disconnected]));
+            }
+        };
 
-            Some((msg, stream))
-        }))
+        // the stream ends with the connection, after the error if any, and says so
+        Box::pin(events.map(|msg| match msg {
+            Ok(msg) => ClientMessage::IncomingMessages(msg),
+            Err(err) => ClientMessage::ConnectionError(err),
+        }).chain(stream::once(async { ClientMessage::Disconnected })))
     }
 }
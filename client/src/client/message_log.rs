@@ -0,0 +1,230 @@
+use super::search::format_time;
+use protocol::file;
+use protocol::multicast::SERVER_ID_LEN;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// shown again when reconnecting, the export has everything
+pub const RESTORED_MESSAGES: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedMessage {
+    pub id: u32,
+    // unix seconds, when it was received
+    pub time: u64,
+    pub from: String,
+    pub content: String,
+}
+
+impl LoggedMessage {
+    pub fn new(id: u32, from: String, content: String) -> Self {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        Self { id, time, from, content }
+    }
+}
+
+// conversation with one server, kept in the data directory under the id the server sends in ServerInfo
+// so it follows the server to a new address, closed until then
+#[derive(Debug, Default)]
+pub struct MessageLog {
+    path: Option<PathBuf>,
+}
+
+impl MessageLog {
+    // logs used to be named after the address, `joined_as` picks up the one from before
+    pub fn open(server_id: &[u8; SERVER_ID_LEN], joined_as: &str) -> Self {
+        let path = log_path(&file::to_hex(server_id));
+
+        if let (Some(path), Some(old)) = (&path, log_path(joined_as)) {
+            if !path.exists() && old.exists() {
+                if let Err(err) = fs::rename(&old, path) {
+                    println!("{}: {}", old.display(), err);
+                }
+            }
+        }
+
+        Self { path }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.path.is_some()
+    }
+
+    // [id] [time] [from] [content], tab separated and escaped
+    pub fn load(&self) -> Vec<LoggedMessage> {
+        let content = self.path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+
+        content.lines().filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let message = (|| Some(LoggedMessage {
+                id: fields.next()?.parse().ok()?,
+                time: fields.next()?.parse().ok()?,
+                from: unescape(fields.next()?),
+                content: unescape(fields.next()?),
+            }))();
+
+            if message.is_none() {
+                println!("Message log: Skipping {}", line);
+            }
+
+            message
+        }).collect()
+    }
+
+    pub fn append(&self, message: &LoggedMessage) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let line = format!("{}\t{}\t{}\t{}\n", message.id, message.time, escape(&message.from), escape(&message.content));
+        let written = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+
+        if let Err(err) = written {
+            println!("{}: {}", path.display(), err);
+        }
+    }
+
+    // the format follows the extension, plain text unless .json or .html
+    pub fn export(&self, to: &Path) -> Result<(), String> {
+        let messages = self.load();
+        let extension = to.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+
+        let content = match extension.to_lowercase().as_str() {
+            "json" => to_json(&messages),
+            "html" | "htm" => to_html(&messages),
+            _ => to_text(&messages),
+        };
+
+        fs::write(to, content).map_err(|err| format!("{}: {}", to.display(), err))
+    }
+}
+
+fn log_path(name: &str) -> Option<PathBuf> {
+    let name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+
+    dirs_next::data_dir().map(|dir| dir.join("messaging").join("logs").join(format!("{}.log", name)))
+}
+
+fn escape(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+fn to_text(messages: &[LoggedMessage]) -> String {
+    messages.iter()
+        .map(|message| format!("[{}] {}: {}\n", format_time(message.time), message.from, message.content))
+        .collect()
+}
+
+fn to_json(messages: &[LoggedMessage]) -> String {
+    let json = |text: &str| text.chars().fold(String::with_capacity(text.len() + 2), |mut json, c| {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+
+        json
+    });
+
+    let entries: Vec<_> = messages.iter().map(|message| format!(
+        "  {{\"id\": {}, \"time\": {}, \"from\": \"{}\", \"content\": \"{}\"}}",
+        message.id,
+        message.time,
+        json(&message.from),
+        json(&message.content),
+    )).collect();
+
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+fn to_html(messages: &[LoggedMessage]) -> String {
+    let html = |text: &str| text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>");
+
+    let rows: String = messages.iter().map(|message| format!(
+        "<tr><td class=\"time\">{}</td><td class=\"from\">{}</td><td>{}</td></tr>\n",
+        format_time(message.time),
+        html(&message.from),
+        html(&message.content),
+    )).collect();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Conversation</title>\n\
+        <style>body {{ font-family: sans-serif; }} td {{ padding: 2px 8px; vertical-align: top; }} .time {{ color: #888; }} .from {{ font-weight: bold; }}</style>\n\
+        </head>\n<body>\n<table>\n{}</table>\n</body>\n</html>\n",
+        rows,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, to_html, to_json, unescape, LoggedMessage};
+
+    fn message(from: &str, content: &str) -> LoggedMessage {
+        LoggedMessage { id: 3, time: 0, from: from.to_owned(), content: content.to_owned() }
+    }
+
+    #[test]
+    fn escapes_and_unescapes() {
+        let field = "tab\there\nline\\ and \\n \r";
+
+        assert!(!escape(field).contains(['\t', '\n', '\r']));
+        assert_eq!(unescape(&escape(field)), field);
+        assert_eq!(escape("a\tb\\"), "a\\tb\\\\");
+        assert_eq!(unescape("end\\"), "end\\");
+    }
+
+    #[test]
+    fn escapes_json() {
+        let json = to_json(&[message("\"quoted\"", "back\\slash\nline\u{1}")]);
+
+        assert_eq!(json, "[\n  {\"id\": 3, \"time\": 0, \"from\": \"\\\"quoted\\\"\", \"content\": \"back\\\\slash\\nline\\u0001\"}\n]\n");
+    }
+
+    #[test]
+    fn escapes_html() {
+        let html = to_html(&[message("<b>", "a & b \"c\"\nd")]);
+
+        assert!(html.contains("<td class=\"from\">&lt;b&gt;</td><td>a &amp; b &quot;c&quot;<br>d</td>"));
+        assert!(!html.contains("<b>"));
+    }
+}
@@ -1,20 +1,76 @@
 mod incoming_messages;
+mod message_log;
+mod preferences;
+mod saved_servers;
+mod search;
+mod server_browser;
+mod settings;
+mod transfer;
 mod events;
 mod ui;
 
-use std::net::{SocketAddr, TcpStream};
+use std::net::SocketAddr;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 
 use iced::{
     Element, Application, Command, Clipboard, Subscription, Color, executor,
 };
 
-use protocol::network::NetworkMessage;
+use client_core::connection::Connection;
+use client_core::discovery::ServerEvent;
+use client_core::i18n::{self, Translator};
+use client_core::notify::{Desktop, Notifier, Silent};
+use client_core::state::ChatState;
+use client_core::theme::Theme;
+use protocol::multicast::{Announcement, Discovery};
+use protocol::network::{NetworkMessage, SECRET_LEN};
+use message_log::MessageLog;
+pub use preferences::Preferences;
+use search::Search;
+use settings::Settings;
+use transfer::Transfer;
+
+#[derive(Debug, Default)]
+pub struct Flags {
+    pub discovery: Discovery,
+    // host:port given on the command line, joined once the username is set
+    pub server: Option<String>,
+    pub preferences: Preferences,
+    // proves who we are across names and sessions
+    pub secret: [u8; SECRET_LEN],
+}
 
 #[derive(Default)]
 pub struct Client {
     view: View,
     username: String,
+    secret: [u8; SECRET_LEN],
+    discovery: Discovery,
+    // saved whenever any of it changes, and on the way out
+    preferences: Preferences,
+    autoconnect: Option<String>,
+    quit: bool,
+    notifier: Box<dyn Notifier>,
+    // notifications are only for when the window is out of sight
+    focused: bool,
+    // built in first, the user's after
+    themes: Vec<Theme>,
+    theme: usize,
+    // picked over the system's language
+    locale: Option<&'static str>,
+    tr: Translator,
+    settings: Settings,
+}
+
+// pinned by a moderator, listed above the chat log
+#[derive(Debug)]
+pub struct PinnedMessage {
+    pub id: u32,
+    pub from: String,
+    pub content: String,
+    pub unpin: iced::button::State,
 }
 
 enum View {
@@ -24,16 +80,50 @@ enum View {
     },
     SelectServer {
         buttons: Vec<iced::button::State>,
-        servers: Vec<(String, SocketAddr)>,
+        // last time each server was heard from
+        servers: Vec<(Announcement, SocketAddr, Instant)>,
+        generation: u32,
+        address_input: iced::text_input::State,
+        address: String,
+        connect: iced::button::State,
+        // (connect, favourite) for each saved server
+        saved_buttons: Vec<(iced::button::State, iced::button::State)>,
+        error: Option<String>,
     },
     Chat {
-        messages: Vec<(NetworkMessage, String)>,
-        users: HashMap<u32, String>,
+        state: ChatState,
         scroll_view: iced::scrollable::State,
         input: iced::text_input::State,
-        socket: TcpStream,
-        personal_id: u32,
+        attach: iced::button::State,
+        export: iced::button::State,
+        // conversation kept on disk, opened once ServerInfo tells which server this is,
+        // the first `restored` messages come from it
+        log: MessageLog,
+        joined_as: String,
+        restored: usize,
+        transfers: HashMap<u32, Transfer>,
+        next_transfer: u32,
+        connection: Connection,
         message: String,
+        // latest ServerInfo, shown as a banner above the messages
+        server_info: Option<NetworkMessage>,
+        rules_button: iced::button::State,
+        show_rules: bool,
+        pinned: Vec<PinnedMessage>,
+        pinned_button: iced::button::State,
+        show_pinned: bool,
+        // pin button of each message, by message id
+        pin_buttons: HashMap<u32, iced::button::State>,
+        // by index in the messages then order in the message
+        link_buttons: HashMap<(usize, usize), iced::button::State>,
+        preview_button: iced::button::State,
+        // the message being typed as it will look, above the input
+        preview: bool,
+        // who has read up to each message, shown under it
+        seen: bool,
+        search: Search,
+        // our own feedback among the messages: (index in the messages it comes before, text, error)
+        notices: Vec<(usize, String, bool)>,
     },
 }
 
@@ -42,23 +132,83 @@ pub enum ClientMessage {
     UpdateUsername(String),
     SubmitUsername,
     RefreshServerList,
+    ServerEvent(ServerEvent),
     SelectServer(SocketAddr),
+    UpdateAddress(String),
+    ConnectAddress(String),
+    AddressResolved(String, Result<SocketAddr, String>),
+    Connected(String, Result<Arc<Connection>, String>),
+    ToggleFavourite(String),
     UpdateMessage(String),
+    CompleteMessage,
+    WindowFocused(bool),
+    TogglePreview,
+    OpenLink(String),
     SendMessage,
+    AttachFile,
+    ExportLog,
+    FileChunkSent(u32, Option<usize>),
+    CancelUpload(u32),
+    RequestFile(u32),
+    SaveFile(u32),
+    ToggleRules,
+    Pin(u32),
+    Unpin(u32),
+    TogglePinned,
+    UpdateSearch(String),
+    SubmitSearch,
+    CloseSearch,
+    JumpTo(u32),
     IncomingMessages(NetworkMessage),
+    ConnectionError(String),
+    Disconnected,
+    WindowResized(u32, u32),
+    CloseRequested,
+    ToggleSettings,
+    SelectTheme(usize),
+    ReloadThemes,
+    TextSize(u16),
+    SelectLocale(Option<&'static str>),
+    ToggleNotifications,
 }
 
 impl Application for Client {
     type Executor = executor::Default;
     type Message = ClientMessage;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        (Default::default(), Command::none())
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let mut preferences = flags.preferences;
+        preferences.text_size = preferences.text_size.clamp(settings::MIN_TEXT_SIZE, settings::MAX_TEXT_SIZE);
+
+        let (themes, error) = settings::load_themes();
+        // a theme that is gone falls back to the default one
+        let theme = themes.iter().position(|theme| theme.name == preferences.theme).unwrap_or_default();
+        preferences.theme = themes[theme].name.to_owned();
+        let locale = preferences.locale.as_deref().map(i18n::supported);
+
+        (Self {
+            username: preferences.username.to_owned(),
+            secret: flags.secret,
+            discovery: flags.discovery,
+            autoconnect: flags.server,
+            notifier: notifier(preferences.notifications),
+            focused: true,
+            themes,
+            theme,
+            locale,
+            tr: Translator::new(locale.unwrap_or_else(i18n::detect)),
+            settings: Settings { error, ..Default::default() },
+            preferences,
+            ..Default::default()
+        }, Command::none())
     }
 
     fn title(&self) -> String {
-        String::from("messaging")
+        match &self.view {
+            View::Chat { state, .. } if state.unread() > 0 => format!("messaging ({})", state.unread()),
+            _ => String::from("messaging"),
+        }
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
@@ -76,6 +226,24 @@ impl Application for Client {
     fn background_color(&self) -> Color {
         self.get_background_color()
     }
+
+    fn should_exit(&self) -> bool {
+        self.quit
+    }
+}
+
+impl Client {
+    fn current_theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+}
+
+// turned off, notifications go nowhere
+fn notifier(enabled: bool) -> Box<dyn Notifier> {
+    match enabled {
+        true => Box::new(Desktop),
+        false => Box::new(Silent),
+    }
 }
 
 impl Default for View {
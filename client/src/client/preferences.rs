@@ -0,0 +1,275 @@
+use super::saved_servers::{SavedServers, MAX_RECENT};
+use super::settings::DEFAULT_TEXT_SIZE;
+
+use std::fs;
+use std::path::PathBuf;
+
+// smaller sizes come from a minimized window, larger ones from a file edited by hand
+const MIN_WINDOW: (u32, u32) = (320, 240);
+const MAX_WINDOW: (u32, u32) = (16384, 16384);
+
+// what the client remembers from one launch to the next
+#[derive(Debug)]
+pub struct Preferences {
+    path: Option<PathBuf>,
+    pub username: String,
+    // by name, the default theme when there is none by that name anymore
+    pub theme: String,
+    // the system's language when there is none
+    pub locale: Option<String>,
+    pub text_size: u16,
+    pub notifications: bool,
+    // width and height, left to iced until the window is first resized
+    pub window: Option<(u32, u32)>,
+    pub servers: SavedServers,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            path: None,
+            username: String::new(),
+            theme: String::new(),
+            locale: None,
+            text_size: DEFAULT_TEXT_SIZE,
+            notifications: true,
+            window: None,
+            servers: SavedServers::default(),
+        }
+    }
+}
+
+impl Preferences {
+    // messaging/settings in the config directory
+    pub fn load() -> Self {
+        match dirs_next::config_dir() {
+            Some(dir) => Self::load_from(dir.join("messaging").join("settings")),
+            None => Self::default(),
+        }
+    }
+
+    // [key] [value] on each line, saved back to the same file
+    pub fn load_from(path: PathBuf) -> Self {
+        // servers had a file of their own before, in the same format
+        let content = fs::read_to_string(&path)
+            .or_else(|_| fs::read_to_string(path.with_file_name("servers")))
+            .unwrap_or_default();
+
+        let mut preferences = Self { path: Some(path), ..Default::default() };
+
+        for line in content.lines() {
+            if !preferences.read(line) {
+                println!("Settings: Skipping {}", line);
+            }
+        }
+
+        preferences
+    }
+
+    // returns whether the line made sense
+    fn read(&mut self, line: &str) -> bool {
+        let (key, value) = match line.split_once(' ') {
+            Some(line) => line,
+            None => return false,
+        };
+
+        match key {
+            "username" => self.username = value.to_owned(),
+            "theme" => self.theme = value.to_owned(),
+            "locale" => self.locale = Some(value.to_owned()),
+            "text-size" => match value.parse() {
+                Ok(size) => self.text_size = size,
+                Err(_) => return false,
+            },
+            "notifications" => match value {
+                "on" => self.notifications = true,
+                "off" => self.notifications = false,
+                _ => return false,
+            },
+            // [width]x[height]
+            "window" => match value.split_once('x').and_then(|(width, height)| Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))) {
+                Some((width, height)) => self.window = Some((
+                    width.clamp(MIN_WINDOW.0, MAX_WINDOW.0),
+                    height.clamp(MIN_WINDOW.1, MAX_WINDOW.1),
+                )),
+                None => return false,
+            },
+            "favourite" => self.servers.favourites.push(value.to_owned()),
+            "recent" if self.servers.recent.len() < MAX_RECENT => self.servers.recent.push(value.to_owned()),
+            _ => return false,
+        }
+
+        true
+    }
+
+    // the size to open with next time, unless the window was only minimized
+    pub fn resized(&mut self, width: u32, height: u32) {
+        if width >= MIN_WINDOW.0 && height >= MIN_WINDOW.1 {
+            self.window = Some((width, height));
+        }
+    }
+
+    // written next to the file then moved over it, a crash midway leaves the previous settings
+    pub fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let mut content = match self.username.is_empty() {
+            true => String::new(),
+            false => format!("username {}\n", self.username),
+        };
+
+        content.push_str(&format!("theme {}\n", self.theme));
+
+        if let Some(locale) = &self.locale {
+            content.push_str(&format!("locale {}\n", locale));
+        }
+
+        content.push_str(&format!("text-size {}\n", self.text_size));
+        content.push_str(&format!("notifications {}\n", match self.notifications {
+            true => "on",
+            false => "off",
+        }));
+
+        if let Some((width, height)) = self.window {
+            content.push_str(&format!("window {}x{}\n", width, height));
+        }
+
+        let servers: String = self.servers.favourites.iter().map(|addr| format!("favourite {}\n", addr))
+            .chain(self.servers.recent.iter().map(|addr| format!("recent {}\n", addr)))
+            .collect();
+        content.push_str(&servers);
+
+        let temp = path.with_extension("tmp");
+        let saved = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temp, content))
+            .and_then(|_| fs::rename(&temp, path));
+
+        if let Err(err) = saved {
+            println!("{}: {}", path.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Preferences;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("messaging-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn loads_every_key() {
+        let path = test_dir("preferences_load").join("settings");
+        fs::write(&path, "username Alice\ntheme Light\nlocale fr\ntext-size 24\nnotifications off\nwindow 800x600\nfavourite lab:5359\nrecent 10.0.0.2:5359\n").unwrap();
+
+        let preferences = Preferences::load_from(path);
+
+        assert_eq!(preferences.username, "Alice");
+        assert_eq!(preferences.theme, "Light");
+        assert_eq!(preferences.locale.as_deref(), Some("fr"));
+        assert_eq!(preferences.text_size, 24);
+        assert!(!preferences.notifications);
+        assert_eq!(preferences.window, Some((800, 600)));
+        assert_eq!(preferences.servers.favourites, ["lab:5359"]);
+        assert_eq!(preferences.servers.recent, ["10.0.0.2:5359"]);
+    }
+
+    #[test]
+    fn saves_and_reloads() {
+        let path = test_dir("preferences_save").join("settings");
+        let mut preferences = Preferences::load_from(path.clone());
+
+        preferences.username = String::from("Alice");
+        preferences.locale = Some(String::from("fr"));
+        preferences.notifications = false;
+        preferences.resized(1024, 768);
+        preferences.servers.toggle_favourite("lab:5359");
+        preferences.servers.add_recent("10.0.0.2:5359");
+        preferences.save();
+
+        assert!(!path.with_extension("tmp").exists());
+
+        let reloaded = Preferences::load_from(path);
+
+        assert_eq!(reloaded.username, "Alice");
+        assert_eq!(reloaded.locale.as_deref(), Some("fr"));
+        assert!(!reloaded.notifications);
+        assert_eq!(reloaded.window, Some((1024, 768)));
+        assert_eq!(reloaded.servers.favourites, ["lab:5359"]);
+        assert_eq!(reloaded.servers.recent, ["10.0.0.2:5359"]);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let path = test_dir("preferences_malformed").join("settings");
+        fs::write(&path, "username Alice
+nonsense
+text-size big
+notifications maybe
+window 800by600
+colour blue
+theme Light
+").unwrap();
+
+        let preferences = Preferences::load_from(path);
+
+        assert_eq!(preferences.username, "Alice");
+        assert_eq!(preferences.theme, "Light");
+        assert_eq!(preferences.text_size, Preferences::default().text_size);
+        assert!(preferences.notifications);
+        assert_eq!(preferences.window, None);
+    }
+
+    #[test]
+    fn keeps_window_sizes_sensible() {
+        let path = test_dir("preferences_window").join("settings");
+        fs::write(&path, "window 0x0
+").unwrap();
+
+        let mut preferences = Preferences::load_from(path);
+        assert_eq!(preferences.window, Some((320, 240)));
+
+        preferences.resized(1024, 768);
+        // minimized
+        preferences.resized(0, 0);
+        assert_eq!(preferences.window, Some((1024, 768)));
+    }
+
+    #[test]
+    fn picks_up_the_old_servers_file() {
+        let dir = test_dir("preferences_migration");
+        fs::write(dir.join("servers"), "favourite lab:5359
+recent 10.0.0.2:5359
+").unwrap();
+
+        let preferences = Preferences::load_from(dir.join("settings"));
+        assert_eq!(preferences.servers.favourites, ["lab:5359"]);
+        assert_eq!(preferences.servers.recent, ["10.0.0.2:5359"]);
+
+        // the settings file wins once there is one
+        preferences.save();
+        fs::write(dir.join("servers"), "favourite other:5359
+").unwrap();
+        assert_eq!(Preferences::load_from(dir.join("settings")).servers.favourites, ["lab:5359"]);
+    }
+
+    #[test]
+    fn missing_file_is_the_defaults() {
+        let preferences = Preferences::load_from(test_dir("preferences_missing").join("settings"));
+
+        assert!(preferences.username.is_empty());
+        assert!(preferences.notifications);
+        assert_eq!(preferences.window, None);
+    }
+}
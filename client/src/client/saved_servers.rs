@@ -0,0 +1,67 @@
+// most recent first, older entries fall off
+pub const MAX_RECENT: usize = 8;
+
+// servers entered by hand or joined before, kept as typed so names resolve again
+// saved with the rest of the preferences
+#[derive(Debug, Default)]
+pub struct SavedServers {
+    pub favourites: Vec<String>,
+    pub recent: Vec<String>,
+}
+
+impl SavedServers {
+    pub fn is_favourite(&self, addr: &str) -> bool {
+        self.favourites.iter().any(|favourite| favourite == addr)
+    }
+
+    pub fn toggle_favourite(&mut self, addr: &str) {
+        match self.favourites.iter().position(|favourite| favourite == addr) {
+            Some(index) => {
+                self.favourites.remove(index);
+            }
+            None => self.favourites.push(addr.to_owned()),
+        }
+    }
+
+    pub fn add_recent(&mut self, addr: &str) {
+        self.recent.retain(|recent| recent != addr);
+        self.recent.insert(0, addr.to_owned());
+        self.recent.truncate(MAX_RECENT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SavedServers, MAX_RECENT};
+
+    #[test]
+    fn toggles_favourites() {
+        let mut servers = SavedServers::default();
+
+        servers.toggle_favourite("lab:5359");
+        servers.toggle_favourite("home:5359");
+        assert!(servers.is_favourite("lab:5359"));
+
+        servers.toggle_favourite("lab:5359");
+        assert!(!servers.is_favourite("lab:5359"));
+        assert_eq!(servers.favourites, ["home:5359"]);
+    }
+
+    #[test]
+    fn keeps_the_latest_recent_servers() {
+        let mut servers = SavedServers::default();
+
+        for index in 0..MAX_RECENT + 2 {
+            servers.add_recent(&format!("10.0.0.{}:5359", index));
+        }
+
+        assert_eq!(servers.recent.len(), MAX_RECENT);
+        assert_eq!(servers.recent[0], format!("10.0.0.{}:5359", MAX_RECENT + 1));
+
+        // joining again moves it to the front instead of listing it twice
+        servers.add_recent("10.0.0.5:5359");
+        assert_eq!(servers.recent.len(), MAX_RECENT);
+        assert_eq!(servers.recent[0], "10.0.0.5:5359");
+        assert_eq!(servers.recent.iter().filter(|recent| *recent == "10.0.0.5:5359").count(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+use client_core::i18n::Translator;
+use protocol::network::{NetworkMessage, MAX_NAME_LEN, MAX_QUERY_LEN};
+
+// the server sends at most 50 anyway
+pub const SEARCH_LIMIT: u16 = 50;
+
+pub struct SearchHit {
+    pub id: u32,
+    pub time: u64,
+    pub from: String,
+    pub content: String,
+    pub button: iced::button::State,
+}
+
+// why a search was not sent, worded by `message`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchError {
+    Empty,
+    QueryTooLong,
+    NameTooLong,
+    // the date as it was typed
+    InvalidDate(String),
+}
+
+impl SearchError {
+    pub fn message(&self, tr: &Translator) -> String {
+        match self {
+            Self::Empty => tr.tr("search-empty"),
+            Self::QueryTooLong => tr.format("search-too-long", [("max", MAX_QUERY_LEN.into())]),
+            Self::NameTooLong => tr.format("search-name-too-long", [("max", MAX_NAME_LEN.into())]),
+            Self::InvalidDate(date) => tr.format("invalid-date", [("date", date.as_str().into())]),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Search {
+    pub input: iced::text_input::State,
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+    // matches on the server, None until the last search is answered
+    pub total: Option<u32>,
+    // searches sent and not answered yet, only the last one is shown
+    pending: u32,
+    pub error: Option<String>,
+    // message jumped to from a hit
+    pub highlighted: Option<u32>,
+    pub results_scroll: iced::scrollable::State,
+    pub close_button: iced::button::State,
+}
+
+impl Search {
+    pub fn is_open(&self) -> bool {
+        self.pending > 0 || self.total.is_some() || self.error.is_some()
+    }
+
+    // words to find, narrowed by `from:name`, `before:yyyy-mm-dd` and `after:yyyy-mm-dd`
+    pub fn request(&mut self) -> Result<NetworkMessage, SearchError> {
+        let mut words = Vec::new();
+        let mut from = String::new();
+        let mut before = 0;
+        let mut after = 0;
+
+        for word in self.query.split_whitespace() {
+            match word.split_once(':') {
+                Some(("from", name)) => from = name.to_owned(),
+                Some(("before", date)) => before = parse_date(date)?,
+                // the server excludes the bound, the day itself is included
+                Some(("after", date)) => after = parse_date(date)?.saturating_sub(1),
+                _ => words.push(word),
+            }
+        }
+
+        let query = words.join(" ");
+
+        if query.is_empty() && from.is_empty() && before == 0 && after == 0 {
+            return Err(SearchError::Empty);
+        }
+
+        if query.len() > MAX_QUERY_LEN {
+            return Err(SearchError::QueryTooLong);
+        }
+
+        if from.len() > MAX_NAME_LEN {
+            return Err(SearchError::NameTooLong);
+        }
+
+        self.pending += 1;
+        self.hits.clear();
+        self.total = None;
+        self.error = None;
+
+        Ok(NetworkMessage::search_request(query, from, before, after, SEARCH_LIMIT))
+    }
+
+    pub fn receive(&mut self, msg: &NetworkMessage) {
+        match msg {
+            NetworkMessage::SearchResult(result) if self.pending == 1 => self.hits.push(SearchHit {
+                id: result.message_id(),
+                time: result.time(),
+                from: result.from().to_owned(),
+                content: result.content().to_owned(),
+                button: iced::button::State::default(),
+            }),
+            NetworkMessage::SearchEnd(end) => {
+                self.pending = self.pending.saturating_sub(1);
+
+                if self.pending == 0 {
+                    self.total = Some(end.total());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.hits.clear();
+        self.total = None;
+        self.error = None;
+        self.highlighted = None;
+    }
+}
+
+// start of the day in unix seconds, UTC
+fn parse_date(date: &str) -> Result<u64, SearchError> {
+    let invalid = || SearchError::InvalidDate(date.to_owned());
+    let mut fields = date.splitn(3, '-').map(|field| field.parse::<i64>().map_err(|_| invalid()));
+
+    let (year, month, day) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(year), Some(month), Some(day)) => (year?, month?, day?),
+        _ => return Err(invalid()),
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    Ok((days_from_civil(year, month, day).max(0) * 86400) as u64)
+}
+
+// yyyy-mm-dd hh:mm, UTC
+pub fn format_time(time: u64) -> String {
+    let days = (time / 86400) as i64;
+    let seconds = time % 86400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3600, seconds % 3600 / 60)
+}
+
+// days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
@@ -0,0 +1,36 @@
+use client_core::discovery::{self, ServerEvent};
+use iced_native::futures::stream::BoxStream;
+use iced_native::subscription::Recipe;
+use protocol::multicast::Discovery;
+use std::hash::{Hash, Hasher};
+
+pub struct ServerBrowser {
+    pub discovery: Discovery,
+    // bumped to start over with a fresh ping
+    pub generation: u32,
+}
+
+impl<H, I> Recipe<H, I> for ServerBrowser
+where
+    H: Hasher,
+{
+    type Output = ServerEvent;
+
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+        self.discovery.hash(state);
+        self.generation.hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: BoxStream<I>,
+    ) -> BoxStream<Self::Output> {
+        let receiver = discovery::browse(self.discovery);
+
+        Box::pin(iced_native::futures::stream::unfold(receiver, |receiver| async move {
+            let event = receiver.recv().ok()?;
+            Some((event, receiver))
+        }))
+    }
+}
@@ -0,0 +1,43 @@
+use client_core::theme::{self, Theme};
+
+// message text, the rest of the interface keeps its size
+pub const DEFAULT_TEXT_SIZE: u16 = 20;
+pub const MIN_TEXT_SIZE: u16 = 12;
+pub const MAX_TEXT_SIZE: u16 = 32;
+
+// shown over the screen it was opened from, which carries on underneath
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub open: bool,
+    // opens it from the other screens
+    pub button: iced::button::State,
+    pub theme_buttons: Vec<iced::button::State>,
+    // the system's language, then each catalog's
+    pub locale_buttons: Vec<iced::button::State>,
+    pub reload: iced::button::State,
+    pub smaller: iced::button::State,
+    pub larger: iced::button::State,
+    pub notifications: iced::button::State,
+    pub close: iced::button::State,
+    pub scroll: iced::scrollable::State,
+    // what was wrong with the themes file when it was last read
+    pub error: Option<String>,
+}
+
+// the built-in themes, then those in messaging/themes.toml in the config directory
+pub fn load_themes() -> (Vec<Theme>, Option<String>) {
+    let mut themes = Theme::builtin();
+
+    let path = match dirs_next::config_dir() {
+        Some(dir) => dir.join("messaging").join("themes.toml"),
+        None => return (themes, None),
+    };
+
+    match theme::load(&path) {
+        Ok(user) => {
+            themes.extend(user);
+            (themes, None)
+        }
+        Err(err) => (themes, Some(err)),
+    }
+}
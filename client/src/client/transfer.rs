@@ -0,0 +1,175 @@
+use client_core::i18n::Translator;
+use protocol::file::{self, HASH_LEN, MAX_FILE_SIZE};
+use std::path::Path;
+
+// why a file could not be attached, worded by `message`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadError {
+    // the path and why it could not be read
+    Unreadable(String, String),
+    // the path and its size
+    TooLarge(String, u64),
+}
+
+impl UploadError {
+    pub fn message(&self, tr: &Translator) -> String {
+        match self {
+            Self::Unreadable(path, err) => tr.format("file-unreadable", [("path", path.as_str().into()), ("error", err.as_str().into())]),
+            Self::TooLarge(path, size) => tr.format("file-too-large", [("path", path.as_str().into()), ("size", (*size).into()), ("max", MAX_FILE_SIZE.into())]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    // offered by someone else, content stays on the server until requested
+    Available,
+    InProgress,
+    Complete,
+    Cancelled,
+}
+
+pub struct Transfer {
+    pub upload: bool,
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+    pub hash: [u8; HASH_LEN],
+    pub data: Vec<u8>,
+    pub progress: u64,
+    pub state: TransferState,
+    pub preview: Option<iced::image::Handle>,
+    pub button: iced::button::State,
+}
+
+impl Transfer {
+    pub fn upload(path: &Path) -> Result<Self, UploadError> {
+        let data = std::fs::read(path).map_err(|err| UploadError::Unreadable(path.display().to_string(), err.to_string()))?;
+        let size = data.len() as u64;
+
+        if size > MAX_FILE_SIZE {
+            return Err(UploadError::TooLarge(path.display().to_string(), size));
+        }
+
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("file"));
+        let mime = mime_type(path).to_owned();
+
+        Ok(Self {
+            upload: true,
+            hash: file::hash(&data),
+            preview: preview(&mime, &data),
+            name,
+            mime,
+            size,
+            data,
+            progress: 0,
+            state: TransferState::InProgress,
+            button: iced::button::State::default(),
+        })
+    }
+
+    pub fn download(size: u64, hash: [u8; HASH_LEN], name: String, mime: String) -> Self {
+        Self {
+            upload: false,
+            name,
+            mime,
+            size,
+            hash,
+            data: Vec::new(),
+            progress: 0,
+            state: TransferState::Available,
+            preview: None,
+            button: iced::button::State::default(),
+        }
+    }
+
+    pub fn receive(&mut self, offset: u64, data: &[u8]) {
+        if self.state != TransferState::InProgress {
+            return;
+        }
+
+        if offset != self.progress || self.progress + data.len() as u64 > self.size {
+            self.cancel();
+            return;
+        }
+
+        self.data.extend_from_slice(data);
+        self.progress += data.len() as u64;
+    }
+
+    pub fn complete(&mut self) {
+        if self.progress != self.size || file::hash(&self.data) != self.hash {
+            self.cancel();
+            return;
+        }
+
+        if self.preview.is_none() {
+            self.preview = preview(&self.mime, &self.data);
+        }
+
+        self.state = TransferState::Complete;
+    }
+
+    // previews are fetched right away, anything else waits for the user
+    pub fn auto_request(&self) -> bool {
+        self.mime.starts_with("image/") && self.size <= 2 * 1024 * 1024
+    }
+
+    pub fn request(&mut self) {
+        self.data = Vec::with_capacity(self.size as usize);
+        self.progress = 0;
+        self.state = TransferState::InProgress;
+    }
+
+    pub fn cancel(&mut self) {
+        self.state = TransferState::Cancelled;
+        self.data = Vec::new();
+        self.preview = None;
+    }
+
+    pub fn percent(&self) -> f32 {
+        match self.size {
+            0 => 100.0,
+            size => self.progress as f32 * 100.0 / size as f32,
+        }
+    }
+}
+
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "webp" => "image/webp",
+        "txt" | "md" | "log" => "text/plain",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn format_size(size: u64) -> String {
+    match size {
+        size if size < 1024 => format!("{} B", size),
+        size if size < 1024 * 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+        size => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn preview(mime: &str, data: &[u8]) -> Option<iced::image::Handle> {
+    if !mime.starts_with("image/") {
+        return None;
+    }
+
+    let thumbnail = image::load_from_memory(data).ok()?.thumbnail(320, 240).into_bgra8();
+    let (width, height) = thumbnail.dimensions();
+
+    Some(iced::image::Handle::from_pixels(width, height, thumbnail.into_raw()))
+}
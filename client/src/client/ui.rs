@@ -1,31 +1,73 @@
-use super::{Client, View, ClientMessage};
-use protocol::network::NetworkMessage;
+use super::{Client, View, ClientMessage, PinnedMessage};
+use super::settings::Settings;
+use super::Preferences;
+use super::search::{format_time, Search};
+use super::transfer::{self, Transfer, TransferState};
+use client_core::command;
+use client_core::i18n::{self, Translator};
+use client_core::markdown::{self, Block};
+use client_core::state::ChatState;
+use client_core::theme::Theme;
+use protocol::multicast::Announcement;
+use protocol::network::{refusal, NetworkMessage, MAX_MESSAGE_LEN, PROTOCOL_VERSION};
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 
 use iced::{
     Application, Element, Row, Length, TextInput, Button, Text, Container,
-    Column, Scrollable, Color,
+    Column, Scrollable, Color, ProgressBar, Image, Font,
+};
+
+// code is set apart in a font of its own
+const MONOSPACE: Font = Font::External {
+    name: "DejaVu Sans Mono",
+    bytes: include_bytes!("../../../resources/DejaVuSansMono.ttf"),
 };
 
 impl Client {
     pub fn get_view(&mut self) -> Element<'_, <Self as Application>::Message> {
+        let palette = style::Palette::from(self.current_theme());
+        let text_size = self.preferences.text_size;
+        let tr = &self.tr;
+
+        if self.settings.open {
+            return settings_view(&mut self.settings, &self.themes, self.theme, self.locale, &self.preferences, tr, palette);
+        }
+
         match &mut self.view {
             View::Home { input, submit } => {
                 let form: Row<_> = Row::new()
                     .width(Length::Units(256))
                     .spacing(7)
                     .push(
-                        TextInput::new(input, "Username", &self.username, ClientMessage::UpdateUsername)
+                        TextInput::new(input, &tr.tr("username"), &self.username, ClientMessage::UpdateUsername)
                             .on_submit(ClientMessage::SubmitUsername)
-                            .style(style::TextInput)
+                            .style(style::TextInput(palette))
                             .padding(5),
                     )
                     .push(
-                        Button::new(submit, Text::new("Login"))
+                        Button::new(submit, Text::new(tr.tr("login")))
                             .on_press(ClientMessage::SubmitUsername)
-                            .style(style::Button)
+                            .style(style::Button(palette))
                             .padding(5),
                     );
 
+                let form = Column::new()
+                    .spacing(7)
+                    .push(form)
+                    .push(
+                        Button::new(
+                            &mut self.settings.button,
+                            Text::new(tr.tr("settings"))
+                                .horizontal_alignment(iced::HorizontalAlignment::Center),
+                        )
+                        .on_press(ClientMessage::ToggleSettings)
+                        .width(Length::Units(256))
+                        .style(style::Button(palette))
+                        .padding(5),
+                    );
+
                 Container::new(form)
                     .width(Length::Fill)
                     .height(Length::Fill)
@@ -33,48 +75,121 @@ impl Client {
                     .center_y()
                     .into()
             }
-            View::SelectServer { buttons, servers } => {
+            View::SelectServer { buttons, servers, address_input, address, connect, saved_buttons, error, .. } => {
                 while buttons.len() <= servers.len() {
                     buttons.push(iced::button::State::default());
                 }
 
-                let mut buttons = buttons.iter_mut();
-                let col = Column::new()
+                let manual: Row<_> = Row::new()
+                    .width(Length::Units(256))
+                    .spacing(7)
+                    .push(
+                        TextInput::new(address_input, &tr.tr("address"), address, ClientMessage::UpdateAddress)
+                            .on_submit(ClientMessage::ConnectAddress(address.to_owned()))
+                            .style(style::TextInput(palette))
+                            .padding(5),
+                    )
+                    .push(
+                        Button::new(connect, Text::new(tr.tr("connect")))
+                            .on_press(ClientMessage::ConnectAddress(address.to_owned()))
+                            .style(style::Button(palette))
+                            .padding(5),
+                    );
+
+                let mut col = Column::new()
                     .width(Length::Shrink)
                     .spacing(5)
+                    .push(manual);
+
+                if let Some(error) = error {
+                    col = col.push(
+                        Text::new(error.as_str())
+                            .size(14)
+                            .width(Length::Units(256))
+                            .color(palette.error),
+                    );
+                }
+
+                // favourites first, recent ones that aren't favourites already after
+                let saved_servers = &self.preferences.servers;
+                let saved: Vec<_> = saved_servers.favourites.iter().map(|addr| (addr, true))
+                    .chain(saved_servers.recent.iter()
+                        .filter(|addr| !saved_servers.is_favourite(addr))
+                        .map(|addr| (addr, false)))
+                    .collect();
+
+                while saved_buttons.len() < saved.len() {
+                    saved_buttons.push(Default::default());
+                }
+
+                if !saved.is_empty() {
+                    col = col.push(Row::new().height(Length::Units(15)));
+                }
+
+                for ((addr, favourite), (connect, toggle)) in saved.into_iter().zip(saved_buttons.iter_mut()) {
+                    col = col.push(
+                        Row::new()
+                            .width(Length::Units(256))
+                            .spacing(7)
+                            .push(
+                                Button::new(connect, Text::new(addr.as_str()))
+                                    .on_press(ClientMessage::ConnectAddress(addr.to_owned()))
+                                    .width(Length::Fill)
+                                    .style(style::Button(palette))
+                                    .padding(5),
+                            )
+                            .push(
+                                Button::new(toggle, Text::new(if favourite { "x" } else { "+" }))
+                                    .on_press(ClientMessage::ToggleFavourite(addr.to_owned()))
+                                    .style(style::Button(palette))
+                                    .padding(5),
+                            ),
+                    );
+                }
+
+                let mut buttons = buttons.iter_mut();
+                let col = col
+                    .push(Row::new().height(Length::Units(15)))
                     .push(
                         Button::new(
                             buttons.next().unwrap(),
-                            Text::new("Refresh")
+                            Text::new(tr.tr("refresh"))
                                 .horizontal_alignment(iced::HorizontalAlignment::Center),
                         )
                         .on_press(ClientMessage::RefreshServerList)
                         .width(Length::Units(256))
-                        .style(style::Button)
+                        .style(style::Button(palette))
+                        .padding(5),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.settings.button,
+                            Text::new(tr.tr("settings"))
+                                .horizontal_alignment(iced::HorizontalAlignment::Center),
+                        )
+                        .on_press(ClientMessage::ToggleSettings)
+                        .width(Length::Units(256))
+                        .style(style::Button(palette))
                         .padding(5),
                     )
                     .push(Row::new().height(Length::Units(15)));
 
                 let list = match servers.len() {
                     0 => col.push(
-                        Text::new("No server found")
+                        Text::new(tr.tr("no-servers"))
                             .horizontal_alignment(iced::HorizontalAlignment::Center)
                             .width(Length::Units(256))
-                            .color(Color::WHITE),
+                            .color(palette.text),
                     ),
                     _ => servers.iter().zip(buttons).fold(
                         col,
-                        |container, ((entree, addr), state)| {
+                        |container, ((announcement, addr, _), state)| {
                             container.push(
-                                Button::new(
-                                    state,
-                                    Text::new(entree.to_owned())
-                                        .horizontal_alignment(iced::HorizontalAlignment::Center),
-                                )
-                                .on_press(ClientMessage::SelectServer(addr.to_owned()))
-                                .width(Length::Units(256))
-                                .style(style::Button)
-                                .padding(5),
+                                Button::new(state, server_entry(announcement, addr, palette, tr))
+                                    .on_press(ClientMessage::SelectServer(addr.to_owned()))
+                                    .width(Length::Units(256))
+                                    .style(style::Button(palette))
+                                    .padding(5),
                             )
                         },
                     ),
@@ -87,81 +202,322 @@ impl Client {
                     .center_y()
                     .into()
             }
-            View::Chat { messages, users, scroll_view, input, message, .. } => {
+            View::Chat {
+                state, scroll_view, input, attach, export, restored, transfers, message, server_info, rules_button, show_rules,
+                pinned, pinned_button, show_pinned, pin_buttons, search, notices, link_buttons, preview_button, preview, seen, ..
+            } => {
+                // by index, known before the state is taken apart
+                let mentioned: HashSet<usize> = state.messages.iter()
+                    .enumerate()
+                    .filter(|(_, (msg, _))| state.mentions_us(msg))
+                    .map(|(index, _)| index)
+                    .collect();
+
+                let seen_by: HashMap<usize, String> = match *seen {
+                    true => state.messages.iter().enumerate().filter_map(|(index, (msg, _))| match msg {
+                        NetworkMessage::Message(msg) => Some((index, state.seen_by(msg.id()))),
+                        _ => None,
+                    })
+                    .filter(|(_, names)| !names.is_empty())
+                    .map(|(index, names)| (index, tr.format("seen-by", [("names", tr.list(&names).into())])))
+                    .collect(),
+                    false => HashMap::new(),
+                };
+
+                // joins, and leaves, in a row are told in one line
+                let runs = membership_runs(&state.messages, state.divider, tr);
+
+                let ChatState { username, messages, users, bots, away, moderator, unread_private, divider, .. } = state;
+
                 let users_col = Column::new()
                     .width(Length::Units(180))
                     .height(Length::Fill)
                     .padding(5)
                     .spacing(5)
-                    .push(Container::new(Text::new(&self.username))
-                        .style(style::SelfContainer)
+                    .push(Container::new(Text::new(username.as_str()))
+                        .style(style::SelfContainer(palette))
                         .width(Length::Fill)
                         .padding(7)
                     );
 
-                let users_col = users.values().fold(users_col, |users, username| {
-                    users.push(Container::new(Text::new(username))
-                        .style(style::GuestContainer)
-                        .width(Length::Fill)
-                        .padding(7)
-                    )
+                let users_col = users.iter().fold(users_col, |users, (id, username)| {
+                    let label = match (away.contains_key(id), unread_private.get(id)) {
+                        (true, _) => Text::new(tr.format("away-label", [("name", username.as_str().into())])).color(palette.dim),
+                        (false, Some(unread)) => Text::new(format!("{} ({})", username, unread)).color(palette.private),
+                        (false, None) => Text::new(username),
+                    };
+
+                    users.push(match bots.contains(id) {
+                        true => Container::new(Text::new(tr.format("bot-label", [("name", username.as_str().into())]))).style(style::BotContainer(palette)),
+                        false => Container::new(label).style(style::GuestContainer(palette)),
+                    }
+                    .width(Length::Fill)
+                    .padding(7))
                 });
 
-                let scroll_view = messages.iter().fold(
+                // each transfer is shown once, next to the offer that started it
+                let mut transfers: HashMap<_, _> = transfers.iter_mut().collect();
+
+                for (msg, _) in messages.iter() {
+                    if let NetworkMessage::Message(msg) = msg {
+                        pin_buttons.entry(msg.id()).or_default();
+                    }
+                }
+
+                for (index, (msg, _)) in messages.iter().enumerate() {
+                    let content = match msg {
+                        NetworkMessage::Message(msg) => msg.content(),
+                        NetworkMessage::PrivateMessage(msg) => msg.content(),
+                        _ => continue,
+                    };
+
+                    for link in 0..link_count(content) {
+                        link_buttons.entry((index, link)).or_default();
+                    }
+                }
+
+                let pinned_ids: Vec<_> = pinned.iter().map(|pin| pin.id).collect();
+                let highlighted = search.highlighted;
+                let mut pin_buttons: HashMap<_, _> = pin_buttons.iter_mut().collect();
+                let mut link_buttons: HashMap<_, _> = link_buttons.iter_mut().collect();
+
+                let scroll_view = messages.iter().enumerate().fold(
                     Scrollable::new(scroll_view)
                         .width(Length::Fill)
                         .spacing(5),
-                    |scroll, (msg, from)| {
-                        match msg {
+                    |scroll, (index, (msg, from))| {
+                        let links: Vec<_> = (0..).map_while(|link| link_buttons.remove(&(index, link))).collect();
+
+                        let scroll = notices.iter()
+                            .filter(|(at, ..)| *at == index)
+                            .fold(scroll, |scroll, (_, text, error)| scroll.push(notice(text, *error, text_size, palette)));
+
+                        // restored from the local log, above what arrived since joining
+                        let scroll = match index == *restored && index > 0 {
+                            true => scroll.push(
+                                Text::new(tr.tr("earlier-messages"))
+                                    .size(text_size)
+                                    .color(palette.dim)
+                            ),
+                            false => scroll,
+                        };
+
+                        // what came in while the window was out of sight
+                        let scroll = match *divider == Some(index) {
+                            true => scroll.push(Row::new()
+                                .align_items(iced::Align::Center)
+                                .spacing(7)
+                                .push(iced::Rule::horizontal(1).style(style::NewMessages(palette)))
+                                .push(Text::new(tr.tr("new-messages")).size(14).color(palette.error))
+                                .push(iced::Rule::horizontal(1).style(style::NewMessages(palette)))
+                            ),
+                            false => scroll,
+                        };
+
+                        let scroll = match msg {
                             NetworkMessage::UserList(list) => {
-                                match list.users().len() {
-                                    0 => scroll,
-                                    1 => scroll.push(
-                                        Text::new(format!("Join a server with {}", list.users().first().unwrap().1))
-                                            .color(Color::from_rgb(0.6, 0.6, 0.6))
+                                let names: Vec<_> = list.users().iter().map(|(_, name, _)| name.as_str()).collect();
+
+                                match names.is_empty() {
+                                    true => scroll,
+                                    false => scroll.push(
+                                        Text::new(tr.format("already-here", [("names", tr.list(&names).into()), ("count", names.len().into())]))
+                                            .size(text_size)
+                                            .color(palette.dim)
                                     ),
-                                    len => {
-                                        let (_, last) = list.users().last().unwrap();
-                                        let mut init = String::with_capacity(128);
-                                        init.push_str(&list.users().first().unwrap().1);
-
-                                        let msg = list.users()[1..len - 1].iter().fold(init, |mut msg, (_, user)| {
-                                            msg.push_str(", ");
-                                            msg.push_str(user);
-                                            msg
-                                        });
-
-                                        scroll.push(
-                                        Text::new(format!("Join a server with {} and {}.", msg, last))
-                                            .color(Color::from_rgb(0.6, 0.6, 0.6))
-                                        )
-                                    }
                                 }
                             }
-                            NetworkMessage::UserJoin(join) => scroll.push(
-                                Text::new(format!("{} joined the server", join.name()))
-                                    .color(Color::from_rgb(0.6, 0.6, 0.6))
+                            NetworkMessage::UserJoin(_) | NetworkMessage::UserLeave(_) => match runs.get(&index) {
+                                Some(run) => scroll.push(
+                                    Text::new(run.as_str())
+                                        .size(text_size)
+                                        .color(palette.dim)
+                                ),
+                                None => scroll,
+                            },
+                            NetworkMessage::UserRename(rename) => scroll.push(
+                                Text::new(tr.format("user-renamed", [("old", from.as_str().into()), ("new", rename.name().into())]))
+                                    .size(text_size)
+                                    .color(palette.dim)
                             ),
-                            NetworkMessage::UserLeave(_) => scroll.push(
-                                Text::new(format!("{} left the server", from))
-                                    .color(Color::from_rgb(0.6, 0.6, 0.6))
+                            NetworkMessage::Away(status) => scroll.push(
+                                Text::new(match (status.away(), status.message().is_empty()) {
+                                    (true, true) => tr.format("user-away", [("name", from.as_str().into())]),
+                                    (true, false) => tr.format("user-away-message", [("name", from.as_str().into()), ("message", status.message().into())]),
+                                    (false, _) => tr.format("user-back", [("name", from.as_str().into())]),
+                                })
+                                .size(text_size)
+                                .color(palette.dim)
                             ),
-                            NetworkMessage::Message(msg) => scroll.push(Row::new()
-                                .push(Text::new(format!("{}: ", from)).color(Color::from_rgb(0.0, 3.0, 5.0)))
-                                .push(Text::new(msg.content()).color(Color::WHITE))
+                            NetworkMessage::PrivateMessage(msg) => {
+                                let row = Row::new()
+                                    .push(Text::new(format!("{} -> {}: ", from, users.get(&msg.to()).unwrap_or(&*username)))
+                                        .size(text_size)
+                                        .color(palette.private))
+                                    .push(rich_text(msg.content(), palette.private, text_size, palette, links));
+
+                                match mentioned.contains(&index) {
+                                    true => scroll.push(Container::new(row).style(style::Mention(palette)).width(Length::Fill)),
+                                    false => scroll.push(row),
+                                }
+                            }
+                            NetworkMessage::Message(msg) if command::action(msg.content()).is_some() => scroll.push(
+                                Text::new(format!("* {} {}", from, command::action(msg.content()).unwrap_or_default()))
+                                    .size(text_size)
+                                    .color(palette.italic)
+                            ),
+                            NetworkMessage::Message(msg) => {
+                                let row = Row::new()
+                                    .align_items(iced::Align::Center)
+                                    .push(match bots.contains(&msg.from()) {
+                                        true => Text::new(format!("{}: ", tr.format("bot-label", [("name", from.as_str().into())]))).size(text_size).color(palette.bot),
+                                        false => Text::new(format!("{}: ", from)).size(text_size).color(palette.name_text),
+                                    })
+                                    .push(rich_text(msg.content(), palette.text, text_size, palette, links));
+
+                                // moderators can pin anything not pinned yet
+                                let row = match pin_buttons.remove(&msg.id()) {
+                                    Some(state) if *moderator && !pinned_ids.contains(&msg.id()) => row
+                                        .push(iced::Space::with_width(Length::Units(7)))
+                                        .push(
+                                            Button::new(state, Text::new(tr.tr("pin")).size(14))
+                                                .on_press(ClientMessage::Pin(msg.id()))
+                                                .style(style::Button(palette))
+                                                .padding(2),
+                                        ),
+                                    _ => row,
+                                };
+
+                                match (highlighted == Some(msg.id()), mentioned.contains(&index)) {
+                                    (true, _) => scroll.push(Container::new(row).style(style::Highlight(palette)).width(Length::Fill)),
+                                    (false, true) => scroll.push(Container::new(row).style(style::Mention(palette)).width(Length::Fill)),
+                                    (false, false) => scroll.push(row),
+                                }
+                            }
+                            NetworkMessage::FileOffer(offer) => {
+                                match transfers.remove(&offer.transfer_id()) {
+                                    Some(transfer) => scroll.push(Row::new()
+                                        .push(Text::new(format!("{}: ", from)).size(text_size).color(palette.name_text))
+                                        .push(transfer_view(transfer, offer.transfer_id(), palette, tr))
+                                    ),
+                                    None => scroll,
+                                }
+                            }
+                            NetworkMessage::RateLimited(limited) => scroll.push(
+                                Text::new(match limited.muted() {
+                                    true => tr.format("muted", [("seconds", ((limited.retry_after() + 999) / 1000).into())]),
+                                    false => tr.tr("rate-limited"),
+                                })
+                                .size(text_size)
+                                .color(palette.error)
+                            ),
+                            NetworkMessage::Refused(refused) => scroll.push(
+                                Text::new(match refused.reason() {
+                                    refusal::SERVER_FULL => tr.tr("refused-full"),
+                                    refusal::NAME_TAKEN => tr.tr("refused-name-taken"),
+                                    _ => tr.tr("refused"),
+                                })
+                                .size(text_size)
+                                .color(palette.error)
                             ),
                             _ => scroll
+                        };
+
+                        match seen_by.get(&index) {
+                            Some(seen) => scroll.push(Text::new(seen.as_str()).size(14).color(palette.dim)),
+                            None => scroll,
                         }
                     }
                 );
 
-                let input = TextInput::new(input, "Envoyez un message", message, ClientMessage::UpdateMessage)
-                    .on_submit(ClientMessage::SendMessage)
-                    .style(style::TextInput)
-                    .padding(7);
+                let scroll_view = notices.iter()
+                    .filter(|(at, ..)| *at >= messages.len())
+                    .fold(scroll_view, |scroll, (_, text, error)| scroll.push(notice(text, *error, text_size, palette)));
+
+                let remaining = MAX_MESSAGE_LEN as isize - message.len() as isize;
+
+                // commands have nothing to preview
+                let preview = match *preview && !message.trim().is_empty() && !message.starts_with('/') {
+                    true => Some(Container::new(rich_text(message, palette.text, text_size, palette, Vec::new()))
+                        .style(style::Banner(palette))
+                        .width(Length::Fill)
+                        .padding(7)),
+                    false => None,
+                };
+
+                let input = Row::new()
+                    .spacing(7)
+                    .align_items(iced::Align::Center)
+                    .push(
+                        Button::new(attach, Text::new(tr.tr("attach")))
+                            .on_press(ClientMessage::AttachFile)
+                            .style(style::Button(palette))
+                            .padding(7),
+                    )
+                    .push(
+                        Button::new(export, Text::new(tr.tr("export")))
+                            .on_press(ClientMessage::ExportLog)
+                            .style(style::Button(palette))
+                            .padding(7),
+                    )
+                    .push(
+                        Button::new(&mut self.settings.button, Text::new(tr.tr("settings")))
+                            .on_press(ClientMessage::ToggleSettings)
+                            .style(style::Button(palette))
+                            .padding(7),
+                    )
+                    .push(
+                        TextInput::new(input, &tr.tr("message-placeholder"), message, ClientMessage::UpdateMessage)
+                            .on_submit(ClientMessage::SendMessage)
+                            .style(style::TextInput(palette))
+                            .padding(7),
+                    )
+                    .push(
+                        Button::new(preview_button, Text::new(tr.tr("preview")))
+                            .on_press(ClientMessage::TogglePreview)
+                            .style(style::Button(palette))
+                            .padding(7),
+                    )
+                    .push(
+                        // the server counts bytes, not characters
+                        Text::new(match remaining {
+                            r if r < 0 => tr.format("bytes-over", [("count", (-r).into())]),
+                            r => tr.format("bytes-left", [("count", r.into())]),
+                        })
+                            .color(match remaining {
+                                r if r < 0 => palette.error,
+                                _ => palette.dim,
+                            }),
+                    );
 
                 let chat_col = Column::new()
                     .padding(5)
+                    .push(search_view(search, palette, tr))
+                    .push(iced::Space::new(
+                        Length::Fill,
+                        Length::Units(7))
+                    );
+                let chat_col = match server_info {
+                    Some(NetworkMessage::ServerInfo(info)) => chat_col
+                        .push(banner(info.name(), info.description(), info.motd(), info.rules(), rules_button, *show_rules, palette, tr))
+                        .push(iced::Space::new(
+                            Length::Fill,
+                            Length::Units(7))
+                        ),
+                    _ => chat_col,
+                };
+
+                let chat_col = match pinned.is_empty() {
+                    true => chat_col,
+                    false => chat_col
+                        .push(pinned_panel(pinned, pinned_button, *show_pinned, *moderator, palette, tr))
+                        .push(iced::Space::new(
+                            Length::Fill,
+                            Length::Units(7))
+                        ),
+                };
+
+                let chat_col = chat_col
                     .push(iced::Space::new(
                         Length::Fill,
                         Length::Fill)
@@ -170,12 +526,23 @@ impl Client {
                     .push(iced::Space::new(
                         Length::Fill,
                         Length::Units(7))
-                    )
-                    .push(input);
+                    );
+
+                let chat_col = match preview {
+                    Some(preview) => chat_col
+                        .push(preview)
+                        .push(iced::Space::new(
+                            Length::Fill,
+                            Length::Units(7))
+                        ),
+                    None => chat_col,
+                };
+
+                let chat_col = chat_col.push(input);
 
                 Row::new()
                     .push(users_col)
-                    .push(iced::Rule::vertical(0).style(style::Rule))
+                    .push(iced::Rule::vertical(0).style(style::Rule(palette)))
                     .push(chat_col)
                     .into()
             }
@@ -183,38 +550,576 @@ impl Client {
     }
 
     pub fn get_background_color(&self) -> Color {
-        Color::from_rgb(0.0, 0.05, 0.1)
+        style::Palette::from(self.current_theme()).background
     }
 }
 
-mod style {
-    use iced::{Color, button, container, rule, text_input};
+// theme, text size, language and notifications, each applied and saved as soon as it is picked
+// `locale` is the language picked over the system's, if any
+fn settings_view<'a>(
+    settings: &'a mut Settings,
+    themes: &[Theme],
+    selected: usize,
+    locale: Option<&'static str>,
+    preferences: &Preferences,
+    tr: &Translator,
+    palette: style::Palette,
+) -> Element<'a, ClientMessage> {
+    let Settings { theme_buttons, locale_buttons, reload, smaller, larger, notifications, close, scroll, error, .. } = settings;
+    let text_size = preferences.text_size;
 
-    const SURFACE: Color = Color::from_rgb(0.05, 0.1, 0.2);
+    while theme_buttons.len() < themes.len() {
+        theme_buttons.push(iced::button::State::default());
+    }
 
-    const ACCENT: Color = Color::from_rgb(
-        0x6F as f32 / 255.0,
-        0xFF as f32 / 255.0,
-        0xE9 as f32 / 255.0,
-    );
+    while locale_buttons.len() <= i18n::LOCALES.len() {
+        locale_buttons.push(iced::button::State::default());
+    }
 
-    const ACTIVE: Color = Color::from_rgb(
-        0x64 as f32 / 255.0,
-        0x95 as f32 / 255.0,
-        0xED as f32 / 255.0,
-    );
+    let col = Column::new()
+        .width(Length::Units(256))
+        .spacing(5)
+        .push(Text::new(tr.tr("settings")).size(22).color(palette.text))
+        .push(Row::new().height(Length::Units(15)))
+        .push(Text::new(tr.tr("theme")).color(palette.dim));
+
+    let col = themes.iter().zip(theme_buttons.iter_mut()).enumerate().fold(col, |col, (index, (theme, state))| {
+        let button = Button::new(state, Text::new(theme.name.as_str()))
+            .on_press(ClientMessage::SelectTheme(index))
+            .width(Length::Fill)
+            .padding(5);
+
+        col.push(match index == selected {
+            true => button.style(style::Button(palette)),
+            false => button.style(style::Flat(palette)),
+        })
+    });
+
+    let col = match error {
+        Some(error) => col.push(Text::new(error.as_str()).size(14).color(palette.error)),
+        None => col,
+    };
+
+    let size = Row::new()
+        .spacing(7)
+        .align_items(iced::Align::Center)
+        .push(
+            Button::new(smaller, Text::new("-"))
+                .on_press(ClientMessage::TextSize(text_size.saturating_sub(1)))
+                .style(style::Button(palette))
+                .padding(5),
+        )
+        .push(
+            Text::new(text_size.to_string())
+                .size(text_size)
+                .color(palette.text)
+                .horizontal_alignment(iced::HorizontalAlignment::Center)
+                .width(Length::Fill),
+        )
+        .push(
+            Button::new(larger, Text::new("+"))
+                .on_press(ClientMessage::TextSize(text_size + 1))
+                .style(style::Button(palette))
+                .padding(5),
+        );
+
+    let col = col
+        .push(
+            Button::new(reload, Text::new(tr.tr("reload-themes")).horizontal_alignment(iced::HorizontalAlignment::Center))
+                .on_press(ClientMessage::ReloadThemes)
+                .width(Length::Fill)
+                .style(style::Button(palette))
+                .padding(5),
+        )
+        .push(Row::new().height(Length::Units(15)))
+        .push(Text::new(tr.tr("text-size")).color(palette.dim))
+        .push(size)
+        .push(Row::new().height(Length::Units(15)))
+        .push(Text::new(tr.tr("language")).color(palette.dim));
+
+    // the system's first, then one for each catalog
+    let system = tr.format("language-system", [("language", i18n::name(i18n::detect()).into())]);
+    let choices = std::iter::once((None, system))
+        .chain(i18n::LOCALES.iter().map(|(code, name, _)| (Some(*code), name.to_string())));
+
+    let col = choices.zip(locale_buttons.iter_mut()).fold(col, |col, ((choice, name), state)| {
+        let button = Button::new(state, Text::new(name))
+            .on_press(ClientMessage::SelectLocale(choice))
+            .width(Length::Fill)
+            .padding(5);
+
+        col.push(match choice == locale {
+            true => button.style(style::Button(palette)),
+            false => button.style(style::Flat(palette)),
+        })
+    });
+
+    let col = col
+        .push(Row::new().height(Length::Units(15)))
+        .push(Text::new(tr.tr("notifications")).color(palette.dim))
+        .push(
+            Button::new(notifications, Text::new(match preferences.notifications {
+                true => tr.tr("notifications-on"),
+                false => tr.tr("notifications-off"),
+            }))
+                .on_press(ClientMessage::ToggleNotifications)
+                .width(Length::Fill)
+                .style(style::Button(palette))
+                .padding(5),
+        )
+        .push(Row::new().height(Length::Units(15)))
+        .push(
+            Button::new(close, Text::new(tr.tr("back")).horizontal_alignment(iced::HorizontalAlignment::Center))
+                .on_press(ClientMessage::ToggleSettings)
+                .width(Length::Fill)
+                .style(style::Button(palette))
+                .padding(5),
+        );
+
+    // taller than small windows
+    Container::new(Scrollable::new(scroll).padding(15).push(col))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+// the line each run of joins, or of leaves, is told in, by the index of its last message
+// a run ends where the new messages begin, its other messages show nothing
+fn membership_runs(messages: &[(NetworkMessage, String)], divider: Option<usize>, tr: &Translator) -> HashMap<usize, String> {
+    let mut runs = HashMap::new();
+    let mut run = Vec::new();
+
+    for (index, (msg, from)) in messages.iter().enumerate() {
+        let (name, id) = match msg {
+            NetworkMessage::UserJoin(join) => (join.name(), "users-joined"),
+            NetworkMessage::UserLeave(_) => (from.as_str(), "users-left"),
+            _ => continue,
+        };
+        run.push(name);
+
+        let next = messages.get(index + 1).map(|(next, _)| std::mem::discriminant(next));
+        if next != Some(std::mem::discriminant(msg)) || divider == Some(index + 1) {
+            runs.insert(index, tr.format(id, [("names", tr.list(&run).into()), ("count", run.len().into())]));
+            run.clear();
+        }
+    }
+
+    runs
+}
+
+fn link_count(content: &str) -> usize {
+    markdown::parse(content).iter().map(|block| match block {
+        Block::Paragraph(spans) => spans.iter().filter(|span| span.link.is_some()).count(),
+        Block::List { items, .. } => items.iter().flatten().filter(|span| span.link.is_some()).count(),
+        Block::Code(_) => 0,
+    }).sum()
+}
+
+// Markdown in `color` at `size`, each link takes the next of `links` and is only clickable while there is one
+// the default font has no bold or italic face, emphasis shows in colour instead
+fn rich_text<'a>(
+    content: &str,
+    color: Color,
+    size: u16,
+    palette: style::Palette,
+    links: Vec<&'a mut iced::button::State>,
+) -> Element<'a, ClientMessage> {
+    let mut links = links.into_iter();
+
+    let mut inline = |spans: Vec<markdown::Span>| -> Element<'a, ClientMessage> {
+        // plain text wraps as a single widget
+        if let [span] = spans.as_slice() {
+            if span.link.is_none() && span.style == markdown::Style::default() {
+                return Text::new(span.text.as_str()).size(size).color(color).into();
+            }
+        }
+
+        spans.into_iter().fold(Row::new(), |row, span| {
+            let text = Text::new(span.text).size(size).color(match span.style {
+                _ if span.link.is_some() => palette.link,
+                markdown::Style { code: true, .. } => palette.code,
+                markdown::Style { bold: true, .. } => palette.bold,
+                markdown::Style { italic: true, .. } => palette.italic,
+                _ => color,
+            });
+            let text = match span.style.code {
+                true => text.font(MONOSPACE),
+                false => text,
+            };
+
+            match (span.link, links.next()) {
+                (Some(url), Some(state)) => row.push(
+                    Button::new(state, text)
+                        .on_press(ClientMessage::OpenLink(url))
+                        .style(style::Link(palette))
+                        .padding(0),
+                ),
+                _ => row.push(text),
+            }
+        }).into()
+    };
+
+    markdown::parse(content).into_iter().fold(Column::new().spacing(3), |column, block| match block {
+        Block::Paragraph(spans) => column.push(inline(spans)),
+        Block::Code(code) => column.push(
+            Container::new(Text::new(code).font(MONOSPACE).size(size.saturating_sub(4)))
+                .style(style::CodeBlock(palette))
+                .width(Length::Fill)
+                .padding(7),
+        ),
+        Block::List { start, items } => items.into_iter().enumerate().fold(column, |column, (number, item)| {
+            let bullet = match start {
+                Some(start) => format!("{}. ", start as usize + number),
+                None => String::from("- "),
+            };
+
+            column.push(Row::new().push(Text::new(bullet).size(size).color(color)).push(inline(item)))
+        }),
+    }).into()
+}
+
+// feedback on what was typed, only ever shown locally
+fn notice(text: &str, error: bool, size: u16, palette: style::Palette) -> Text {
+    Text::new(text).size(size).color(match error {
+        true => palette.error,
+        false => palette.dim,
+    })
+}
+
+// pinned above the messages, rules are folded away until asked for
+#[allow(clippy::too_many_arguments)]
+fn banner<'a>(
+    name: &str,
+    description: &str,
+    motd: &str,
+    rules: &str,
+    rules_button: &'a mut iced::button::State,
+    show_rules: bool,
+    palette: style::Palette,
+    tr: &Translator,
+) -> Element<'a, ClientMessage> {
+    let mut title = Row::new()
+        .spacing(7)
+        .align_items(iced::Align::Center)
+        .push(Text::new(name).size(22).color(palette.text));
+
+    if !description.is_empty() {
+        title = title.push(Text::new(description).color(palette.dim));
+    }
+
+    let mut col = Column::new()
+        .spacing(5)
+        .push(title);
+
+    if !motd.is_empty() {
+        col = col.push(Text::new(motd).color(palette.text));
+    }
+
+    if !rules.is_empty() {
+        col = col.push(
+            Button::new(rules_button, Text::new(match show_rules {
+                true => tr.tr("hide-rules"),
+                false => tr.tr("show-rules"),
+            }))
+                .on_press(ClientMessage::ToggleRules)
+                .style(style::Button(palette))
+                .padding(3),
+        );
+
+        if show_rules {
+            col = col.push(Text::new(rules).color(palette.text));
+        }
+    }
+
+    Container::new(col)
+        .style(style::Banner(palette))
+        .width(Length::Fill)
+        .padding(7)
+        .into()
+}
+
+// search box, with the results of the last search below it once answered
+fn search_view<'a>(search: &'a mut Search, palette: style::Palette, tr: &Translator) -> Element<'a, ClientMessage> {
+    let open = search.is_open();
+    let Search { input, query, hits, total, error, results_scroll, close_button, .. } = search;
 
-    const HOVERED: Color = Color::from_rgb(
-        0x41 as f32 / 255.0,
-        0x69 as f32 / 255.0,
-        0xE1 as f32 / 255.0,
+    let col = Column::new()
+        .spacing(5)
+        .push(
+            TextInput::new(input, &tr.tr("search-placeholder"), query, ClientMessage::UpdateSearch)
+                .on_submit(ClientMessage::SubmitSearch)
+                .style(style::TextInput(palette))
+                .padding(5),
+        );
+
+    if !open {
+        return col.into();
+    }
+
+    let summary = match total {
+        Some(total) if *total as usize > hits.len() => tr.format("search-most-recent", [("shown", hits.len().into()), ("total", (*total).into())]),
+        Some(total) => tr.format("search-results", [("total", (*total).into())]),
+        None => tr.tr("searching"),
+    };
+
+    let header = Row::new()
+        .spacing(7)
+        .align_items(iced::Align::Center)
+        .push(Text::new(summary).color(palette.dim).width(Length::Fill))
+        .push(
+            Button::new(close_button, Text::new(tr.tr("close")))
+                .on_press(ClientMessage::CloseSearch)
+                .style(style::Button(palette))
+                .padding(3),
+        );
+
+    let col = col.push(header);
+    let col = match error {
+        Some(error) => col.push(Text::new(error.as_str()).color(palette.error)),
+        None => col,
+    };
+
+    let results = hits.iter_mut().fold(
+        Scrollable::new(results_scroll)
+            .width(Length::Fill)
+            .max_height(200)
+            .spacing(3),
+        |results, hit| results.push(
+            Button::new(&mut hit.button, Column::new()
+                .push(Text::new(format!("{} {}", format_time(hit.time), hit.from)).size(14).color(palette.dim))
+                .push(Text::new(hit.content.as_str()).color(palette.text))
+            )
+                .on_press(ClientMessage::JumpTo(hit.id))
+                .style(style::Flat(palette))
+                .width(Length::Fill)
+                .padding(3),
+        ),
     );
 
-    pub struct TextInput;
+    col.push(results).into()
+}
+
+// collapsible, unpin buttons only for moderators
+fn pinned_panel<'a>(
+    pinned: &'a mut [PinnedMessage],
+    toggle: &'a mut iced::button::State,
+    show: bool,
+    moderator: bool,
+    palette: style::Palette,
+    tr: &Translator,
+) -> Element<'a, ClientMessage> {
+    let header = Button::new(toggle, Text::new(tr.format(match show {
+        true => "hide-pinned",
+        false => "show-pinned",
+    }, [("count", pinned.len().into())])))
+        .on_press(ClientMessage::TogglePinned)
+        .style(style::Button(palette))
+        .padding(3);
+
+    let col = Column::new()
+        .spacing(5)
+        .push(header);
+
+    let col = match show {
+        false => col,
+        true => pinned.iter_mut().fold(col, |col, pin| {
+            let row = Row::new()
+                .spacing(7)
+                .align_items(iced::Align::Center)
+                .push(Text::new(format!("{}: {}", pin.from, pin.content)).color(palette.text).width(Length::Fill));
+
+            col.push(match moderator {
+                true => row.push(
+                    Button::new(&mut pin.unpin, Text::new(tr.tr("unpin")).size(14))
+                        .on_press(ClientMessage::Unpin(pin.id))
+                        .style(style::Button(palette))
+                        .padding(2),
+                ),
+                false => row,
+            })
+        }),
+    };
+
+    Container::new(col)
+        .style(style::Banner(palette))
+        .width(Length::Fill)
+        .padding(7)
+        .into()
+}
+
+fn transfer_view<'a>(transfer: &'a mut Transfer, id: u32, palette: style::Palette, tr: &Translator) -> Element<'a, ClientMessage> {
+    let col = Column::new()
+        .spacing(5)
+        .push(
+            Text::new(format!("{} ({})", transfer.name, transfer::format_size(transfer.size)))
+                .color(palette.text)
+        );
+
+    match transfer.state {
+        TransferState::Available => col.push(
+            Button::new(&mut transfer.button, Text::new(tr.tr("download")))
+                .on_press(ClientMessage::RequestFile(id))
+                .style(style::Button(palette))
+                .padding(3),
+        ).into(),
+        TransferState::InProgress => {
+            let row = Row::new()
+                .spacing(7)
+                .align_items(iced::Align::Center)
+                .push(
+                    ProgressBar::new(0.0..=100.0, transfer.percent())
+                        .width(Length::Units(256))
+                        .height(Length::Units(8))
+                )
+                .push(
+                    Text::new(format!("{:.0}%", transfer.percent()))
+                        .color(palette.dim)
+                );
+
+            let row = match transfer.upload {
+                true => row.push(
+                    Button::new(&mut transfer.button, Text::new(tr.tr("cancel")))
+                        .on_press(ClientMessage::CancelUpload(id))
+                        .style(style::Button(palette))
+                        .padding(3),
+                ),
+                false => row,
+            };
+
+            col.push(row).into()
+        }
+        TransferState::Complete => {
+            let col = match &transfer.preview {
+                Some(preview) => col.push(Image::new(preview.clone())),
+                None => col,
+            };
+
+            match transfer.upload {
+                true => col.into(),
+                false => col.push(
+                    Button::new(&mut transfer.button, Text::new(tr.tr("save")))
+                        .on_press(ClientMessage::SaveFile(id))
+                        .style(style::Button(palette))
+                        .padding(3),
+                ).into(),
+            }
+        }
+        TransferState::Cancelled => col.push(
+            Text::new(tr.tr("transfer-cancelled"))
+                .color(palette.dim)
+        ).into(),
+    }
+}
+
+fn server_entry<'a>(announcement: &Announcement, addr: &SocketAddr, palette: style::Palette, tr: &Translator) -> Element<'a, ClientMessage> {
+    let mut details = Vec::new();
+
+    match announcement.users {
+        Some((current, 0)) => details.push(tr.format("users-online", [("current", current.into())])),
+        Some((current, max)) => details.push(tr.format("users-online-max", [("current", current.into()), ("max", max.into())])),
+        None => (),
+    }
+
+    if announcement.encryption_required {
+        details.push(tr.tr("encrypted"));
+    }
+
+    if announcement.password_required {
+        details.push(tr.tr("password"));
+    }
+
+    let mut entry = Column::new()
+        .width(Length::Fill)
+        .spacing(2)
+        .push(Text::new(announcement.name.to_owned()))
+        .push(Text::new(addr.to_string()).size(12).color(palette.dim));
+
+    if !announcement.description.is_empty() {
+        entry = entry.push(Text::new(announcement.description.to_owned()).size(14));
+    }
+
+    if !details.is_empty() {
+        entry = entry.push(Text::new(details.join(" - ")).size(12));
+    }
+
+    // servers announcing no version predate versioning
+    let entry = match announcement.version {
+        Some(version) if version != PROTOCOL_VERSION => entry.push(
+            Text::new(tr.format("protocol-mismatch", [("version", version.into()), ("ours", PROTOCOL_VERSION.into())]))
+                .size(12)
+                .color(palette.error),
+        ),
+        _ => entry,
+    };
+
+    entry.into()
+}
+
+mod style {
+    use client_core::theme::{Rgb, Theme};
+    use iced::{Color, button, container, rule, text_input};
+
+    // the colours of a theme as iced takes them, every style is drawn from one
+    #[derive(Debug, Clone, Copy)]
+    pub struct Palette {
+        pub background: Color,
+        pub surface: Color,
+        pub text: Color,
+        pub dim: Color,
+        pub border: Color,
+        pub accent: Color,
+        pub active: Color,
+        pub hovered: Color,
+        pub button_text: Color,
+        pub own: Color,
+        pub name_text: Color,
+        pub bot: Color,
+        pub private: Color,
+        pub bold: Color,
+        pub italic: Color,
+        pub code: Color,
+        pub code_background: Color,
+        pub link: Color,
+        pub error: Color,
+        pub mention: Color,
+    }
+
+    impl From<&Theme> for Palette {
+        fn from(theme: &Theme) -> Self {
+            let color = |Rgb(r, g, b): Rgb| Color::from_rgb8(r, g, b);
+
+            Self {
+                background: color(theme.background),
+                surface: color(theme.surface),
+                text: color(theme.text),
+                dim: color(theme.dim),
+                border: color(theme.border),
+                accent: color(theme.accent),
+                active: color(theme.active),
+                hovered: color(theme.hovered),
+                button_text: color(theme.button_text),
+                own: color(theme.own),
+                name_text: color(theme.name_text),
+                bot: color(theme.bot),
+                private: color(theme.private),
+                bold: color(theme.bold),
+                italic: color(theme.italic),
+                code: color(theme.code),
+                code_background: color(theme.code_background),
+                link: color(theme.link),
+                error: color(theme.error),
+                mention: color(theme.mention),
+            }
+        }
+    }
+
+    pub struct TextInput(pub Palette);
     impl text_input::StyleSheet for TextInput {
         fn active(&self) -> text_input::Style {
             text_input::Style {
-                background: SURFACE.into(),
+                background: self.0.surface.into(),
                 border_radius: 2.0,
                 border_width: 0.0,
                 border_color: Color::TRANSPARENT,
@@ -224,30 +1129,30 @@ mod style {
         fn focused(&self) -> text_input::Style {
             text_input::Style {
                 border_width: 1.0,
-                border_color: ACCENT,
+                border_color: self.0.accent,
                 ..self.active()
             }
         }
 
         fn placeholder_color(&self) -> Color {
-            Color::from_rgb(0.5, 0.5, 0.5)
+            self.0.dim
         }
 
         fn value_color(&self) -> Color {
-            Color::WHITE
+            self.0.text
         }
 
         fn selection_color(&self) -> Color {
-            ACTIVE
+            self.0.active
         }
     }
 
-    pub struct Button;
+    pub struct Button(pub Palette);
     impl button::StyleSheet for Button {
         fn active(&self) -> button::Style {
             button::Style {
-                background: ACTIVE.into(),
-                text_color: Color::WHITE,
+                background: self.0.active.into(),
+                text_color: self.0.button_text,
                 border_radius: 3.0,
                 ..button::Style::default()
             }
@@ -255,27 +1160,27 @@ mod style {
 
         fn hovered(&self) -> button::Style {
             button::Style {
-                background: HOVERED.into(),
+                background: self.0.hovered.into(),
                 ..self.active()
             }
         }
 
         fn pressed(&self) -> button::Style {
             button::Style {
-                border_color: Color::WHITE,
+                border_color: self.0.button_text,
                 border_width: 1.0,
                 ..self.hovered()
             }
         }
     }
 
-    pub struct SelfContainer;
+    pub struct SelfContainer(pub Palette);
     impl container::StyleSheet for SelfContainer {
         fn style(&self) -> container::Style {
             container::Style {
-                text_color: Some(Color::WHITE),
+                text_color: Some(self.0.button_text),
                 background: Some(iced::Background::Color(
-                    Color::from_rgb8(60, 180, 110)
+                    self.0.own
                 )),
                 border_radius: 3.0,
                 ..Default::default()
@@ -283,24 +1188,141 @@ mod style {
         }
     }
 
-    pub struct GuestContainer;
+    pub struct GuestContainer(pub Palette);
     impl container::StyleSheet for GuestContainer {
         fn style(&self) -> container::Style {
             container::Style {
-                text_color: Some(Color::WHITE),
+                text_color: Some(self.0.text),
+                border_radius: 3.0,
+                border_width: 1.0,
+                border_color: self.0.border,
+                ..Default::default()
+            }
+        }
+    }
+
+    pub struct BotContainer(pub Palette);
+    impl container::StyleSheet for BotContainer {
+        fn style(&self) -> container::Style {
+            container::Style {
+                text_color: Some(self.0.bot),
+                border_radius: 3.0,
+                border_width: 1.0,
+                border_color: self.0.bot,
+                ..Default::default()
+            }
+        }
+    }
+
+    pub struct Flat(pub Palette);
+    impl button::StyleSheet for Flat {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: self.0.surface.into(),
+                text_color: self.0.text,
+                border_radius: 3.0,
+                ..button::Style::default()
+            }
+        }
+
+        fn hovered(&self) -> button::Style {
+            button::Style {
+                border_color: self.0.active,
+                border_width: 1.0,
+                ..self.active()
+            }
+        }
+    }
+
+    pub struct CodeBlock(pub Palette);
+    impl container::StyleSheet for CodeBlock {
+        fn style(&self) -> container::Style {
+            container::Style {
+                text_color: Some(self.0.code),
+                background: Some(self.0.code_background.into()),
+                border_radius: 3.0,
+                border_width: 1.0,
+                border_color: self.0.surface,
+                ..Default::default()
+            }
+        }
+    }
+
+    pub struct Link(pub Palette);
+    impl button::StyleSheet for Link {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: None,
+                ..button::Style::default()
+            }
+        }
+
+        fn hovered(&self) -> button::Style {
+            button::Style {
+                background: Some(self.0.surface.into()),
+                ..self.active()
+            }
+        }
+    }
+
+    pub struct Highlight(pub Palette);
+    impl container::StyleSheet for Highlight {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(self.0.surface.into()),
+                border_radius: 3.0,
+                border_width: 1.0,
+                border_color: self.0.accent,
+                ..Default::default()
+            }
+        }
+    }
+
+    // a message that mentions us or was sent to us alone
+    pub struct Mention(pub Palette);
+    impl container::StyleSheet for Mention {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(self.0.mention.into()),
+                border_radius: 3.0,
+                border_width: 1.0,
+                border_color: self.0.private,
+                ..Default::default()
+            }
+        }
+    }
+
+    pub struct Banner(pub Palette);
+    impl container::StyleSheet for Banner {
+        fn style(&self) -> container::Style {
+            container::Style {
+                text_color: Some(self.0.text),
+                background: Some(self.0.surface.into()),
                 border_radius: 3.0,
                 border_width: 1.0,
-                border_color: Color::from_rgb(0.5, 0.5, 0.5),
+                border_color: self.0.active,
                 ..Default::default()
             }
         }
     }
 
-    pub struct Rule;
+    pub struct NewMessages(pub Palette);
+    impl rule::StyleSheet for NewMessages {
+        fn style(&self) -> rule::Style {
+            rule::Style {
+                color: self.0.error,
+                width: 1,
+                radius: 0.0,
+                fill_mode: rule::FillMode::Full,
+            }
+        }
+    }
+
+    pub struct Rule(pub Palette);
     impl rule::StyleSheet for Rule {
         fn style(&self) -> rule::Style {
             rule::Style {
-                color: Color::from_rgb(0.5, 0.5, 0.5),
+                color: self.0.border,
                 width: 1,
                 radius: 0.0,
                 fill_mode: rule::FillMode::Full,
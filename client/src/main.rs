@@ -3,11 +3,50 @@
 
 mod client;
 
-use client::Client;
+use client::{Client, Flags, Preferences};
+use client_core::identity;
+use protocol::multicast::Discovery;
 use iced::{Application, Settings, window::{self, Icon}};
 use image::{GenericImageView, io::Reader as ImReader};
 
 pub fn main() {
+    let mut discovery = match Discovery::from_env() {
+        Ok(discovery) => discovery,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let mut server = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let set = match arg.starts_with("--") {
+            true => match args.next() {
+                Some(value) => discovery.set(&arg, &value),
+                None => Err(format!("{}: missing value", arg)),
+            },
+            false if server.is_some() => Err(format!("{}: only one server can be given", arg)),
+            false => {
+                server = Some(arg);
+                continue;
+            }
+        };
+
+        if let Err(err) = set {
+            println!("{}", err);
+            println!("usage: client.exe [host:port] [--multicast-group ADDR] [--multicast-port N] [--multicast-ttl N] [--multicast-interface ADDR] [--multicast-group-v6 ADDR] [--multicast-interface-v6 INDEX]");
+            return;
+        }
+    }
+
+    let preferences = Preferences::load();
+
+    let (secret, error) = identity::load("identity");
+    if let Some(err) = error {
+        println!("{}", err);
+    }
+
     let path = "./resources/aircraft.png";
     let icon = match ImReader::open(path) {
         Ok(buffer) => match buffer.decode() {
@@ -22,11 +61,20 @@ pub fn main() {
         _ => None,
     };
 
+    let mut window = window::Settings {
+        icon,
+        ..Default::default()
+    };
+
+    if let Some(size) = preferences.window {
+        window.size = size;
+    }
+
     Client::run(Settings {
-        window: window::Settings {
-            icon,
-            ..Default::default()
-        },
+        window,
+        flags: Flags { discovery, server, preferences, secret },
+        // closing saves the window's size first
+        exit_on_close_request: false,
         ..Default::default()
     }).unwrap();
 }
@@ -0,0 +1,31 @@
+use sha2::{Digest, Sha256};
+
+pub const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+// keeps every FileChunk frame within MAX_FRAME_LEN
+pub const CHUNK_LEN: usize = 1000;
+pub const HASH_LEN: usize = 32;
+
+pub fn hash(data: &[u8]) -> [u8; HASH_LEN] {
+    let mut hash = [0; HASH_LEN];
+    hash.copy_from_slice(&Sha256::digest(data));
+
+    hash
+}
+
+// hashes and client secrets alike
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex<const LEN: usize>(hex: &str) -> Option<[u8; LEN]> {
+    if hex.len() != LEN * 2 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; LEN];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(bytes)
+}
@@ -2,12 +2,42 @@ mod network_message;
 
 pub mod multicast;
 pub mod encrypt;
+pub mod file;
+pub mod mention;
 
 pub mod network {
     pub const MULTICAST_ADDRESS: &str = "233.141.56.26";
+    // site-local scope, same group id as the IPv4 address
+    pub const MULTICAST_ADDRESS_V6: &str = "ff15::e98d:381a";
     pub const MULTICAST_PORT: u16 = 5358;
 
-    pub use super::network_message::NetworkMessage;
+    // bumped whenever a message changes in a way older peers can't read
+    pub const PROTOCOL_VERSION: u16 = 7;
+
+    // every frame must fit in a single read buffer
+    pub const MAX_FRAME_LEN: usize = 2048;
+    pub const MAX_NAME_LEN: usize = 32;
+    // random bytes each client keeps, they tell the server who is back whatever their name
+    pub const SECRET_LEN: usize = 16;
+    pub const MAX_MESSAGE_LEN: usize = 1000;
+    pub const MAX_QUERY_LEN: usize = u8::MAX as usize;
+    pub const MAX_AWAY_LEN: usize = u8::MAX as usize;
+    pub const MAX_MENTIONS: usize = 32;
+    pub const MAX_USER_LIST_LEN: usize = u16::MAX as usize;
+
+    // advertised in ServerInfo
+    pub mod capabilities {
+        pub const FILE_TRANSFER: u32 = 0x01;
+    }
+
+    // why the server closed the connection or turned a request down, sent in Refused
+    pub mod refusal {
+        pub const SERVER_FULL: u8 = 0x01;
+        // answers a UserRename, the connection stays open
+        pub const NAME_TAKEN: u8 = 0x02;
+    }
+
+    pub use super::network_message::{NetworkMessage, FrameReader};
 }
 
 #[cfg(test)]
@@ -16,41 +46,46 @@ mod slice_to_msg {
 
     #[test]
     fn client_identity() {
-        let slice = &[0x4F, 0x04, 0x04, b'U', b's', b'e', b'r'];
+        let slice = &[0x4F, 0x04, 0x04, b'U', b's', b'e', b'r', 0x01, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
         assert_eq!(msg, NetworkMessage::client_identity(
-            String::from("User")
+            String::from("User"),
+            true,
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
         ));
     }
 
     #[test]
     fn message() {
-        let slice = &[0x4F, 0x20, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x0C, b'H', b'e', b'l', b'l', b'o', b',', b' ', b'w', b'o', b'r', b'l', b'd'];
+        let slice = &[0x4F, 0x20, 0x00, 0x00, 0x00, 0x2A, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x0C, b'H', b'e', b'l', b'l', b'o', b',', b' ', b'w', b'o', b'r', b'l', b'd', 0x00];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
         assert_eq!(msg, NetworkMessage::message(
+            42,
             1_579_631_826,
-            String::from("Hello, world")
+            String::from("Hello, world"),
+            Vec::new()
         ));
     }
 
     #[test]
     fn personal_id() {
-        let slice = &[0x4F, 0x1F, 0xD4, 0x25, 0x97, 0xE0];
+        let slice = &[0x4F, 0x1F, 0xD4, 0x25, 0x97, 0xE0, 0x01];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
-        assert_eq!(msg, NetworkMessage::personal_id(3_559_233_504));
+        assert_eq!(msg, NetworkMessage::personal_id(3_559_233_504, true));
     }
 
     #[test]
     fn user_join() {
-        let slice = &[0x4F, 0x16, 0x04, b'U', b's', b'e', b'r', 0xF1, 0x58, 0xB4, 0x49];
+        let slice = &[0x4F, 0x16, 0x04, b'U', b's', b'e', b'r', 0xF1, 0x58, 0xB4, 0x49, 0x01];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
         assert_eq!(msg, NetworkMessage::user_join(
             String::from("User"),
-            4_049_122_377
+            4_049_122_377,
+            true
         ));
     }
 
@@ -64,10 +99,48 @@ mod slice_to_msg {
         ));
     }
 
+    #[test]
+    fn user_rename() {
+        let slice = &[0x4F, 0x1B, 0x41, 0xDC, 0x3E, 0xAB, 0x03, b'B', b'o', b'b'];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::user_rename(1_104_953_003, String::from("Bob")));
+    }
+
+    #[test]
+    fn away() {
+        let slice = &[0x4F, 0x1C, 0x41, 0xDC, 0x3E, 0xAB, 0x01, 0x05, b'L', b'u', b'n', b'c', b'h'];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::away(1_104_953_003, true, String::from("Lunch")));
+
+        // neither away nor back
+        let slice = &[0x4F, 0x1C, 0x41, 0xDC, 0x3E, 0xAB, 0x02, 0x00];
+        assert!(NetworkMessage::from_slice(slice).is_err());
+    }
+
+    #[test]
+    fn message_with_mentions() {
+        let slice = &[0x4F, 0x20, 0x00, 0x00, 0x00, 0x2A, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x03, b'@', b'B', b'o', 0x02, 0x41, 0xDC, 0x3E, 0xAB, 0x00, 0x00, 0x00, 0x07];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::message(42, 1_579_631_826, String::from("@Bo"), vec![1_104_953_003, 7]));
+        assert_eq!(NetworkMessage::frame_len(slice), Ok(Some(slice.len())));
+        assert!(NetworkMessage::from_slice(&slice[..slice.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn private_message() {
+        let slice = &[0x4F, 0x21, 0x5E, 0x27, 0x44, 0xD2, 0x41, 0xDC, 0x3E, 0xAB, 0x00, 0x02, b'H', b'i'];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::private_message(1_579_631_826, 1_104_953_003, String::from("Hi")));
+    }
+
     #[test]
     fn user_list() {
         // empty
-        let slice = &[0x4F, 0x10, 0x00];
+        let slice = &[0x4F, 0x10, 0x00, 0x00];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
         assert_eq!(msg, NetworkMessage::user_list(
@@ -75,21 +148,163 @@ mod slice_to_msg {
         ));
 
         // len = 3
-        let slice = &[0x4F, 0x10, 0x03,
-            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1',
-            0x91, 0x02, 0x0D, 0x70, 0x06, b'U', b's', b'e', b'r', b'_', b'2',
-            0x76, 0x54, 0xB7, 0xD2, 0x06, b'U', b's', b'e', b'r', b'_', b'3'
+        let slice = &[0x4F, 0x10, 0x00, 0x03,
+            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1', 0x00,
+            0x91, 0x02, 0x0D, 0x70, 0x06, b'U', b's', b'e', b'r', b'_', b'2', 0x01,
+            0x76, 0x54, 0xB7, 0xD2, 0x06, b'U', b's', b'e', b'r', b'_', b'3', 0x00
         ];
         let msg = NetworkMessage::from_slice(slice).unwrap();
 
         assert_eq!(msg, NetworkMessage::user_list(
             vec![
-                (1_073_776_589, String::from("User_1")),
-                (2_432_830_832, String::from("User_2")),
-                (1_985_263_570, String::from("User_3"))
+                (1_073_776_589, String::from("User_1"), false),
+                (2_432_830_832, String::from("User_2"), true),
+                (1_985_263_570, String::from("User_3"), false)
             ]
         ));
     }
+
+    #[test]
+    fn user_list_chunk() {
+        let slice = &[0x4F, 0x11, 0x01, 0x00, 0x01,
+            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1', 0x01
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::user_list_chunk(
+            vec![(1_073_776_589, String::from("User_1"), true)],
+            true
+        ));
+    }
+
+    #[test]
+    fn rate_limited() {
+        let slice = &[0x4F, 0x30, 0x00, 0x00, 0x03, 0xE8, 0x01];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::rate_limited(1_000, true));
+    }
+
+    #[test]
+    fn refused() {
+        let slice = &[0x4F, 0x31, 0x01];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::refused(crate::network::refusal::SERVER_FULL));
+    }
+
+    #[test]
+    fn file_chunk() {
+        let slice = &[0x4F, 0x41, 0x00, 0x00, 0x00, 0x07,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xE8,
+            0x00, 0x03, 0x01, 0x02, 0x03
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::file_chunk(7, 1_000, vec![0x01, 0x02, 0x03]));
+    }
+
+    #[test]
+    fn file_cancel() {
+        let slice = &[0x4F, 0x43, 0x00, 0x00, 0x00, 0x07, 0x41, 0xDC, 0x3E, 0xAB];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::file_cancel(7, 1_104_953_003));
+    }
+
+    #[test]
+    fn server_info() {
+        let slice = &[0x4F, 0x50, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+            0x03, b'L', b'a', b'b',
+            0x00,
+            0x00, 0x02, b'H', b'i',
+            0x00, 0x03, b'B', b'e', b'!',
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(NetworkMessage::frame_len(slice), Ok(Some(slice.len())));
+        assert_eq!(msg, NetworkMessage::server_info(
+            crate::network::capabilities::FILE_TRANSFER,
+            [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F],
+            String::from("Lab"),
+            String::new(),
+            String::from("Hi"),
+            String::from("Be!"),
+        ));
+        assert!(NetworkMessage::from_slice(&slice[..slice.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn pin() {
+        let slice = &[0x4F, 0x22, 0x00, 0x00, 0x00, 0x2A];
+
+        assert_eq!(NetworkMessage::from_slice(slice).unwrap(), NetworkMessage::pin(42));
+        assert_eq!(NetworkMessage::from_slice(&[0x4F, 0x23, 0x00, 0x00, 0x00, 0x2A]).unwrap(), NetworkMessage::unpin(42));
+    }
+
+    #[test]
+    fn search_request() {
+        let slice = &[0x4F, 0x60,
+            0x02, b'H', b'i',
+            0x04, b'U', b's', b'e', b'r',
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x14,
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(NetworkMessage::frame_len(slice), Ok(Some(slice.len())));
+        assert_eq!(msg, NetworkMessage::search_request(String::from("Hi"), String::from("User"), 100, 0, 20));
+        assert!(NetworkMessage::from_slice(&slice[..slice.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn search_result() {
+        let slice = &[0x4F, 0x61, 0x00, 0x00, 0x00, 0x2A,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64,
+            0x04, b'U', b's', b'e', b'r',
+            0x00, 0x02, b'H', b'i',
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(NetworkMessage::frame_len(slice), Ok(Some(slice.len())));
+        assert_eq!(msg, NetworkMessage::search_result(42, 100, String::from("User"), String::from("Hi")));
+        assert_eq!(NetworkMessage::from_slice(&[0x4F, 0x62, 0x00, 0x00, 0x01, 0x00]).unwrap(), NetworkMessage::search_end(256));
+    }
+
+    #[test]
+    fn read_marker() {
+        let slice = &[0x4F, 0x25, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x00, 0x00, 0x2A];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(msg, NetworkMessage::read_marker(1_579_631_826, 42));
+        assert_eq!(msg.into_vec().unwrap(), slice);
+        assert!(NetworkMessage::from_slice(&slice[..slice.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn pinned() {
+        let slice = &[0x4F, 0x24, 0x00, 0x00, 0x00, 0x2A,
+            0x04, b'U', b's', b'e', b'r',
+            0x00, 0x02, b'H', b'i',
+        ];
+        let msg = NetworkMessage::from_slice(slice).unwrap();
+
+        assert_eq!(NetworkMessage::frame_len(slice), Ok(Some(slice.len())));
+        assert_eq!(msg, NetworkMessage::pinned(42, String::from("User"), String::from("Hi")));
+        assert!(NetworkMessage::from_slice(&slice[..slice.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn message_too_long() {
+        let len = crate::network::MAX_MESSAGE_LEN + 1;
+        let mut slice = vec![0x4F, 0x20, 0x00, 0x00, 0x00, 0x00, 0x5E, 0x27, 0x44, 0xD2];
+        slice.extend_from_slice(&(len as u16).to_be_bytes());
+        slice.resize(slice.len() + len, b'a');
+
+        assert!(NetworkMessage::from_slice(&slice).is_err());
+    }
 }
 
 #[cfg(test)]
@@ -98,38 +313,43 @@ mod msg_to_slice {
 
     #[test]
     fn client_identity() {
-        let slice = [0x4F, 0x04, 0x04, b'U', b's', b'e', b'r'];
+        let slice = [0x4F, 0x04, 0x04, b'U', b's', b'e', b'r', 0x00, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7];
 
         assert_eq!(&slice[..], NetworkMessage::client_identity(
-            String::from("User")
-        ).into_vec());
+            String::from("User"),
+            false,
+            [7; 16]
+        ).into_vec().unwrap());
     }
 
     #[test]
     fn message() {
-        let slice = [0x4F, 0x20, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x0C, b'H', b'e', b'l', b'l', b'o', b',', b' ', b'w', b'o', b'r', b'l', b'd'];
+        let slice = [0x4F, 0x20, 0x00, 0x00, 0x00, 0x2A, 0x5E, 0x27, 0x44, 0xD2, 0x00, 0x0C, b'H', b'e', b'l', b'l', b'o', b',', b' ', b'w', b'o', b'r', b'l', b'd', 0x00];
 
         assert_eq!(&slice[..], NetworkMessage::message(
+            42,
             1_579_631_826,
-            String::from("Hello, world")
-        ).into_vec());
+            String::from("Hello, world"),
+            Vec::new()
+        ).into_vec().unwrap());
     }
 
     #[test]
     fn personal_id() {
-        let slice = [0x4F, 0x1F, 0xD4, 0x25, 0x97, 0xE0];
+        let slice = [0x4F, 0x1F, 0xD4, 0x25, 0x97, 0xE0, 0x00];
 
-        assert_eq!(&slice[..], NetworkMessage::personal_id(3_559_233_504).into_vec());
+        assert_eq!(&slice[..], NetworkMessage::personal_id(3_559_233_504, false).into_vec().unwrap());
     }
 
     #[test]
     fn user_join() {
-        let slice = [0x4F, 0x16, 0x04, b'U', b's', b'e', b'r', 0xF1, 0x58, 0xB4, 0x49];
+        let slice = [0x4F, 0x16, 0x04, b'U', b's', b'e', b'r', 0xF1, 0x58, 0xB4, 0x49, 0x00];
 
         assert_eq!(&slice[..], NetworkMessage::user_join(
             String::from("User"),
-            4_049_122_377
-        ).into_vec());
+            4_049_122_377,
+            false
+        ).into_vec().unwrap());
     }
 
     #[test]
@@ -138,32 +358,300 @@ mod msg_to_slice {
 
         assert_eq!(&slice[..], NetworkMessage::user_leave(
             1_104_953_003
-        ).into_vec());
+        ).into_vec().unwrap());
+    }
+
+    #[test]
+    fn user_rename() {
+        let slice = [0x4F, 0x1B, 0x00, 0x00, 0x00, 0x00, 0x03, b'B', b'o', b'b'];
+
+        assert_eq!(&slice[..], NetworkMessage::user_rename(0, String::from("Bob")).into_vec().unwrap());
+        assert!(NetworkMessage::user_rename(0, String::new()).into_vec().is_err());
+        assert!(NetworkMessage::user_rename(0, "a".repeat(crate::network::MAX_NAME_LEN + 1)).into_vec().is_err());
+    }
+
+    #[test]
+    fn away() {
+        let slice = [0x4F, 0x1C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+        assert_eq!(&slice[..], NetworkMessage::away(0, false, String::new()).into_vec().unwrap());
+        assert!(NetworkMessage::away(0, true, "a".repeat(crate::network::MAX_AWAY_LEN + 1)).into_vec().is_err());
+    }
+
+    #[test]
+    fn private_message() {
+        let slice = [0x4F, 0x21, 0x00, 0x00, 0x00, 0x00, 0x41, 0xDC, 0x3E, 0xAB, 0x00, 0x02, b'H', b'i'];
+
+        assert_eq!(&slice[..], NetworkMessage::private_message(0, 1_104_953_003, String::from("Hi")).into_vec().unwrap());
     }
 
     #[test]
     fn user_list() {
         // empty
-        let slice = [0x4F, 0x10, 0x00];
+        let slice = [0x4F, 0x10, 0x00, 0x00];
 
         assert_eq!(&slice[..], NetworkMessage::user_list(
             vec![]
-        ).into_vec());
+        ).into_vec().unwrap());
 
         // len = 3
-        let slice = [0x4F, 0x10, 0x03,
-            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1',
-            0x91, 0x02, 0x0D, 0x70, 0x06, b'U', b's', b'e', b'r', b'_', b'2',
-            0x76, 0x54, 0xB7, 0xD2, 0x06, b'U', b's', b'e', b'r', b'_', b'3'
+        let slice = [0x4F, 0x10, 0x00, 0x03,
+            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1', 0x00,
+            0x91, 0x02, 0x0D, 0x70, 0x06, b'U', b's', b'e', b'r', b'_', b'2', 0x01,
+            0x76, 0x54, 0xB7, 0xD2, 0x06, b'U', b's', b'e', b'r', b'_', b'3', 0x00
         ];
 
         assert_eq!(&slice[..], NetworkMessage::user_list(
             vec![
-                (1_073_776_589, String::from("User_1")),
-                (2_432_830_832, String::from("User_2")),
-                (1_985_263_570, String::from("User_3"))
+                (1_073_776_589, String::from("User_1"), false),
+                (2_432_830_832, String::from("User_2"), true),
+                (1_985_263_570, String::from("User_3"), false)
             ]
-        ).into_vec());
+        ).into_vec().unwrap());
+    }
+
+    #[test]
+    fn user_list_chunk() {
+        let slice = [0x4F, 0x11, 0x01, 0x00, 0x01,
+            0x40, 0x00, 0x87, 0xCD, 0x06, b'U', b's', b'e', b'r', b'_', b'1', 0x01
+        ];
+
+        assert_eq!(&slice[..], NetworkMessage::user_list_chunk(
+            vec![(1_073_776_589, String::from("User_1"), true)],
+            true
+        ).into_vec().unwrap());
+    }
+
+    #[test]
+    fn user_list_frames() {
+        let users: Vec<_> = (0..300)
+            .map(|id| (id, format!("User_{}", id), id % 7 == 0))
+            .collect();
+
+        let frames = NetworkMessage::user_list_frames(users.clone());
+        assert!(frames.len() > 1);
+
+        let mut received = vec![];
+        for (i, frame) in frames.into_iter().enumerate() {
+            let frame = frame.into_vec().unwrap();
+            assert!(frame.len() <= crate::network::MAX_FRAME_LEN);
+
+            match NetworkMessage::from_slice(&frame).unwrap() {
+                NetworkMessage::UserListChunk(chunk) => {
+                    received.extend(chunk.users().iter().cloned());
+                    assert_eq!(chunk.more(), received.len() != users.len(), "chunk {}", i);
+                }
+                msg => panic!("expected UserListChunk, found {}", msg),
+            }
+        }
+
+        assert_eq!(received, users);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let slice = [0x4F, 0x30, 0x00, 0x00, 0x03, 0xE8, 0x01];
+
+        assert_eq!(&slice[..], NetworkMessage::rate_limited(1_000, true).into_vec().unwrap());
+    }
+
+    #[test]
+    fn refused() {
+        let slice = [0x4F, 0x31, 0x01];
+
+        assert_eq!(&slice[..], NetworkMessage::refused(crate::network::refusal::SERVER_FULL).into_vec().unwrap());
+    }
+
+    #[test]
+    fn file_offer() {
+        let hash = crate::file::hash(b"Hello, world");
+        let mut slice = vec![0x4F, 0x40, 0x00, 0x00, 0x00, 0x07, 0x41, 0xDC, 0x3E, 0xAB,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C,
+        ];
+        slice.extend_from_slice(&hash);
+        slice.extend_from_slice(&[0x05, b'a', b'.', b't', b'x', b't']);
+        slice.extend_from_slice(&[0x0A, b't', b'e', b'x', b't', b'/', b'p', b'l', b'a', b'i', b'n']);
+
+        let msg = NetworkMessage::file_offer(
+            7, 1_104_953_003, 12, hash,
+            String::from("a.txt"),
+            String::from("text/plain")
+        );
+
+        assert_eq!(NetworkMessage::frame_len(&slice), Ok(Some(slice.len())));
+        assert_eq!(slice, msg.clone().into_vec().unwrap());
+        assert_eq!(msg, NetworkMessage::from_slice(&slice).unwrap());
+    }
+
+    #[test]
+    fn file_chunk() {
+        let slice = [0x4F, 0x41, 0x00, 0x00, 0x00, 0x07,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xE8,
+            0x00, 0x03, 0x01, 0x02, 0x03
+        ];
+
+        assert_eq!(&slice[..], NetworkMessage::file_chunk(7, 1_000, vec![0x01, 0x02, 0x03]).into_vec().unwrap());
+    }
+
+    #[test]
+    fn file_complete() {
+        let slice = [0x4F, 0x42, 0x00, 0x00, 0x00, 0x07, 0x41, 0xDC, 0x3E, 0xAB];
+
+        assert_eq!(&slice[..], NetworkMessage::file_complete(7, 1_104_953_003).into_vec().unwrap());
+    }
+
+    #[test]
+    fn file_request() {
+        let hash = crate::file::hash(b"Hello, world");
+        let mut slice = vec![0x4F, 0x44, 0x00, 0x00, 0x00, 0x07];
+        slice.extend_from_slice(&hash);
+
+        assert_eq!(slice, NetworkMessage::file_request(7, hash).into_vec().unwrap());
+        assert_eq!(NetworkMessage::from_slice(&slice).unwrap(), NetworkMessage::file_request(7, hash));
+    }
+
+    #[test]
+    fn message_too_long() {
+        let content = "a".repeat(crate::network::MAX_MESSAGE_LEN + 1);
+
+        assert!(NetworkMessage::message(0, 1_579_631_826, content, Vec::new()).into_vec().is_err());
+    }
+
+    #[test]
+    fn too_many_mentions() {
+        let mentions = (0..=crate::network::MAX_MENTIONS as u32).collect();
+
+        assert!(NetworkMessage::message(0, 1_579_631_826, String::from("Hi"), mentions).into_vec().is_err());
+    }
+
+    #[test]
+    fn server_info() {
+        let msg = NetworkMessage::server_info(0x01, [0xAB; 16], String::from("Lab"), String::from("Desc"), String::from("Hi"), String::new());
+        let slice = msg.into_vec().unwrap();
+
+        assert_eq!(&slice[..6], &[0x4F, 0x50, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(&slice[6..22], &[0xAB; 16]);
+        assert_eq!(slice.len(), 1 + 11 + 16 + 3 + 4 + 2);
+
+        let too_long = NetworkMessage::server_info(0, [0; 16], String::new(), String::new(), "a".repeat(crate::network::MAX_FRAME_LEN), String::new());
+        assert!(too_long.into_vec().is_err());
+    }
+
+    #[test]
+    fn pinned() {
+        let slice = [0x4F, 0x24, 0x00, 0x00, 0x00, 0x2A, 0x04, b'U', b's', b'e', b'r', 0x00, 0x02, b'H', b'i'];
+
+        assert_eq!(&slice[..], NetworkMessage::pinned(42, String::from("User"), String::from("Hi")).into_vec().unwrap());
+
+        // the longest message from the longest name still fits in a frame
+        let longest = NetworkMessage::pinned(
+            0,
+            "a".repeat(crate::network::MAX_NAME_LEN),
+            "a".repeat(crate::network::MAX_MESSAGE_LEN),
+        );
+        assert!(longest.into_vec().unwrap().len() <= crate::network::MAX_FRAME_LEN);
+    }
+
+    #[test]
+    fn search_request() {
+        let msg = NetworkMessage::search_request(String::new(), String::new(), 0, 100, 20);
+        let slice = msg.clone().into_vec().unwrap();
+
+        assert_eq!(slice.len(), msg.msg_len());
+        assert_eq!(NetworkMessage::from_slice(&slice).unwrap(), msg);
+
+        let too_long = NetworkMessage::search_request("a".repeat(crate::network::MAX_QUERY_LEN + 1), String::new(), 0, 0, 20);
+        assert!(too_long.into_vec().is_err());
+    }
+
+    #[test]
+    fn search_result() {
+        let longest = NetworkMessage::search_result(
+            0,
+            0,
+            "a".repeat(crate::network::MAX_NAME_LEN),
+            "a".repeat(crate::network::MAX_MESSAGE_LEN),
+        );
+
+        assert!(longest.into_vec().unwrap().len() <= crate::network::MAX_FRAME_LEN);
+    }
+
+    #[test]
+    fn user_list_too_long() {
+        let users = (0..=crate::network::MAX_USER_LIST_LEN as u32)
+            .map(|id| (id, format!("User_{}", id), false))
+            .collect();
+
+        assert!(NetworkMessage::user_list(users).into_vec().is_err());
+    }
+}
+
+#[cfg(test)]
+mod frame_reader {
+    use crate::network::{FrameReader, NetworkMessage};
+
+    #[test]
+    fn split_and_merged_frames() {
+        let first = NetworkMessage::personal_id(3_559_233_504, false);
+        let second = NetworkMessage::message(42, 1_579_631_826, String::from("Hello, world"), vec![7]);
+
+        let mut data = first.clone().into_vec().unwrap();
+        data.extend(second.clone().into_vec().unwrap());
+
+        let mut reader = FrameReader::new();
+        reader.extend(&data[..3]);
+        assert_eq!(reader.next_message(), Ok(None));
+
+        reader.extend(&data[3..]);
+        assert_eq!(reader.next_message(), Ok(Some(first)));
+        assert_eq!(reader.next_message(), Ok(Some(second)));
+        assert_eq!(reader.next_message(), Ok(None));
+    }
+
+    #[test]
+    fn wrong_start() {
+        let mut reader = FrameReader::new();
+        reader.extend(&[0x00, 0x1F, 0xD4, 0x25, 0x97, 0xE0, 0x00]);
+
+        assert!(reader.next_message().is_err());
+    }
+}
+
+#[cfg(test)]
+mod mentions {
+    use crate::mention::resolve;
+
+    #[test]
+    fn longest_name_wins() {
+        let users = [(1, "Bo"), (2, "Bo Peep"), (3, "Alice")];
+
+        assert_eq!(resolve("@bo peep and @ALICE, @alice again", users), [2, 3]);
+        assert_eq!(resolve("@Bo!", users), [1]);
+    }
+
+    #[test]
+    fn only_whole_names() {
+        let users = [(1, "Bo"), (2, "")];
+
+        assert!(resolve("@Bob", users).is_empty());
+        assert!(resolve("mail bo@Bo.com", users).is_empty());
+        assert!(resolve("@ alone", users).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod file_hash {
+    use crate::file::{from_hex, hash, to_hex, HASH_LEN};
+
+    #[test]
+    fn hex() {
+        let hash = hash(b"Hello, world");
+        let hex = to_hex(&hash);
+
+        assert_eq!(hex, "4ae7c3b6ac0beff671efa8cf57386151c06e58ca53a78d83f36107316cec125f");
+        assert_eq!(from_hex(&hex), Some(hash));
+        assert_eq!(from_hex::<2>("4ae7"), Some([0x4a, 0xe7]));
+        assert_eq!(from_hex::<HASH_LEN>("4ae7"), None);
     }
 }
 
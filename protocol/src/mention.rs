@@ -0,0 +1,31 @@
+use crate::network::MAX_MENTIONS;
+
+// ids of the users an `@name` in `content` refers to, in the order they are first named
+// names may contain spaces, the longest one that matches wins
+pub fn resolve<'a>(content: &str, users: impl IntoIterator<Item = (u32, &'a str)>) -> Vec<u32> {
+    let users: Vec<_> = users.into_iter().filter(|(_, name)| !name.is_empty()).collect();
+    let mut mentions = Vec::new();
+
+    for (index, _) in content.match_indices('@') {
+        // mail addresses are not mentions
+        if content[..index].chars().last().is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+
+        let rest = &content[index + 1..];
+        let found = users.iter()
+            .filter(|(_, name)| {
+                let named = rest.get(..name.len()).is_some_and(|start| start.eq_ignore_ascii_case(name));
+                named && !rest[name.len()..].starts_with(char::is_alphanumeric)
+            })
+            .max_by_key(|(_, name)| name.len());
+
+        if let Some((id, _)) = found {
+            if !mentions.contains(id) && mentions.len() < MAX_MENTIONS {
+                mentions.push(*id);
+            }
+        }
+    }
+
+    mentions
+}
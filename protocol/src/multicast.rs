@@ -1,46 +1,238 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::str::FromStr;
+
+pub const SERVER_ID_LEN: usize = 16;
+
+// everything a server tells about itself, fields after the port are optional and only sent
+// in the extended layout, clients from before it read nothing but the name and the port
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Announcement {
+    pub name: String,
+    pub port: u16,
+    // addresses the server can be reached at, besides the one answering
+    pub endpoints: Vec<SocketAddr>,
+    pub description: String,
+    // (current, max), max is 0 when unlimited
+    pub users: Option<(u16, u16)>,
+    pub version: Option<u16>,
+    pub encryption_required: bool,
+    pub password_required: bool,
+    pub server_id: Option<[u8; SERVER_ID_LEN]>,
+}
+
+impl Announcement {
+    // [type, len, value...], unknown types are skipped when reading
+    const ENDPOINT: u8 = 0x01;
+    const DESCRIPTION: u8 = 0x02;
+    const USERS: u8 = 0x03;
+    const VERSION: u8 = 0x04;
+    const REQUIREMENTS: u8 = 0x05;
+    const SERVER_ID: u8 = 0x06;
+
+    const ENCRYPTION: u8 = 0x01;
+    const PASSWORD: u8 = 0x02;
+
+    pub fn new(name: String, port: u16) -> Self {
+        Self { name, port, ..Default::default() }
+    }
+
+    fn push_field(vec: &mut Vec<u8>, kind: u8, value: &[u8]) {
+        vec.push(kind);
+        vec.push(value.len() as u8);
+        vec.extend_from_slice(value);
+    }
+
+    fn into_vec(self, vec: &mut Vec<u8>, extended: bool) {
+        let name_len = self.name.len();
+        if name_len > u8::MAX as usize {
+            panic!("server name should be shorter than {} characters, found {}", u8::MAX, name_len);
+        }
+
+        vec.push(name_len as u8);
+        vec.extend(self.name.into_bytes());
+        vec.extend_from_slice(&self.port.to_be_bytes());
+
+        if !extended {
+            return;
+        }
+
+        for endpoint in self.endpoints {
+            let mut value = match endpoint.ip() {
+                IpAddr::V4(ip) => ip.octets().to_vec(),
+                IpAddr::V6(ip) => ip.octets().to_vec(),
+            };
+
+            value.extend_from_slice(&endpoint.port().to_be_bytes());
+            Self::push_field(vec, Self::ENDPOINT, &value);
+        }
+
+        if !self.description.is_empty() {
+            // a field holds at most 255 byte, cut on a character boundary
+            let mut end = self.description.len().min(u8::MAX as usize);
+            while !self.description.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            Self::push_field(vec, Self::DESCRIPTION, &self.description.as_bytes()[..end]);
+        }
+
+        if let Some((current, max)) = self.users {
+            let mut value = current.to_be_bytes().to_vec();
+            value.extend_from_slice(&max.to_be_bytes());
+            Self::push_field(vec, Self::USERS, &value);
+        }
+
+        if let Some(version) = self.version {
+            Self::push_field(vec, Self::VERSION, &version.to_be_bytes());
+        }
+
+        let mut requirements = 0;
+        if self.encryption_required {
+            requirements |= Self::ENCRYPTION;
+        }
+        if self.password_required {
+            requirements |= Self::PASSWORD;
+        }
+        if requirements != 0 {
+            Self::push_field(vec, Self::REQUIREMENTS, &[requirements]);
+        }
+
+        if let Some(server_id) = self.server_id {
+            Self::push_field(vec, Self::SERVER_ID, &server_id);
+        }
+    }
+
+    fn from_slice(slice: &[u8], extended: bool) -> Result<Self, IntoMulticastError> {
+        let name_len = slice[0] as usize;
+
+        // the old layout is exactly the name and the port
+        if slice.len() < name_len + 3 || (!extended && slice.len() != name_len + 3) {
+            return Err(IntoMulticastError::IncompleteData);
+        }
+
+        let name = std::str::from_utf8(&slice[1..1 + name_len])
+            .map_err(|_| IntoMulticastError::InvalidData)?
+            .to_owned();
+        let port = u16::from_be_bytes([slice[1 + name_len], slice[2 + name_len]]);
+
+        let mut announcement = Self::new(name, port);
+        let mut fields = &slice[3 + name_len..];
+
+        while !fields.is_empty() {
+            let (kind, len) = match fields {
+                [kind, len, ..] => (*kind, *len as usize),
+                _ => return Err(IntoMulticastError::IncompleteData),
+            };
+
+            let value = fields.get(2..2 + len).ok_or(IntoMulticastError::IncompleteData)?;
+            fields = &fields[2 + len..];
+
+            match (kind, value) {
+                (Self::ENDPOINT, [a, b, c, d, port @ ..]) if len == 6 => {
+                    let port = u16::from_be_bytes([port[0], port[1]]);
+                    announcement.endpoints.push(SocketAddr::new(Ipv4Addr::new(*a, *b, *c, *d).into(), port));
+                }
+                (Self::ENDPOINT, value) if len == 18 => {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(&value[..16]);
+
+                    let port = u16::from_be_bytes([value[16], value[17]]);
+                    announcement.endpoints.push(SocketAddr::new(Ipv6Addr::from(octets).into(), port));
+                }
+                (Self::DESCRIPTION, value) => {
+                    announcement.description = std::str::from_utf8(value)
+                        .map_err(|_| IntoMulticastError::InvalidData)?
+                        .to_owned();
+                }
+                (Self::USERS, [c0, c1, m0, m1]) => {
+                    announcement.users = Some((u16::from_be_bytes([*c0, *c1]), u16::from_be_bytes([*m0, *m1])));
+                }
+                (Self::VERSION, [v0, v1]) => announcement.version = Some(u16::from_be_bytes([*v0, *v1])),
+                (Self::REQUIREMENTS, [requirements]) => {
+                    announcement.encryption_required = requirements & Self::ENCRYPTION != 0;
+                    announcement.password_required = requirements & Self::PASSWORD != 0;
+                }
+                (Self::SERVER_ID, value) if len == SERVER_ID_LEN => {
+                    let mut server_id = [0; SERVER_ID_LEN];
+                    server_id.copy_from_slice(value);
+                    announcement.server_id = Some(server_id);
+                }
+                (Self::ENDPOINT..=Self::SERVER_ID, _) => return Err(IntoMulticastError::InvalidData),
+                // added by a newer server
+                _ => (),
+            }
+        }
+
+        Ok(announcement)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MulticastMessage {
-    content: Option<(String, u16)>,
+    content: Option<Announcement>,
+    // sent once by a server shutting down
+    goodbye: bool,
+    // in the layout every client reads: a ping answered with it, or an announcement of only a name and a port
+    legacy: bool,
 }
 
 impl MulticastMessage {
+    const START: u8 = 0x4F;
+    // announcements with fields, and the pings asking for them
+    const EXTENDED: u8 = 0x41;
+    const GOODBYE: u8 = 0x47;
+
     pub fn ping() -> Self {
-        Self { content: None }
+        Self { content: None, goodbye: false, legacy: true }
+    }
+
+    pub fn extended_ping() -> Self {
+        Self { content: None, goodbye: false, legacy: false }
     }
 
     pub fn server_identity(name: String, port: u16) -> Self {
-        Self { content: Some((name, port)) }
+        Self { content: Some(Announcement::new(name, port)), goodbye: false, legacy: true }
+    }
+
+    pub fn announce(announcement: Announcement) -> Self {
+        Self { content: Some(announcement), goodbye: false, legacy: false }
+    }
+
+    pub fn goodbye(announcement: Announcement) -> Self {
+        Self { content: Some(announcement), goodbye: true, legacy: false }
     }
 
     pub fn is_ping(&self) -> bool {
         self.content.is_none()
     }
 
-    pub fn content(&self) -> &Option<(String, u16)> {
+    pub fn is_goodbye(&self) -> bool {
+        self.goodbye
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    pub fn content(&self) -> &Option<Announcement> {
         &self.content
     }
 }
 
 impl From<MulticastMessage> for Vec<u8> {
     fn from(msg: MulticastMessage) -> Self {
-        match msg.content {
-            Some((name, port)) => {
-                let name_len = name.len();
-                if name_len > u8::MAX as usize {
-                    panic!("server name should be shorter than {} characters, found {}", u8::MAX, name_len);
-                }
-
-                let mut vec = Vec::with_capacity(name_len + 4);
+        let mut vec = match (msg.goodbye, msg.legacy) {
+            (true, _) => vec![MulticastMessage::GOODBYE],
+            (false, true) => vec![MulticastMessage::START],
+            (false, false) => vec![MulticastMessage::EXTENDED],
+        };
 
-                vec.push(0x4F);
-                vec.push(name_len as u8);
-                vec.extend(name.into_bytes());
-                vec.extend_from_slice(&port.to_be_bytes());
-
-                vec
-            }
-            None => vec![0x4F],
+        if let Some(announcement) = msg.content {
+            announcement.into_vec(&mut vec, !msg.legacy);
         }
+
+        vec
     }
 }
 
@@ -48,57 +240,188 @@ impl From<MulticastMessage> for Vec<u8> {
 pub enum IntoMulticastError {
     WrongStart,
     IncompleteData,
+    InvalidData,
 }
 
 impl std::convert::TryInto<MulticastMessage> for &[u8] {
     type Error = IntoMulticastError;
 
     fn try_into(self) -> Result<MulticastMessage, Self::Error> {
-        let slice_len = self.len();
+        match self {
+            [] => Err(Self::Error::IncompleteData),
+            [MulticastMessage::START] => Ok(MulticastMessage::ping()),
+            [MulticastMessage::EXTENDED] => Ok(MulticastMessage::extended_ping()),
+            [MulticastMessage::START, announcement @ ..] => {
+                let Announcement { name, port, .. } = Announcement::from_slice(announcement, false)?;
+                Ok(MulticastMessage::server_identity(name, port))
+            }
+            [MulticastMessage::EXTENDED, announcement @ ..] => Ok(MulticastMessage::announce(Announcement::from_slice(announcement, true)?)),
+            [MulticastMessage::GOODBYE, announcement @ ..] if !announcement.is_empty() => {
+                Ok(MulticastMessage::goodbye(Announcement::from_slice(announcement, true)?))
+            }
+            _ => Err(Self::Error::WrongStart),
+        }
+    }
+}
 
-        if slice_len < 1 {
-            return Err(Self::Error::IncompleteData);
+impl std::fmt::Display for MulticastMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.content, self.goodbye) {
+            (Some(_), true) => write!(f, "Goodbye"),
+            (Some(_), false) => write!(f, "Server identity"),
+            (None, _) => write!(f, "Ping"),
         }
+    }
+}
 
-        if self[0] == 0x4F {
-            if slice_len > 1 {
-                let name_len = self[1] as usize;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Discovery {
+    pub group: Ipv4Addr,
+    pub port: u16,
+    pub ttl: u32,
+    // unspecified means every interface
+    pub interface: Ipv4Addr,
+    pub group_v6: Ipv6Addr,
+    // interface index, 0 lets the system choose
+    pub interface_v6: u32,
+}
 
-                if slice_len != name_len + 4 {
-                    return Err(Self::Error::IncompleteData);
-                }
+impl Default for Discovery {
+    fn default() -> Self {
+        Self {
+            group: Ipv4Addr::from_str(crate::network::MULTICAST_ADDRESS).unwrap(),
+            port: crate::network::MULTICAST_PORT,
+            ttl: 1,
+            interface: Ipv4Addr::UNSPECIFIED,
+            group_v6: Ipv6Addr::from_str(crate::network::MULTICAST_ADDRESS_V6).unwrap(),
+            interface_v6: 0,
+        }
+    }
+}
 
-                let name = std::str::from_utf8(
-                    &self[2..2 + name_len]
-                ).unwrap().to_owned();
+impl Discovery {
+    const FLAGS: [(&'static str, &'static str); 6] = [
+        ("--multicast-group", "MESSAGING_MULTICAST_GROUP"),
+        ("--multicast-port", "MESSAGING_MULTICAST_PORT"),
+        ("--multicast-ttl", "MESSAGING_MULTICAST_TTL"),
+        ("--multicast-interface", "MESSAGING_MULTICAST_INTERFACE"),
+        ("--multicast-group-v6", "MESSAGING_MULTICAST_GROUP_V6"),
+        ("--multicast-interface-v6", "MESSAGING_MULTICAST_INTERFACE_V6"),
+    ];
 
-                let mut port = [0; 2];
-                port.copy_from_slice(&self[2 + name_len..]);
-                let port = u16::from_be_bytes(port);
+    pub fn from_env() -> Result<Self, String> {
+        let mut discovery = Self::default();
 
-                Ok(MulticastMessage::server_identity(name, port))
-            } else {
-                Ok(MulticastMessage::ping())
+        for (flag, var) in Self::FLAGS {
+            if let Ok(value) = std::env::var(var) {
+                discovery.set(flag, &value).map_err(|err| format!("{}: {}", var, err))?;
             }
-        } else {
-            Err(Self::Error::WrongStart)
         }
+
+        Ok(discovery)
     }
-}
 
-impl std::fmt::Display for MulticastMessage {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.content {
-            Some(_) => write!(f, "Server identity"),
-            None => write!(f, "Ping"),
+    pub fn is_flag(flag: &str) -> bool {
+        Self::FLAGS.iter().any(|(known, _)| *known == flag)
+    }
+
+    pub fn set(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        match flag {
+            "--multicast-group" => {
+                let group = Ipv4Addr::from_str(value).map_err(|err| format!("{}: {}", flag, err))?;
+
+                // multicast address must be between
+                // 224.x.x.x and 239.x.x.x => D class
+                if !group.is_multicast() {
+                    return Err(format!("{}: {} is not a multicast address", flag, group));
+                }
+
+                self.group = group;
+            }
+            "--multicast-port" => self.port = value.parse().map_err(|_| format!("{}: expected a port, found {}", flag, value))?,
+            "--multicast-ttl" => self.ttl = value.parse().map_err(|_| format!("{}: expected a number, found {}", flag, value))?,
+            "--multicast-interface" => self.interface = Ipv4Addr::from_str(value).map_err(|err| format!("{}: {}", flag, err))?,
+            "--multicast-group-v6" => {
+                let group = Ipv6Addr::from_str(value).map_err(|err| format!("{}: {}", flag, err))?;
+
+                // ff00::/8, the scope (link, site...) is left to the user
+                if !group.is_multicast() {
+                    return Err(format!("{}: {} is not a multicast address", flag, group));
+                }
+
+                self.group_v6 = group;
+            }
+            "--multicast-interface-v6" => self.interface_v6 = value.parse().map_err(|_| format!("{}: expected an interface index, found {}", flag, value))?,
+            unknown => return Err(format!("Unknown flag: {}", unknown)),
         }
+
+        Ok(())
+    }
+
+    pub fn group_addr(&self) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new(self.group, self.port))
+    }
+
+    pub fn group_addr_v6(&self) -> SocketAddr {
+        SocketAddr::V6(SocketAddrV6::new(self.group_v6, self.port, 0, self.interface_v6))
+    }
+
+    // socket joined to the group, used by servers to answer pings
+    pub fn listen(&self) -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+
+        // several servers may run on the same host
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, self.port)).into())?;
+        socket.join_multicast_v4(&self.group, &self.interface)?;
+        socket.set_multicast_if_v4(&self.interface)?;
+        socket.set_multicast_ttl_v4(self.ttl)?;
+
+        Ok(socket.into())
+    }
+
+    // socket sending pings out of the given interface
+    pub fn probe(&self, interface: Ipv4Addr) -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(interface, 0)).into())?;
+        socket.set_multicast_if_v4(&interface)?;
+        socket.set_multicast_ttl_v4(self.ttl)?;
+
+        Ok(socket.into())
+    }
+
+    pub fn listen_v6(&self) -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+
+        // the IPv4 socket already holds this port
+        socket.set_only_v6(true)?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, self.port, 0, 0)).into())?;
+        socket.join_multicast_v6(&self.group_v6, self.interface_v6)?;
+        socket.set_multicast_if_v6(self.interface_v6)?;
+        socket.set_multicast_hops_v6(self.ttl)?;
+
+        Ok(socket.into())
+    }
+
+    pub fn probe_v6(&self) -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+
+        socket.set_only_v6(true)?;
+        socket.bind(&SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0)).into())?;
+        socket.set_multicast_if_v6(self.interface_v6)?;
+        socket.set_multicast_hops_v6(self.ttl)?;
+
+        Ok(socket.into())
     }
 }
 
 #[cfg(test)]
 mod test {
     use std::convert::TryInto;
-    use super::MulticastMessage;
+    use std::net::Ipv4Addr;
+    use super::{Announcement, Discovery, MulticastMessage};
 
     #[test]
     fn ping() {
@@ -127,4 +450,113 @@ mod test {
 
         assert_eq!(si, msg);
     }
+
+    #[test]
+    fn announcement() {
+        let si = MulticastMessage::announce(Announcement {
+            endpoints: vec![
+                "[fe80::1]:4338".parse().unwrap(),
+                "192.168.1.10:4338".parse().unwrap(),
+            ],
+            description: String::from("Lab server"),
+            users: Some((3, 50)),
+            version: Some(1),
+            encryption_required: true,
+            password_required: false,
+            server_id: Some([7; 16]),
+            ..Announcement::new(String::from("Server_name"), 4338)
+        });
+
+        let vec: Vec<_> = si.clone().into();
+        let msg: MulticastMessage = vec
+            .as_slice()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(si, msg);
+
+        let truncated: Result<MulticastMessage, _> = vec[..vec.len() - 1].try_into();
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn announcement_unknown_field() {
+        let announcement = Announcement::new(String::from("Server_name"), 4338);
+        let mut vec: Vec<_> = MulticastMessage::announce(announcement.clone()).into();
+        vec.extend_from_slice(&[0xF0, 0x02, 0xAB, 0xCD]);
+
+        let msg: MulticastMessage = vec
+            .as_slice()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(msg, MulticastMessage::announce(announcement));
+    }
+
+    // what servers and clients from before the extended layout send and expect
+    #[test]
+    fn legacy_layout() {
+        let old = [0x4F, 0x0B, b'S', b'e', b'r', b'v', b'e', b'r', b'_', b'n', b'a', b'm', b'e', 0x10, 0xF2];
+
+        let msg: MulticastMessage = old[..].try_into().unwrap();
+        assert!(msg.is_legacy());
+        assert_eq!(msg, MulticastMessage::server_identity(String::from("Server_name"), 4338));
+
+        let vec: Vec<_> = msg.into();
+        assert_eq!(vec, old);
+
+        // fields never go out in the old layout, old clients expect the exact length
+        let announcement = Announcement { description: String::from("Lab server"), ..Announcement::new(String::from("Server_name"), 4338) };
+        let vec: Vec<_> = MulticastMessage::server_identity(announcement.name, announcement.port).into();
+        assert_eq!(vec, old);
+
+        let mut trailing = old.to_vec();
+        trailing.push(0x00);
+        assert!(TryInto::<MulticastMessage>::try_into(trailing.as_slice()).is_err());
+
+        let ping: MulticastMessage = [0x4F][..].try_into().unwrap();
+        assert!(ping.is_ping() && ping.is_legacy());
+        let ping: MulticastMessage = [0x41][..].try_into().unwrap();
+        assert!(ping.is_ping() && !ping.is_legacy());
+    }
+
+    #[test]
+    fn goodbye() {
+        let announcement = Announcement {
+            server_id: Some([7; 16]),
+            ..Announcement::new(String::from("Server_name"), 4338)
+        };
+
+        let goodbye = MulticastMessage::goodbye(announcement);
+        let vec: Vec<_> = goodbye.clone().into();
+        let msg: MulticastMessage = vec
+            .as_slice()
+            .try_into()
+            .unwrap();
+
+        assert!(msg.is_goodbye());
+        assert_eq!(goodbye, msg);
+    }
+
+    #[test]
+    fn discovery_flags() {
+        let mut discovery = Discovery::default();
+
+        discovery.set("--multicast-group", "239.1.2.3").unwrap();
+        discovery.set("--multicast-port", "6000").unwrap();
+        discovery.set("--multicast-ttl", "4").unwrap();
+        discovery.set("--multicast-interface", "192.168.1.10").unwrap();
+
+        assert_eq!(discovery.group_addr().to_string(), "239.1.2.3:6000");
+        assert_eq!(discovery.ttl, 4);
+        assert_eq!(discovery.interface, Ipv4Addr::new(192, 168, 1, 10));
+
+        discovery.set("--multicast-group-v6", "ff02::1234").unwrap();
+        discovery.set("--multicast-interface-v6", "3").unwrap();
+        assert_eq!(discovery.group_addr_v6().to_string(), "[ff02::1234%3]:6000");
+
+        assert!(discovery.set("--multicast-group", "192.168.1.10").is_err());
+        assert!(discovery.set("--multicast-group-v6", "fe80::1").is_err());
+        assert!(discovery.set("--multicast-port", "port").is_err());
+    }
 }
@@ -0,0 +1,84 @@
+use crate::network::MAX_AWAY_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Away {
+    // set by the server, clients send 0
+    id: u32,
+    away: bool,
+    // optional, only meaningful while away
+    message: String,
+}
+
+impl Away {
+    pub const ID: u8 = 0x1C;
+
+    pub fn new(id: u32, away: bool, message: String) -> Self {
+        Self { id, away, message }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        let slice_len = slice.len();
+
+        // [id_p0, .., id_p3, away, len, char] => 6
+        if slice_len < 6 {
+            return Err(String::from("Away must be at least 6 byte"));
+        }
+
+        let mut id = [0; 4];
+        id.copy_from_slice(&slice[..4]);
+        let id = u32::from_be_bytes(id);
+
+        let away = match slice[4] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("Away has invalid away flag: {:#04X}", other)),
+        };
+
+        if slice_len != 6 + slice[5] as usize {
+            return Err(String::from("Away has incomplete data"));
+        }
+
+        let message = std::str::from_utf8(
+            &slice[6..]
+        ).map_err(|_| String::from("Away message is not valid UTF-8"))?.to_owned();
+
+        Ok(Self { id, away, message })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        slice.get(5).map(|message_len| 6 + *message_len as usize)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn away(&self) -> bool {
+        self.away
+    }
+
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+
+    pub fn msg_len(&self) -> usize {
+        7 + self.message.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let message_len = self.message.len();
+        if message_len > MAX_AWAY_LEN {
+            return Err(format!("Away message must be at most {} byte, found {}", MAX_AWAY_LEN, message_len));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.id.to_be_bytes());
+        vec.push(self.away as u8);
+        vec.push(message_len as u8);
+        vec.extend(self.message.into_bytes());
+
+        Ok(vec)
+    }
+}
@@ -1,51 +1,88 @@
+use crate::network::{MAX_NAME_LEN, SECRET_LEN};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClientIdentity {
     name: String,
+    // automated participant, shown apart by clients
+    bot: bool,
+    // kept by the client across names and connections, the server only keeps a hash of it
+    secret: [u8; SECRET_LEN],
 }
 
 impl ClientIdentity {
     pub const ID: u8 = 0x04;
 
-    pub fn new(name: String) -> Self {
-        Self { name }
+    pub fn new(name: String, bot: bool, secret: [u8; SECRET_LEN]) -> Self {
+        Self { name, bot, secret }
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
         let slice_len = slice.len();
 
-        // [len, char] => 2
-        if slice_len < 2 {
-            return Err(String::from("PingServerList must be at least 2 byte"));
+        // [len, char, bot, secret_p0, .., secret_p15] => 19
+        if slice_len < 3 + SECRET_LEN {
+            return Err(format!("ClientIdentity must be at least {} byte", 3 + SECRET_LEN));
         }
 
         let name_len = slice[0] as usize;
-        if slice_len != name_len + 1 {
+        if slice_len != name_len + 2 + SECRET_LEN {
             return Err(String::from("PingServerList has incomplete data"));
         }
 
+        if name_len > MAX_NAME_LEN {
+            return Err(format!("ClientIdentity name must be at most {} byte", MAX_NAME_LEN));
+        }
+
         let user = std::str::from_utf8(
             &slice[1..1 + name_len]
-        ).unwrap().to_owned();
+        ).map_err(|_| String::from("ClientIdentity name is not valid UTF-8"))?.to_owned();
+
+        let bot = match slice[1 + name_len] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("ClientIdentity has invalid bot flag: {:#04X}", other)),
+        };
+
+        let mut secret = [0; SECRET_LEN];
+        secret.copy_from_slice(&slice[2 + name_len..]);
+
+        Ok(Self { name: user, bot, secret })
+    }
 
-        Ok(Self { name: user })
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        slice.first().map(|name_len| 2 + *name_len as usize + SECRET_LEN)
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    pub fn bot(&self) -> bool {
+        self.bot
+    }
+
+    pub fn secret(&self) -> &[u8; SECRET_LEN] {
+        &self.secret
+    }
+
     pub fn msg_len(&self) -> usize {
-        self.name.len() + 2
+        self.name.len() + 3 + SECRET_LEN
     }
 
-    pub fn into_vec(self) -> Vec<u8> {
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
         let user_len = self.name.len();
-        let mut vec = Vec::with_capacity(user_len + 2);
+        if user_len > MAX_NAME_LEN {
+            return Err(format!("ClientIdentity name must be at most {} byte, found {}", MAX_NAME_LEN, user_len));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
 
         vec.push(Self::ID);
         vec.push(user_len as u8);
         vec.extend(self.name.into_bytes());
+        vec.push(self.bot as u8);
+        vec.extend_from_slice(&self.secret);
 
-        vec
+        Ok(vec)
     }
 }
@@ -0,0 +1,85 @@
+use crate::file::CHUNK_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChunk {
+    transfer_id: u32,
+    offset: u64,
+    data: Vec<u8>,
+}
+
+impl FileChunk {
+    pub const ID: u8 = 0x41;
+
+    pub fn new(transfer_id: u32, offset: u64, data: Vec<u8>) -> Self {
+        Self { transfer_id, offset, data }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        let slice_len = slice.len();
+
+        // [transfer_id, offset, data_len, data] => 4 + 8 + 2 + 1
+        if slice_len < 15 {
+            return Err(String::from("FileChunk must be at least 15 byte"));
+        }
+
+        let mut transfer_id = [0; 4];
+        transfer_id.copy_from_slice(&slice[..4]);
+        let transfer_id = u32::from_be_bytes(transfer_id);
+
+        let mut offset = [0; 8];
+        offset.copy_from_slice(&slice[4..12]);
+        let offset = u64::from_be_bytes(offset);
+
+        let mut data_len = [0; 2];
+        data_len.copy_from_slice(&slice[12..14]);
+        let data_len = u16::from_be_bytes(data_len) as usize;
+
+        if data_len > CHUNK_LEN {
+            return Err(format!("FileChunk data must be at most {} byte", CHUNK_LEN));
+        }
+
+        if slice_len != 14 + data_len {
+            return Err(String::from("FileChunk has incomplete data"));
+        }
+
+        Ok(Self { transfer_id, offset, data: slice[14..].to_vec() })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let data_len = slice.get(12..14)?;
+        Some(14 + u16::from_be_bytes([data_len[0], data_len[1]]) as usize)
+    }
+
+    pub fn transfer_id(&self) -> u32 {
+        self.transfer_id
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn data(&self) -> &Vec<u8> {
+        &self.data
+    }
+
+    pub fn msg_len(&self) -> usize {
+        15 + self.data.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let data_len = self.data.len();
+        if data_len == 0 || data_len > CHUNK_LEN {
+            return Err(format!("FileChunk data must be between 1 and {} byte, found {}", CHUNK_LEN, data_len));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.transfer_id.to_be_bytes());
+        vec.extend_from_slice(&self.offset.to_be_bytes());
+        vec.extend_from_slice(&(data_len as u16).to_be_bytes());
+        vec.extend(self.data);
+
+        Ok(vec)
+    }
+}
@@ -0,0 +1,57 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileComplete {
+    transfer_id: u32,
+    from: u32,
+}
+
+impl FileComplete {
+    pub const ID: u8 = 0x42;
+
+    pub fn new(transfer_id: u32, from: u32) -> Self {
+        Self { transfer_id, from }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [transfer_id_p0, .., transfer_id_p3, from_p0, .., from_p3] => 8
+        if slice.len() != 8 {
+            return Err(String::from("FileComplete must be 8 byte"));
+        }
+
+        let mut transfer_id = [0; 4];
+        transfer_id.copy_from_slice(&slice[..4]);
+        let transfer_id = u32::from_be_bytes(transfer_id);
+
+        let mut from = [0; 4];
+        from.copy_from_slice(&slice[4..]);
+        let from = u32::from_be_bytes(from);
+
+        Ok(Self { transfer_id, from })
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(8)
+    }
+
+    pub fn transfer_id(&self) -> u32 {
+        self.transfer_id
+    }
+
+    // the user the transfer belongs to
+    pub fn from(&self) -> u32 {
+        self.from
+    }
+
+    pub fn msg_len(&self) -> usize {
+        9
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.transfer_id.to_be_bytes());
+        vec.extend_from_slice(&self.from.to_be_bytes());
+
+        vec
+    }
+}
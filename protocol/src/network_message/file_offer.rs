@@ -0,0 +1,138 @@
+use crate::file::{HASH_LEN, MAX_FILE_SIZE};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileOffer {
+    transfer_id: u32,
+    from: u32,
+    size: u64,
+    hash: [u8; HASH_LEN],
+    name: String,
+    mime: String,
+}
+
+impl FileOffer {
+    pub const ID: u8 = 0x40;
+
+    pub fn new(transfer_id: u32, from: u32, size: u64, hash: [u8; HASH_LEN], name: String, mime: String) -> Self {
+        Self { transfer_id, from, size, hash, name, mime }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        let slice_len = slice.len();
+
+        // [transfer_id, from, size, hash, name_len, name, mime_len, mime] => 4 + 4 + 8 + 32 + 1 + 1 + 1 + 1
+        if slice_len < 52 {
+            return Err(String::from("FileOffer must be at least 52 byte"));
+        }
+
+        let mut transfer_id = [0; 4];
+        transfer_id.copy_from_slice(&slice[..4]);
+        let transfer_id = u32::from_be_bytes(transfer_id);
+
+        let mut from = [0; 4];
+        from.copy_from_slice(&slice[4..8]);
+        let from = u32::from_be_bytes(from);
+
+        let mut size = [0; 8];
+        size.copy_from_slice(&slice[8..16]);
+        let size = u64::from_be_bytes(size);
+
+        if size > MAX_FILE_SIZE {
+            return Err(format!("FileOffer size must be at most {} byte", MAX_FILE_SIZE));
+        }
+
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(&slice[16..16 + HASH_LEN]);
+
+        let mut cursor = 16 + HASH_LEN;
+        let name_len = slice[cursor] as usize;
+        cursor += 1;
+
+        if slice_len < cursor + name_len + 1 {
+            return Err(String::from("FileOffer has incomplete data"));
+        }
+
+        let name = std::str::from_utf8(
+            &slice[cursor..cursor + name_len]
+        ).map_err(|_| String::from("FileOffer name is not valid UTF-8"))?.to_owned();
+        cursor += name_len;
+
+        let mime_len = slice[cursor] as usize;
+        cursor += 1;
+
+        if slice_len != cursor + mime_len {
+            return Err(String::from("FileOffer has incomplete data"));
+        }
+
+        let mime = std::str::from_utf8(
+            &slice[cursor..]
+        ).map_err(|_| String::from("FileOffer mime type is not valid UTF-8"))?.to_owned();
+
+        Ok(Self { transfer_id, from, size, hash, name, mime })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let name_len = *slice.get(16 + HASH_LEN)? as usize;
+        let mime_len = *slice.get(17 + HASH_LEN + name_len)? as usize;
+
+        Some(18 + HASH_LEN + name_len + mime_len)
+    }
+
+    pub fn transfer_id(&self) -> u32 {
+        self.transfer_id
+    }
+
+    pub fn from(&self) -> u32 {
+        self.from
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hash(&self) -> &[u8; HASH_LEN] {
+        &self.hash
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn mime(&self) -> &String {
+        &self.mime
+    }
+
+    pub fn msg_len(&self) -> usize {
+        19 + HASH_LEN + self.name.len() + self.mime.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let name_len = self.name.len();
+        if name_len == 0 || name_len > u8::MAX as usize {
+            return Err(format!("FileOffer name must be between 1 and {} byte, found {}", u8::MAX, name_len));
+        }
+
+        let mime_len = self.mime.len();
+        if mime_len == 0 || mime_len > u8::MAX as usize {
+            return Err(format!("FileOffer mime type must be between 1 and {} byte, found {}", u8::MAX, mime_len));
+        }
+
+        if self.size > MAX_FILE_SIZE {
+            return Err(format!("FileOffer size must be at most {} byte, found {}", MAX_FILE_SIZE, self.size));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.transfer_id.to_be_bytes());
+        vec.extend_from_slice(&self.from.to_be_bytes());
+        vec.extend_from_slice(&self.size.to_be_bytes());
+        vec.extend_from_slice(&self.hash);
+        vec.push(name_len as u8);
+        vec.extend(self.name.into_bytes());
+        vec.push(mime_len as u8);
+        vec.extend(self.mime.into_bytes());
+
+        Ok(vec)
+    }
+}
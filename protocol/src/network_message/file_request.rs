@@ -0,0 +1,57 @@
+use crate::file::HASH_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRequest {
+    transfer_id: u32,
+    hash: [u8; HASH_LEN],
+}
+
+impl FileRequest {
+    pub const ID: u8 = 0x44;
+
+    pub fn new(transfer_id: u32, hash: [u8; HASH_LEN]) -> Self {
+        Self { transfer_id, hash }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [transfer_id_p0, .., transfer_id_p3, hash] => 4 + 32
+        if slice.len() != 4 + HASH_LEN {
+            return Err(format!("FileRequest must be {} byte", 4 + HASH_LEN));
+        }
+
+        let mut transfer_id = [0; 4];
+        transfer_id.copy_from_slice(&slice[..4]);
+        let transfer_id = u32::from_be_bytes(transfer_id);
+
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(&slice[4..]);
+
+        Ok(Self { transfer_id, hash })
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(4 + HASH_LEN)
+    }
+
+    pub fn transfer_id(&self) -> u32 {
+        self.transfer_id
+    }
+
+    pub fn hash(&self) -> &[u8; HASH_LEN] {
+        &self.hash
+    }
+
+    pub fn msg_len(&self) -> usize {
+        5 + HASH_LEN
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.transfer_id.to_be_bytes());
+        vec.extend_from_slice(&self.hash);
+
+        vec
+    }
+}
@@ -0,0 +1,36 @@
+use super::NetworkMessage;
+use crate::network::MAX_FRAME_LEN;
+
+#[derive(Debug, Default)]
+pub struct FrameReader {
+    buf: Vec<u8>,
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    // pop the next complete frame, Ok(None) while more data is needed
+    pub fn next_message(&mut self) -> Result<Option<NetworkMessage>, String> {
+        let frame_len = match NetworkMessage::frame_len(&self.buf)? {
+            Some(len) if len > MAX_FRAME_LEN => {
+                return Err(format!("Frame must be at most {} byte, found {}", MAX_FRAME_LEN, len));
+            }
+            Some(len) if len <= self.buf.len() => len,
+            _ if self.buf.len() > MAX_FRAME_LEN => {
+                return Err(format!("Frame must be at most {} byte", MAX_FRAME_LEN));
+            }
+            _ => return Ok(None),
+        };
+
+        let msg = NetworkMessage::from_slice(&self.buf[..frame_len]);
+        self.buf.drain(..frame_len);
+
+        msg.map(Some)
+    }
+}
@@ -1,41 +1,80 @@
+use crate::network::{MAX_MENTIONS, MAX_MESSAGE_LEN};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Message {
+    // assigned by the server, clients send 0
+    id: u32,
     from: u32,
     content: String,
+    // ids of the users named with an @, resolved by the server
+    mentions: Vec<u32>,
 }
 
 impl Message {
     pub const ID: u8 = 0x20;
 
-    pub fn new(from: u32, content: String) -> Self {
-        Self { from, content }
+    pub fn new(id: u32, from: u32, content: String, mentions: Vec<u32>) -> Self {
+        Self { id, from, content, mentions }
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
         let slice_len = slice.len();
 
-        // [id_p0, id_p1, id_p2, id_p3, msg_len_up, msg_len_down, msg] => 6
-        if slice_len < 7 {
-            return Err(String::from("Message must be at least 7 byte"));
+        // [id_p0, .., id_p3, from_p0, .., from_p3, msg_len_up, msg_len_down, msg, mentions_len, mentions] => 11
+        if slice_len < 11 {
+            return Err(String::from("Message must be at least 11 byte"));
         }
 
+        let mut id = [0; 4];
+        id.copy_from_slice(&slice[..4]);
+        let id = u32::from_be_bytes(id);
+
         let mut from = [0; 4];
-        from.copy_from_slice(&slice[..4]);
+        from.copy_from_slice(&slice[4..8]);
         let from = u32::from_be_bytes(from);
 
         let mut msg_len = [0; 2];
-        msg_len.copy_from_slice(&slice[4..6]);
+        msg_len.copy_from_slice(&slice[8..10]);
         let msg_len = u16::from_be_bytes(msg_len);
 
-        if slice_len != 6 + msg_len as usize {
+        if msg_len as usize > MAX_MESSAGE_LEN {
+            return Err(format!("Message content must be at most {} byte", MAX_MESSAGE_LEN));
+        }
+
+        let cursor = 10 + msg_len as usize;
+        let mentions_len = match slice.get(cursor) {
+            Some(len) => *len as usize,
+            None => return Err(String::from("Message has incomplete data")),
+        };
+
+        if mentions_len > MAX_MENTIONS {
+            return Err(format!("Message must mention at most {} users", MAX_MENTIONS));
+        }
+
+        if slice_len != cursor + 1 + mentions_len * 4 {
             return Err(String::from("Message has incomplete data"));
         }
 
         let content = std::str::from_utf8(
-            &slice[6..]
-        ).unwrap().to_owned();
+            &slice[10..cursor]
+        ).map_err(|_| String::from("Message content is not valid UTF-8"))?.to_owned();
+
+        let mentions = slice[cursor + 1..].chunks_exact(4).map(|id| {
+            u32::from_be_bytes([id[0], id[1], id[2], id[3]])
+        }).collect();
+
+        Ok(Self { id, from, content, mentions })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let msg_len = slice.get(8..10)?;
+        let cursor = 10 + u16::from_be_bytes([msg_len[0], msg_len[1]]) as usize;
 
-        Ok(Self { from, content })
+        Some(cursor + 1 + *slice.get(cursor)? as usize * 4)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
     }
 
     pub fn from(&self) -> u32 {
@@ -46,19 +85,37 @@ impl Message {
         &self.content
     }
 
+    pub fn mentions(&self) -> &Vec<u32> {
+        &self.mentions
+    }
+
     pub fn msg_len(&self) -> usize {
-        6 + self.content.len()
+        12 + self.content.len() + self.mentions.len() * 4
     }
 
-    pub fn into_vec(self) -> Vec<u8> {
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
         let content_len = self.content.len();
-        let mut vec = Vec::with_capacity(content_len + 6);
+        if content_len > MAX_MESSAGE_LEN {
+            return Err(format!("Message content must be at most {} byte, found {}", MAX_MESSAGE_LEN, content_len));
+        }
+
+        if self.mentions.len() > MAX_MENTIONS {
+            return Err(format!("Message must mention at most {} users, found {}", MAX_MENTIONS, self.mentions.len()));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
 
         vec.push(Self::ID);
+        vec.extend_from_slice(&self.id.to_be_bytes());
         vec.extend_from_slice(&self.from.to_be_bytes());
         vec.extend_from_slice(&(content_len as u16).to_be_bytes());
         vec.extend(self.content.into_bytes());
+        vec.push(self.mentions.len() as u8);
+
+        for mention in self.mentions {
+            vec.extend_from_slice(&mention.to_be_bytes());
+        }
 
-        vec
+        Ok(vec)
     }
 }
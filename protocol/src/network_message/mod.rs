@@ -1,16 +1,57 @@
 mod client_identity;
 mod personal_id;
 mod user_list;
+mod user_list_chunk;
 mod user_join;
 mod user_leave;
+mod user_rename;
+mod away;
 mod message;
+mod private_message;
+mod pin;
+mod unpin;
+mod pinned;
+mod read_marker;
+mod rate_limited;
+mod refused;
+mod file_offer;
+mod file_chunk;
+mod file_complete;
+mod file_cancel;
+mod file_request;
+mod server_info;
+mod search_request;
+mod search_result;
+mod search_end;
+mod frame_reader;
 
 use client_identity::ClientIdentity;
 use personal_id::PersonalId;
 use user_list::UserList;
+use user_list_chunk::UserListChunk;
 use user_join::UserJoin;
 use user_leave::UserLeave;
+use user_rename::UserRename;
+use away::Away;
 use message::Message;
+use private_message::PrivateMessage;
+use pin::Pin;
+use unpin::Unpin;
+use pinned::Pinned;
+use read_marker::ReadMarker;
+use rate_limited::RateLimited;
+use refused::Refused;
+use file_offer::FileOffer;
+use file_chunk::FileChunk;
+use file_complete::FileComplete;
+use file_cancel::FileCancel;
+use file_request::FileRequest;
+use server_info::ServerInfo;
+use search_request::SearchRequest;
+use search_result::SearchResult;
+use search_end::SearchEnd;
+
+pub use frame_reader::FrameReader;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NetworkMessage {
@@ -22,36 +63,142 @@ pub enum NetworkMessage {
     ClientIdentity(ClientIdentity),
     PersonalId(PersonalId),
     UserList(UserList),
+    UserListChunk(UserListChunk),
     UserJoin(UserJoin),
     UserLeave(UserLeave),
+    UserRename(UserRename),
+    Away(Away),
     Message(Message),
+    PrivateMessage(PrivateMessage),
+    Pin(Pin),
+    Unpin(Unpin),
+    Pinned(Pinned),
+    ReadMarker(ReadMarker),
+    RateLimited(RateLimited),
+    Refused(Refused),
+    FileOffer(FileOffer),
+    FileChunk(FileChunk),
+    FileComplete(FileComplete),
+    FileCancel(FileCancel),
+    FileRequest(FileRequest),
+    ServerInfo(ServerInfo),
+    SearchRequest(SearchRequest),
+    SearchResult(SearchResult),
+    SearchEnd(SearchEnd),
 }
 
 impl NetworkMessage {
     const IDENTIFIER: u8 = 0x4F;
 
-    pub fn client_identity(name: String) -> Self {
-        Self::ClientIdentity(ClientIdentity::new(name))
+    pub fn client_identity(name: String, bot: bool, secret: [u8; crate::network::SECRET_LEN]) -> Self {
+        Self::ClientIdentity(ClientIdentity::new(name, bot, secret))
     }
 
-    pub fn personal_id(id: u32) -> Self {
-        Self::PersonalId(PersonalId::new(id))
+    pub fn personal_id(id: u32, moderator: bool) -> Self {
+        Self::PersonalId(PersonalId::new(id, moderator))
     }
 
-    pub fn user_list(users: Vec<(u32, String)>) -> Self {
+    pub fn user_list(users: Vec<(u32, String, bool)>) -> Self {
         Self::UserList(UserList::new(users))
     }
 
-    pub fn user_join(name: String, id: u32) -> Self {
-        Self::UserJoin(UserJoin::new(name, id))
+    pub fn user_list_chunk(users: Vec<(u32, String, bool)>, more: bool) -> Self {
+        Self::UserListChunk(UserListChunk::new(users, more))
+    }
+
+    // a single UserList when it fits in one frame, a sequence of UserListChunk otherwise
+    pub fn user_list_frames(users: Vec<(u32, String, bool)>) -> Vec<Self> {
+        let list = UserList::new(users);
+
+        if list.msg_len() < crate::network::MAX_FRAME_LEN {
+            vec![Self::UserList(list)]
+        } else {
+            list.into_chunks().into_iter().map(Self::UserListChunk).collect()
+        }
+    }
+
+    pub fn user_join(name: String, id: u32, bot: bool) -> Self {
+        Self::UserJoin(UserJoin::new(name, id, bot))
     }
 
     pub fn user_leave(id: u32) -> Self {
         Self::UserLeave(UserLeave::new(id))
     }
 
-    pub fn message(from: u32, content: String) -> Self {
-        Self::Message(Message::new(from, content))
+    pub fn user_rename(id: u32, name: String) -> Self {
+        Self::UserRename(UserRename::new(id, name))
+    }
+
+    pub fn away(id: u32, away: bool, message: String) -> Self {
+        Self::Away(Away::new(id, away, message))
+    }
+
+    pub fn message(id: u32, from: u32, content: String, mentions: Vec<u32>) -> Self {
+        Self::Message(Message::new(id, from, content, mentions))
+    }
+
+    pub fn private_message(from: u32, to: u32, content: String) -> Self {
+        Self::PrivateMessage(PrivateMessage::new(from, to, content))
+    }
+
+    pub fn pin(message_id: u32) -> Self {
+        Self::Pin(Pin::new(message_id))
+    }
+
+    pub fn unpin(message_id: u32) -> Self {
+        Self::Unpin(Unpin::new(message_id))
+    }
+
+    pub fn read_marker(user: u32, message_id: u32) -> Self {
+        Self::ReadMarker(ReadMarker::new(user, message_id))
+    }
+
+    pub fn pinned(message_id: u32, from: String, content: String) -> Self {
+        Self::Pinned(Pinned::new(message_id, from, content))
+    }
+
+    pub fn rate_limited(retry_after: u32, muted: bool) -> Self {
+        Self::RateLimited(RateLimited::new(retry_after, muted))
+    }
+
+    pub fn refused(reason: u8) -> Self {
+        Self::Refused(Refused::new(reason))
+    }
+
+    pub fn file_offer(transfer_id: u32, from: u32, size: u64, hash: [u8; crate::file::HASH_LEN], name: String, mime: String) -> Self {
+        Self::FileOffer(FileOffer::new(transfer_id, from, size, hash, name, mime))
+    }
+
+    pub fn file_chunk(transfer_id: u32, offset: u64, data: Vec<u8>) -> Self {
+        Self::FileChunk(FileChunk::new(transfer_id, offset, data))
+    }
+
+    pub fn file_complete(transfer_id: u32, from: u32) -> Self {
+        Self::FileComplete(FileComplete::new(transfer_id, from))
+    }
+
+    pub fn file_cancel(transfer_id: u32, from: u32) -> Self {
+        Self::FileCancel(FileCancel::new(transfer_id, from))
+    }
+
+    pub fn file_request(transfer_id: u32, hash: [u8; crate::file::HASH_LEN]) -> Self {
+        Self::FileRequest(FileRequest::new(transfer_id, hash))
+    }
+
+    pub fn server_info(capabilities: u32, server_id: [u8; crate::multicast::SERVER_ID_LEN], name: String, description: String, motd: String, rules: String) -> Self {
+        Self::ServerInfo(ServerInfo::new(capabilities, server_id, name, description, motd, rules))
+    }
+
+    pub fn search_request(query: String, from_user: String, before: u64, after: u64, limit: u16) -> Self {
+        Self::SearchRequest(SearchRequest::new(query, from_user, before, after, limit))
+    }
+
+    pub fn search_result(message_id: u32, time: u64, from: String, content: String) -> Self {
+        Self::SearchResult(SearchResult::new(message_id, time, from, content))
+    }
+
+    pub fn search_end(total: u32) -> Self {
+        Self::SearchEnd(SearchEnd::new(total))
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
@@ -67,29 +214,141 @@ impl NetworkMessage {
             ClientIdentity::ID => Ok(Self::ClientIdentity(ClientIdentity::from_slice(&slice[2..])?)),
             PersonalId::ID => Ok(Self::PersonalId(PersonalId::from_slice(&slice[2..])?)),
             UserList::ID => Ok(Self::UserList(UserList::from_slice(&slice[2..])?)),
+            UserListChunk::ID => Ok(Self::UserListChunk(UserListChunk::from_slice(&slice[2..])?)),
             UserJoin::ID => Ok(Self::UserJoin(UserJoin::from_slice(&slice[2..])?)),
             UserLeave::ID => Ok(Self::UserLeave(UserLeave::from_slice(&slice[2..])?)),
+            UserRename::ID => Ok(Self::UserRename(UserRename::from_slice(&slice[2..])?)),
+            Away::ID => Ok(Self::Away(Away::from_slice(&slice[2..])?)),
             Message::ID => Ok(Self::Message(Message::from_slice(&slice[2..])?)),
+            PrivateMessage::ID => Ok(Self::PrivateMessage(PrivateMessage::from_slice(&slice[2..])?)),
+            Pin::ID => Ok(Self::Pin(Pin::from_slice(&slice[2..])?)),
+            Unpin::ID => Ok(Self::Unpin(Unpin::from_slice(&slice[2..])?)),
+            Pinned::ID => Ok(Self::Pinned(Pinned::from_slice(&slice[2..])?)),
+            ReadMarker::ID => Ok(Self::ReadMarker(ReadMarker::from_slice(&slice[2..])?)),
+            RateLimited::ID => Ok(Self::RateLimited(RateLimited::from_slice(&slice[2..])?)),
+            Refused::ID => Ok(Self::Refused(Refused::from_slice(&slice[2..])?)),
+            FileOffer::ID => Ok(Self::FileOffer(FileOffer::from_slice(&slice[2..])?)),
+            FileChunk::ID => Ok(Self::FileChunk(FileChunk::from_slice(&slice[2..])?)),
+            FileComplete::ID => Ok(Self::FileComplete(FileComplete::from_slice(&slice[2..])?)),
+            FileCancel::ID => Ok(Self::FileCancel(FileCancel::from_slice(&slice[2..])?)),
+            FileRequest::ID => Ok(Self::FileRequest(FileRequest::from_slice(&slice[2..])?)),
+            ServerInfo::ID => Ok(Self::ServerInfo(ServerInfo::from_slice(&slice[2..])?)),
+            SearchRequest::ID => Ok(Self::SearchRequest(SearchRequest::from_slice(&slice[2..])?)),
+            SearchResult::ID => Ok(Self::SearchResult(SearchResult::from_slice(&slice[2..])?)),
+            SearchEnd::ID => Ok(Self::SearchEnd(SearchEnd::from_slice(&slice[2..])?)),
 
             unknown_id => Err(format!("Unknown identifier: {:#04X}", unknown_id)),
         }
     }
 
-    pub fn into_vec(self) -> Vec<u8> {
+    // length of the first frame in the slice, None while the slice is too short to tell
+    pub fn frame_len(slice: &[u8]) -> Result<Option<usize>, String> {
+        if slice.len() < 2 {
+            return Ok(None);
+        }
+
+        if slice[0] != Self::IDENTIFIER {
+            return Err(String::from("Message must start with 0x4F"));
+        }
+
+        let data = &slice[2..];
+        let len = match slice[1] {
+            ClientIdentity::ID => ClientIdentity::frame_len(data),
+            PersonalId::ID => PersonalId::frame_len(data),
+            UserList::ID => UserList::frame_len(data),
+            UserListChunk::ID => UserListChunk::frame_len(data),
+            UserJoin::ID => UserJoin::frame_len(data),
+            UserLeave::ID => UserLeave::frame_len(data),
+            UserRename::ID => UserRename::frame_len(data),
+            Away::ID => Away::frame_len(data),
+            Message::ID => Message::frame_len(data),
+            PrivateMessage::ID => PrivateMessage::frame_len(data),
+            Pin::ID => Pin::frame_len(data),
+            Unpin::ID => Unpin::frame_len(data),
+            Pinned::ID => Pinned::frame_len(data),
+            ReadMarker::ID => ReadMarker::frame_len(data),
+            RateLimited::ID => RateLimited::frame_len(data),
+            Refused::ID => Refused::frame_len(data),
+            FileOffer::ID => FileOffer::frame_len(data),
+            FileChunk::ID => FileChunk::frame_len(data),
+            FileComplete::ID => FileComplete::frame_len(data),
+            FileCancel::ID => FileCancel::frame_len(data),
+            FileRequest::ID => FileRequest::frame_len(data),
+            ServerInfo::ID => ServerInfo::frame_len(data),
+            SearchRequest::ID => SearchRequest::frame_len(data),
+            SearchResult::ID => SearchResult::frame_len(data),
+            SearchEnd::ID => SearchEnd::frame_len(data),
+
+            unknown_id => return Err(format!("Unknown identifier: {:#04X}", unknown_id)),
+        };
+
+        Ok(len.map(|len| len + 2))
+    }
+
+    pub fn msg_len(&self) -> usize {
+        1 + match self {
+            NetworkMessage::ClientIdentity(ci) => ci.msg_len(),
+            NetworkMessage::PersonalId(pi) => pi.msg_len(),
+            NetworkMessage::UserList(ul) => ul.msg_len(),
+            NetworkMessage::UserListChunk(uc) => uc.msg_len(),
+            NetworkMessage::UserJoin(uj) => uj.msg_len(),
+            NetworkMessage::UserLeave(ul) => ul.msg_len(),
+            NetworkMessage::UserRename(ur) => ur.msg_len(),
+            NetworkMessage::Away(aw) => aw.msg_len(),
+            NetworkMessage::Message(ms) => ms.msg_len(),
+            NetworkMessage::PrivateMessage(pm) => pm.msg_len(),
+            NetworkMessage::Pin(pi) => pi.msg_len(),
+            NetworkMessage::Unpin(up) => up.msg_len(),
+            NetworkMessage::Pinned(pd) => pd.msg_len(),
+            NetworkMessage::ReadMarker(rm) => rm.msg_len(),
+            NetworkMessage::RateLimited(rl) => rl.msg_len(),
+            NetworkMessage::Refused(rf) => rf.msg_len(),
+            NetworkMessage::FileOffer(fo) => fo.msg_len(),
+            NetworkMessage::FileChunk(fc) => fc.msg_len(),
+            NetworkMessage::FileComplete(fc) => fc.msg_len(),
+            NetworkMessage::FileCancel(fc) => fc.msg_len(),
+            NetworkMessage::FileRequest(fr) => fr.msg_len(),
+            NetworkMessage::ServerInfo(si) => si.msg_len(),
+            NetworkMessage::SearchRequest(sr) => sr.msg_len(),
+            NetworkMessage::SearchResult(sr) => sr.msg_len(),
+            NetworkMessage::SearchEnd(se) => se.msg_len(),
+        }
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
         let (msg_len, data) = match self {
-            NetworkMessage::ClientIdentity(ci) => (ci.msg_len(), ci.into_vec()),
+            NetworkMessage::ClientIdentity(ci) => (ci.msg_len(), ci.into_vec()?),
             NetworkMessage::PersonalId(pi) => (pi.msg_len(), pi.into_vec()),
-            NetworkMessage::UserList(ul) => (ul.msg_len(), ul.into_vec()),
-            NetworkMessage::UserJoin(uj) => (uj.msg_len(), uj.into_vec()),
+            NetworkMessage::UserList(ul) => (ul.msg_len(), ul.into_vec()?),
+            NetworkMessage::UserListChunk(uc) => (uc.msg_len(), uc.into_vec()?),
+            NetworkMessage::UserJoin(uj) => (uj.msg_len(), uj.into_vec()?),
             NetworkMessage::UserLeave(ul) => (ul.msg_len(), ul.into_vec()),
-            NetworkMessage::Message(ms) => (ms.msg_len(), ms.into_vec()),
+            NetworkMessage::UserRename(ur) => (ur.msg_len(), ur.into_vec()?),
+            NetworkMessage::Away(aw) => (aw.msg_len(), aw.into_vec()?),
+            NetworkMessage::Message(ms) => (ms.msg_len(), ms.into_vec()?),
+            NetworkMessage::PrivateMessage(pm) => (pm.msg_len(), pm.into_vec()?),
+            NetworkMessage::Pin(pi) => (pi.msg_len(), pi.into_vec()),
+            NetworkMessage::Unpin(up) => (up.msg_len(), up.into_vec()),
+            NetworkMessage::Pinned(pd) => (pd.msg_len(), pd.into_vec()?),
+            NetworkMessage::ReadMarker(rm) => (rm.msg_len(), rm.into_vec()),
+            NetworkMessage::RateLimited(rl) => (rl.msg_len(), rl.into_vec()),
+            NetworkMessage::Refused(rf) => (rf.msg_len(), rf.into_vec()),
+            NetworkMessage::FileOffer(fo) => (fo.msg_len(), fo.into_vec()?),
+            NetworkMessage::FileChunk(fc) => (fc.msg_len(), fc.into_vec()?),
+            NetworkMessage::FileComplete(fc) => (fc.msg_len(), fc.into_vec()),
+            NetworkMessage::FileCancel(fc) => (fc.msg_len(), fc.into_vec()),
+            NetworkMessage::FileRequest(fr) => (fr.msg_len(), fr.into_vec()),
+            NetworkMessage::ServerInfo(si) => (si.msg_len(), si.into_vec()?),
+            NetworkMessage::SearchRequest(sr) => (sr.msg_len(), sr.into_vec()?),
+            NetworkMessage::SearchResult(sr) => (sr.msg_len(), sr.into_vec()?),
+            NetworkMessage::SearchEnd(se) => (se.msg_len(), se.into_vec()),
         };
 
         let mut vec = Vec::with_capacity(msg_len + 1);
         vec.push(Self::IDENTIFIER);
         vec.extend(data);
 
-        vec
+        Ok(vec)
     }
 }
 
@@ -99,9 +358,28 @@ impl std::fmt::Display for NetworkMessage {
             NetworkMessage::ClientIdentity(_) => "ClientIdentity",
             NetworkMessage::PersonalId(_) => "PersonalId",
             NetworkMessage::UserList(_) => "UserList",
+            NetworkMessage::UserListChunk(_) => "UserListChunk",
             NetworkMessage::UserJoin(_) => "UserJoin",
             NetworkMessage::UserLeave(_) => "UserLeave",
+            NetworkMessage::UserRename(_) => "UserRename",
+            NetworkMessage::Away(_) => "Away",
             NetworkMessage::Message(_) => "Message",
+            NetworkMessage::PrivateMessage(_) => "PrivateMessage",
+            NetworkMessage::Pin(_) => "Pin",
+            NetworkMessage::Unpin(_) => "Unpin",
+            NetworkMessage::Pinned(_) => "Pinned",
+            NetworkMessage::ReadMarker(_) => "ReadMarker",
+            NetworkMessage::RateLimited(_) => "RateLimited",
+            NetworkMessage::Refused(_) => "Refused",
+            NetworkMessage::FileOffer(_) => "FileOffer",
+            NetworkMessage::FileChunk(_) => "FileChunk",
+            NetworkMessage::FileComplete(_) => "FileComplete",
+            NetworkMessage::FileCancel(_) => "FileCancel",
+            NetworkMessage::FileRequest(_) => "FileRequest",
+            NetworkMessage::ServerInfo(_) => "ServerInfo",
+            NetworkMessage::SearchRequest(_) => "SearchRequest",
+            NetworkMessage::SearchResult(_) => "SearchResult",
+            NetworkMessage::SearchEnd(_) => "SearchEnd",
         })
     }
 }
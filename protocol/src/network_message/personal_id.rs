@@ -1,34 +1,50 @@
 #[derive(Debug, Clone, PartialEq)]
 pub struct PersonalId {
     id: u32,
+    // allowed to pin and unpin messages
+    moderator: bool,
 }
 
 impl PersonalId {
     pub const ID: u8 = 0x1F;
 
-    pub fn new(id: u32) -> Self {
-        Self { id }
+    pub fn new(id: u32, moderator: bool) -> Self {
+        Self { id, moderator }
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
-        // [id_p0, id_p1, id_p2, id_p3] => 4
-        if slice.len() != 4 {
-            return Err(String::from("PersonalId must be 4 byte"));
+        // [id_p0, id_p1, id_p2, id_p3, moderator] => 5
+        if slice.len() != 5 {
+            return Err(String::from("PersonalId must be 5 byte"));
         }
 
         let mut id = [0; 4];
-        id.copy_from_slice(slice);
+        id.copy_from_slice(&slice[..4]);
         let id = u32::from_be_bytes(id);
 
-        Ok(Self { id })
+        let moderator = match slice[4] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("PersonalId has invalid moderator flag: {:#04X}", other)),
+        };
+
+        Ok(Self { id, moderator })
     }
 
     pub fn id(&self) -> u32 {
         self.id
     }
 
+    pub fn moderator(&self) -> bool {
+        self.moderator
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(5)
+    }
+
     pub fn msg_len(&self) -> usize {
-        5
+        6
     }
 
     pub fn into_vec(self) -> Vec<u8> {
@@ -36,6 +52,7 @@ impl PersonalId {
 
         vec.push(Self::ID);
         vec.extend_from_slice(&self.id.to_be_bytes());
+        vec.push(self.moderator as u8);
 
         vec
     }
@@ -0,0 +1,46 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pin {
+    message_id: u32,
+}
+
+impl Pin {
+    pub const ID: u8 = 0x22;
+
+    pub fn new(message_id: u32) -> Self {
+        Self { message_id }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [message_id_p0, .., message_id_p3] => 4
+        if slice.len() != 4 {
+            return Err(String::from("Pin must be 4 byte"));
+        }
+
+        let mut message_id = [0; 4];
+        message_id.copy_from_slice(slice);
+        let message_id = u32::from_be_bytes(message_id);
+
+        Ok(Self { message_id })
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(4)
+    }
+
+    pub fn message_id(&self) -> u32 {
+        self.message_id
+    }
+
+    pub fn msg_len(&self) -> usize {
+        5
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.message_id.to_be_bytes());
+
+        vec
+    }
+}
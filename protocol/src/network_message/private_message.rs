@@ -0,0 +1,91 @@
+use crate::network::MAX_MESSAGE_LEN;
+
+// only seen by its recipient and sender, never kept in the history
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateMessage {
+    // set by the server, whatever the client sends
+    from: u32,
+    to: u32,
+    content: String,
+}
+
+impl PrivateMessage {
+    pub const ID: u8 = 0x21;
+
+    pub fn new(from: u32, to: u32, content: String) -> Self {
+        Self { from, to, content }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        let slice_len = slice.len();
+
+        // [from_p0, .., from_p3, to_p0, .., to_p3, msg_len_up, msg_len_down, msg] => 11
+        if slice_len < 11 {
+            return Err(String::from("PrivateMessage must be at least 11 byte"));
+        }
+
+        let mut from = [0; 4];
+        from.copy_from_slice(&slice[..4]);
+        let from = u32::from_be_bytes(from);
+
+        let mut to = [0; 4];
+        to.copy_from_slice(&slice[4..8]);
+        let to = u32::from_be_bytes(to);
+
+        let mut msg_len = [0; 2];
+        msg_len.copy_from_slice(&slice[8..10]);
+        let msg_len = u16::from_be_bytes(msg_len);
+
+        if msg_len as usize > MAX_MESSAGE_LEN {
+            return Err(format!("PrivateMessage content must be at most {} byte", MAX_MESSAGE_LEN));
+        }
+
+        if slice_len != 10 + msg_len as usize {
+            return Err(String::from("PrivateMessage has incomplete data"));
+        }
+
+        let content = std::str::from_utf8(
+            &slice[10..]
+        ).map_err(|_| String::from("PrivateMessage content is not valid UTF-8"))?.to_owned();
+
+        Ok(Self { from, to, content })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let msg_len = slice.get(8..10)?;
+        Some(10 + u16::from_be_bytes([msg_len[0], msg_len[1]]) as usize)
+    }
+
+    pub fn from(&self) -> u32 {
+        self.from
+    }
+
+    pub fn to(&self) -> u32 {
+        self.to
+    }
+
+    pub fn content(&self) -> &String {
+        &self.content
+    }
+
+    pub fn msg_len(&self) -> usize {
+        11 + self.content.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let content_len = self.content.len();
+        if content_len > MAX_MESSAGE_LEN {
+            return Err(format!("PrivateMessage content must be at most {} byte, found {}", MAX_MESSAGE_LEN, content_len));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.from.to_be_bytes());
+        vec.extend_from_slice(&self.to.to_be_bytes());
+        vec.extend_from_slice(&(content_len as u16).to_be_bytes());
+        vec.extend(self.content.into_bytes());
+
+        Ok(vec)
+    }
+}
@@ -0,0 +1,59 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimited {
+    retry_after: u32,
+    muted: bool,
+}
+
+impl RateLimited {
+    pub const ID: u8 = 0x30;
+
+    pub fn new(retry_after: u32, muted: bool) -> Self {
+        Self { retry_after, muted }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [ms_p0, ms_p1, ms_p2, ms_p3, muted] => 5
+        if slice.len() != 5 {
+            return Err(String::from("RateLimited must be 5 byte"));
+        }
+
+        let mut retry_after = [0; 4];
+        retry_after.copy_from_slice(&slice[..4]);
+        let retry_after = u32::from_be_bytes(retry_after);
+
+        let muted = match slice[4] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("RateLimited has invalid muted flag: {:#04X}", other)),
+        };
+
+        Ok(Self { retry_after, muted })
+    }
+
+    // in milliseconds
+    pub fn retry_after(&self) -> u32 {
+        self.retry_after
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(5)
+    }
+
+    pub fn msg_len(&self) -> usize {
+        6
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.retry_after.to_be_bytes());
+        vec.push(self.muted as u8);
+
+        vec
+    }
+}
@@ -0,0 +1,57 @@
+// the last message a user has read, clients send 0 as their id
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadMarker {
+    user: u32,
+    message_id: u32,
+}
+
+impl ReadMarker {
+    pub const ID: u8 = 0x25;
+
+    pub fn new(user: u32, message_id: u32) -> Self {
+        Self { user, message_id }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [user_p0, .., user_p3, message_id_p0, .., message_id_p3] => 8
+        if slice.len() != 8 {
+            return Err(String::from("ReadMarker must be 8 byte"));
+        }
+
+        let mut user = [0; 4];
+        user.copy_from_slice(&slice[..4]);
+        let user = u32::from_be_bytes(user);
+
+        let mut message_id = [0; 4];
+        message_id.copy_from_slice(&slice[4..]);
+        let message_id = u32::from_be_bytes(message_id);
+
+        Ok(Self { user, message_id })
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(8)
+    }
+
+    pub fn user(&self) -> u32 {
+        self.user
+    }
+
+    pub fn message_id(&self) -> u32 {
+        self.message_id
+    }
+
+    pub fn msg_len(&self) -> usize {
+        9
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.user.to_be_bytes());
+        vec.extend_from_slice(&self.message_id.to_be_bytes());
+
+        vec
+    }
+}
@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refused {
+    reason: u8,
+}
+
+impl Refused {
+    pub const ID: u8 = 0x31;
+
+    pub fn new(reason: u8) -> Self {
+        Self { reason }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [reason] => 1
+        if slice.len() != 1 {
+            return Err(String::from("Refused must be 1 byte"));
+        }
+
+        Ok(Self { reason: slice[0] })
+    }
+
+    // one of network::refusal, newer servers may send others
+    pub fn reason(&self) -> u8 {
+        self.reason
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(1)
+    }
+
+    pub fn msg_len(&self) -> usize {
+        2
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        vec![Self::ID, self.reason]
+    }
+}
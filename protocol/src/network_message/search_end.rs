@@ -0,0 +1,47 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchEnd {
+    // every match, even past the requested limit
+    total: u32,
+}
+
+impl SearchEnd {
+    pub const ID: u8 = 0x62;
+
+    pub fn new(total: u32) -> Self {
+        Self { total }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [total_p0, .., total_p3] => 4
+        if slice.len() != 4 {
+            return Err(String::from("SearchEnd must be 4 byte"));
+        }
+
+        let mut total = [0; 4];
+        total.copy_from_slice(slice);
+        let total = u32::from_be_bytes(total);
+
+        Ok(Self { total })
+    }
+
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(4)
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn msg_len(&self) -> usize {
+        5
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.total.to_be_bytes());
+
+        vec
+    }
+}
@@ -0,0 +1,117 @@
+use crate::network::{MAX_NAME_LEN, MAX_QUERY_LEN};
+
+// words to find, all of them must appear, narrowed by author and time
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchRequest {
+    query: String,
+    // empty is anyone
+    from_user: String,
+    // unix seconds, 0 is unbounded
+    before: u64,
+    after: u64,
+    limit: u16,
+}
+
+impl SearchRequest {
+    pub const ID: u8 = 0x60;
+
+    pub fn new(query: String, from_user: String, before: u64, after: u64, limit: u16) -> Self {
+        Self { query, from_user, before, after, limit }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [query_len, query, from_len, from, before_p0, .., before_p7, after_p0, .., after_p7, limit_up, limit_down] => 1 + 1 + 8 + 8 + 2
+        if slice.len() < 20 {
+            return Err(String::from("SearchRequest must be at least 20 byte"));
+        }
+
+        let query_len = slice[0] as usize;
+        if query_len > MAX_QUERY_LEN {
+            return Err(format!("SearchRequest query must be at most {} byte", MAX_QUERY_LEN));
+        }
+
+        let query = slice.get(1..1 + query_len).ok_or_else(|| String::from("SearchRequest has incomplete data"))?;
+        let query = std::str::from_utf8(query).map_err(|_| String::from("SearchRequest query is not valid UTF-8"))?.to_owned();
+
+        let cursor = 1 + query_len;
+        let from_len = *slice.get(cursor).ok_or_else(|| String::from("SearchRequest has incomplete data"))? as usize;
+        if from_len > MAX_NAME_LEN {
+            return Err(format!("SearchRequest name must be at most {} byte", MAX_NAME_LEN));
+        }
+
+        if slice.len() != cursor + 1 + from_len + 18 {
+            return Err(String::from("SearchRequest has incomplete data"));
+        }
+
+        let from_user = std::str::from_utf8(&slice[cursor + 1..cursor + 1 + from_len])
+            .map_err(|_| String::from("SearchRequest name is not valid UTF-8"))?
+            .to_owned();
+
+        let cursor = cursor + 1 + from_len;
+        let mut before = [0; 8];
+        before.copy_from_slice(&slice[cursor..cursor + 8]);
+        let before = u64::from_be_bytes(before);
+
+        let mut after = [0; 8];
+        after.copy_from_slice(&slice[cursor + 8..cursor + 16]);
+        let after = u64::from_be_bytes(after);
+
+        let limit = u16::from_be_bytes([slice[cursor + 16], slice[cursor + 17]]);
+
+        Ok(Self { query, from_user, before, after, limit })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let query_len = *slice.first()? as usize;
+        let from_len = *slice.get(1 + query_len)? as usize;
+
+        Some(2 + query_len + from_len + 18)
+    }
+
+    pub fn query(&self) -> &String {
+        &self.query
+    }
+
+    pub fn user(&self) -> &String {
+        &self.from_user
+    }
+
+    pub fn before(&self) -> u64 {
+        self.before
+    }
+
+    pub fn after(&self) -> u64 {
+        self.after
+    }
+
+    pub fn limit(&self) -> u16 {
+        self.limit
+    }
+
+    pub fn msg_len(&self) -> usize {
+        21 + self.query.len() + self.from_user.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        if self.query.len() > MAX_QUERY_LEN {
+            return Err(format!("SearchRequest query must be at most {} byte, found {}", MAX_QUERY_LEN, self.query.len()));
+        }
+
+        if self.from_user.len() > MAX_NAME_LEN {
+            return Err(format!("SearchRequest name must be at most {} byte, found {}", MAX_NAME_LEN, self.from_user.len()));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.push(self.query.len() as u8);
+        vec.extend(self.query.into_bytes());
+        vec.push(self.from_user.len() as u8);
+        vec.extend(self.from_user.into_bytes());
+        vec.extend_from_slice(&self.before.to_be_bytes());
+        vec.extend_from_slice(&self.after.to_be_bytes());
+        vec.extend_from_slice(&self.limit.to_be_bytes());
+
+        Ok(vec)
+    }
+}
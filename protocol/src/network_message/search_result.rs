@@ -0,0 +1,107 @@
+use crate::network::{MAX_MESSAGE_LEN, MAX_NAME_LEN};
+
+// one match of a search, newest first, followed by a SearchEnd
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    message_id: u32,
+    // unix seconds
+    time: u64,
+    from: String,
+    content: String,
+}
+
+impl SearchResult {
+    pub const ID: u8 = 0x61;
+
+    pub fn new(message_id: u32, time: u64, from: String, content: String) -> Self {
+        Self { message_id, time, from, content }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [message_id_p0, .., message_id_p3, time_p0, .., time_p7, from_len, from, content_len_up, content_len_down, content] => 4 + 8 + 1 + 2
+        if slice.len() < 15 {
+            return Err(String::from("SearchResult must be at least 15 byte"));
+        }
+
+        let mut message_id = [0; 4];
+        message_id.copy_from_slice(&slice[..4]);
+        let message_id = u32::from_be_bytes(message_id);
+
+        let mut time = [0; 8];
+        time.copy_from_slice(&slice[4..12]);
+        let time = u64::from_be_bytes(time);
+
+        let from_len = slice[12] as usize;
+        let from = slice.get(13..13 + from_len).ok_or_else(|| String::from("SearchResult has incomplete data"))?;
+        let from = std::str::from_utf8(from).map_err(|_| String::from("SearchResult name is not valid UTF-8"))?.to_owned();
+
+        let cursor = 13 + from_len;
+        let content_len = match slice.get(cursor..cursor + 2) {
+            Some(len) => u16::from_be_bytes([len[0], len[1]]) as usize,
+            None => return Err(String::from("SearchResult has incomplete data")),
+        };
+
+        if content_len > MAX_MESSAGE_LEN {
+            return Err(format!("SearchResult content must be at most {} byte", MAX_MESSAGE_LEN));
+        }
+
+        if slice.len() != cursor + 2 + content_len {
+            return Err(String::from("SearchResult has incomplete data"));
+        }
+
+        let content = std::str::from_utf8(&slice[cursor + 2..])
+            .map_err(|_| String::from("SearchResult content is not valid UTF-8"))?
+            .to_owned();
+
+        Ok(Self { message_id, time, from, content })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let cursor = 13 + *slice.get(12)? as usize;
+        let content_len = slice.get(cursor..cursor + 2)?;
+
+        Some(cursor + 2 + u16::from_be_bytes([content_len[0], content_len[1]]) as usize)
+    }
+
+    pub fn message_id(&self) -> u32 {
+        self.message_id
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn from(&self) -> &String {
+        &self.from
+    }
+
+    pub fn content(&self) -> &String {
+        &self.content
+    }
+
+    pub fn msg_len(&self) -> usize {
+        16 + self.from.len() + self.content.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        if self.from.len() > MAX_NAME_LEN {
+            return Err(format!("SearchResult name must be at most {} byte, found {}", MAX_NAME_LEN, self.from.len()));
+        }
+
+        if self.content.len() > MAX_MESSAGE_LEN {
+            return Err(format!("SearchResult content must be at most {} byte, found {}", MAX_MESSAGE_LEN, self.content.len()));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.message_id.to_be_bytes());
+        vec.extend_from_slice(&self.time.to_be_bytes());
+        vec.push(self.from.len() as u8);
+        vec.extend(self.from.into_bytes());
+        vec.extend_from_slice(&(self.content.len() as u16).to_be_bytes());
+        vec.extend(self.content.into_bytes());
+
+        Ok(vec)
+    }
+}
@@ -0,0 +1,139 @@
+use crate::multicast::SERVER_ID_LEN;
+use crate::network::MAX_FRAME_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerInfo {
+    // bits from crate::network::capabilities
+    capabilities: u32,
+    // the same id the server announces, stays put when the server moves
+    server_id: [u8; SERVER_ID_LEN],
+    name: String,
+    description: String,
+    motd: String,
+    rules: String,
+}
+
+impl ServerInfo {
+    pub const ID: u8 = 0x50;
+
+    pub fn new(capabilities: u32, server_id: [u8; SERVER_ID_LEN], name: String, description: String, motd: String, rules: String) -> Self {
+        Self { capabilities, server_id, name, description, motd, rules }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [capabilities, server_id, name_len, name, description_len, description, motd_len_up, motd_len_down, motd, rules_len_up, rules_len_down, rules] => 4 + 16 + 1 + 1 + 2 + 2
+        if slice.len() < 26 {
+            return Err(String::from("ServerInfo must be at least 26 byte"));
+        }
+
+        let mut capabilities = [0; 4];
+        capabilities.copy_from_slice(&slice[..4]);
+        let capabilities = u32::from_be_bytes(capabilities);
+
+        let mut server_id = [0; SERVER_ID_LEN];
+        server_id.copy_from_slice(&slice[4..4 + SERVER_ID_LEN]);
+
+        let mut cursor = 4 + SERVER_ID_LEN;
+        let name = read_field(slice, &mut cursor, 1)?;
+        let description = read_field(slice, &mut cursor, 1)?;
+        let motd = read_field(slice, &mut cursor, 2)?;
+        let rules = read_field(slice, &mut cursor, 2)?;
+
+        if cursor != slice.len() {
+            return Err(String::from("ServerInfo has trailing data"));
+        }
+
+        Ok(Self { capabilities, server_id, name, description, motd, rules })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        let cursor = 4 + SERVER_ID_LEN;
+        let name_len = *slice.get(cursor)? as usize;
+        let description_len = *slice.get(cursor + 1 + name_len)? as usize;
+
+        let cursor = cursor + 2 + name_len + description_len;
+        let motd_len = u16::from_be_bytes([*slice.get(cursor)?, *slice.get(cursor + 1)?]) as usize;
+
+        let cursor = cursor + 2 + motd_len;
+        let rules_len = u16::from_be_bytes([*slice.get(cursor)?, *slice.get(cursor + 1)?]) as usize;
+
+        Some(cursor + 2 + rules_len)
+    }
+
+    pub fn capabilities(&self) -> u32 {
+        self.capabilities
+    }
+
+    pub fn has(&self, capability: u32) -> bool {
+        self.capabilities & capability == capability
+    }
+
+    pub fn server_id(&self) -> &[u8; SERVER_ID_LEN] {
+        &self.server_id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn description(&self) -> &String {
+        &self.description
+    }
+
+    pub fn motd(&self) -> &String {
+        &self.motd
+    }
+
+    pub fn rules(&self) -> &String {
+        &self.rules
+    }
+
+    pub fn msg_len(&self) -> usize {
+        11 + SERVER_ID_LEN + self.name.len() + self.description.len() + self.motd.len() + self.rules.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        if self.name.len() > u8::MAX as usize || self.description.len() > u8::MAX as usize {
+            return Err(format!("ServerInfo name and description must be at most {} byte", u8::MAX));
+        }
+
+        // motd and rules share what is left of the frame
+        let msg_len = self.msg_len();
+        if msg_len + 1 > MAX_FRAME_LEN {
+            return Err(format!("ServerInfo must be at most {} byte, found {}", MAX_FRAME_LEN, msg_len + 1));
+        }
+
+        let mut vec = Vec::with_capacity(msg_len);
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.capabilities.to_be_bytes());
+        vec.extend_from_slice(&self.server_id);
+        vec.push(self.name.len() as u8);
+        vec.extend(self.name.into_bytes());
+        vec.push(self.description.len() as u8);
+        vec.extend(self.description.into_bytes());
+        vec.extend_from_slice(&(self.motd.len() as u16).to_be_bytes());
+        vec.extend(self.motd.into_bytes());
+        vec.extend_from_slice(&(self.rules.len() as u16).to_be_bytes());
+        vec.extend(self.rules.into_bytes());
+
+        Ok(vec)
+    }
+}
+
+// a string preceded by its length on `len_size` byte
+fn read_field(slice: &[u8], cursor: &mut usize, len_size: usize) -> Result<String, String> {
+    let len = match slice.get(*cursor..*cursor + len_size) {
+        Some([len]) => *len as usize,
+        Some([up, down]) => u16::from_be_bytes([*up, *down]) as usize,
+        _ => return Err(String::from("ServerInfo has incomplete data")),
+    };
+
+    let field = slice.get(*cursor + len_size..*cursor + len_size + len)
+        .ok_or_else(|| String::from("ServerInfo has incomplete data"))?;
+    *cursor += len_size + len;
+
+    std::str::from_utf8(field)
+        .map(str::to_owned)
+        .map_err(|_| String::from("ServerInfo is not valid UTF-8"))
+}
@@ -1,38 +1,55 @@
+use crate::network::MAX_NAME_LEN;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UserJoin {
     name: String,
     id: u32,
+    bot: bool,
 }
 
 impl UserJoin {
     pub const ID: u8 = 0x16;
 
-    pub fn new(name: String, id: u32) -> Self {
-        Self { name, id }
+    pub fn new(name: String, id: u32, bot: bool) -> Self {
+        Self { name, id, bot }
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
         let slice_len = slice.len();
 
-        // [len, char, id_p0, id_p1, id_p2, id_p3] => 6
-        if slice_len < 6 {
-            return Err(String::from("UserJoin must be at least 6 byte"));
+        // [len, char, id_p0, id_p1, id_p2, id_p3, bot] => 7
+        if slice_len < 7 {
+            return Err(String::from("UserJoin must be at least 7 byte"));
         }
 
         let name_len = slice[0] as usize;
-        if slice_len != name_len + 5 {
+        if slice_len != name_len + 6 {
             return Err(String::from("ServerList has incomplete data"));
         }
 
+        if name_len > MAX_NAME_LEN {
+            return Err(format!("UserJoin name must be at most {} byte", MAX_NAME_LEN));
+        }
+
         let name = std::str::from_utf8(
             &slice[1..1 + name_len]
-        ).unwrap().to_owned();
+        ).map_err(|_| String::from("UserJoin name is not valid UTF-8"))?.to_owned();
 
         let mut id = [0; 4];
-        id.copy_from_slice(&slice[1 + name_len..]);
+        id.copy_from_slice(&slice[1 + name_len..5 + name_len]);
         let id = u32::from_be_bytes(id);
 
-        Ok(Self { name, id })
+        let bot = match slice[5 + name_len] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("UserJoin has invalid bot flag: {:#04X}", other)),
+        };
+
+        Ok(Self { name, id, bot })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        slice.first().map(|name_len| 6 + *name_len as usize)
     }
 
     pub fn name(&self) -> &String {
@@ -43,19 +60,28 @@ impl UserJoin {
         self.id
     }
 
+    pub fn bot(&self) -> bool {
+        self.bot
+    }
+
     pub fn msg_len(&self) -> usize {
-        self.name.len() + 6
+        self.name.len() + 7
     }
 
-    pub fn into_vec(self) -> Vec<u8> {
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
         let name_len = self.name.len();
-        let mut vec = Vec::with_capacity(name_len + 6);
+        if name_len > MAX_NAME_LEN {
+            return Err(format!("UserJoin name must be at most {} byte, found {}", MAX_NAME_LEN, name_len));
+        }
+
+        let mut vec = Vec::with_capacity(name_len + 7);
 
         vec.push(Self::ID);
         vec.push(name_len as u8);
         vec.extend(self.name.into_bytes());
         vec.extend_from_slice(&self.id.to_be_bytes());
+        vec.push(self.bot as u8);
 
-        vec
+        Ok(vec)
     }
 }
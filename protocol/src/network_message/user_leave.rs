@@ -27,6 +27,10 @@ impl UserLeave {
         self.id
     }
 
+    pub fn frame_len(_slice: &[u8]) -> Option<usize> {
+        Some(4)
+    }
+
     pub fn msg_len(&self) -> usize {
         5
     }
@@ -1,79 +1,165 @@
+use crate::network::{MAX_FRAME_LEN, MAX_NAME_LEN, MAX_USER_LIST_LEN};
+use super::user_list_chunk::UserListChunk;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UserList {
-    users: Vec<(u32, String)>,
+    users: Vec<(u32, String, bool)>,
 }
 
 impl UserList {
     pub const ID: u8 = 0x10;
 
-    pub fn new(users: Vec<(u32, String)>) -> Self {
+    pub fn new(users: Vec<(u32, String, bool)>) -> Self {
         Self { users }
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
-        let slice_len = slice.len();
+        Ok(Self { users: users_from_slice(slice)? })
+    }
 
-        // [list_len] => 1
-        if slice_len < 1 {
-            return Err(String::from("UserList must be at least 1 byte"));
-        }
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        users_frame_len(slice)
+    }
 
-        let list_len = slice[0] as usize;
+    pub fn users(&self) -> &Vec<(u32, String, bool)> {
+        &self.users
+    }
 
-        let mut users = Vec::with_capacity(list_len);
-        let mut cursor = 1;
+    pub fn msg_len(&self) -> usize {
+        3 + users_len(&self.users)
+    }
 
-        for _ in 0..list_len {
-            if slice_len < cursor + 5 {
-                return Err(String::from("UserList has incomplet data"));
-            }
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let mut vec = Vec::with_capacity(self.msg_len());
 
-            let mut id = [0; 4];
-            id.copy_from_slice(&slice[cursor..cursor + 4]);
-            let id = u32::from_be_bytes(id);
-            cursor += 4;
+        vec.push(Self::ID);
+        users_into_vec(self.users, vec)
+    }
 
-            let name_len = slice[cursor] as usize;
-            cursor += 1;
+    // cut the list so that each chunk fits in a single frame
+    pub fn into_chunks(self) -> Vec<UserListChunk> {
+        // [0x4F, id, more, list_len_up, list_len_down] => 5
+        let max_len = MAX_FRAME_LEN - 5;
+        let mut chunks = vec![];
+        let mut users = Vec::new();
+        let mut len = 0;
 
-            if slice_len < cursor + name_len {
-                return Err(String::from("UserList has incomplet data"));
-            }
+        for (id, user, bot) in self.users {
+            let user_len = user.len() + 6;
 
-            let name = std::str::from_utf8(
-                &slice[cursor..cursor + name_len]
-            ).unwrap().to_owned();
+            if len + user_len > max_len && !users.is_empty() {
+                chunks.push(UserListChunk::new(std::mem::take(&mut users), true));
+                len = 0;
+            }
 
-            cursor += name_len;
-            users.push((id, name));
+            len += user_len;
+            users.push((id, user, bot));
         }
 
-        Ok(Self { users })
+        chunks.push(UserListChunk::new(users, false));
+        chunks
     }
+}
 
-    pub fn users(&self) -> &Vec<(u32, String)> {
-        &self.users
+pub(super) fn users_len(users: &[(u32, String, bool)]) -> usize {
+    users.iter().fold(0, |acc, (_, user, _)| {
+        acc + user.len() + 6
+    })
+}
+
+pub(super) fn users_frame_len(slice: &[u8]) -> Option<usize> {
+    // [list_len_up, list_len_down] => 2
+    if slice.len() < 2 {
+        return None;
     }
 
-    pub fn msg_len(&self) -> usize {
-        2 + self.users.iter().fold(0, |acc, (_ ,user)| {
-            acc + user.len() + 5
-        })
+    let mut list_len = [0; 2];
+    list_len.copy_from_slice(&slice[..2]);
+    let list_len = u16::from_be_bytes(list_len);
+
+    let mut cursor = 2;
+    for _ in 0..list_len {
+        // [id_p0, id_p1, id_p2, id_p3, name_len, name, bot] => 6 + name_len
+        let name_len = *slice.get(cursor + 4)? as usize;
+        cursor += 6 + name_len;
     }
 
-    pub fn into_vec(self) -> Vec<u8> {
-        let users_len = self.users.len();
-        let mut vec = Vec::with_capacity(self.msg_len());
+    Some(cursor)
+}
 
-        vec.push(Self::ID);
-        vec.push(users_len as u8);
-        self.users.into_iter().fold(vec, |mut vec, (id, user)| {
-            vec.extend_from_slice(&id.to_be_bytes());
+pub(super) fn users_from_slice(slice: &[u8]) -> Result<Vec<(u32, String, bool)>, String> {
+    let slice_len = slice.len();
 
-            vec.push(user.len() as u8);
-            vec.extend(user.into_bytes());
+    // [list_len_up, list_len_down] => 2
+    if slice_len < 2 {
+        return Err(String::from("UserList must be at least 2 byte"));
+    }
+
+    let mut list_len = [0; 2];
+    list_len.copy_from_slice(&slice[..2]);
+    let list_len = u16::from_be_bytes(list_len) as usize;
+
+    let mut users = Vec::with_capacity(list_len);
+    let mut cursor = 2;
+
+    for _ in 0..list_len {
+        if slice_len < cursor + 5 {
+            return Err(String::from("UserList has incomplet data"));
+        }
+
+        let mut id = [0; 4];
+        id.copy_from_slice(&slice[cursor..cursor + 4]);
+        let id = u32::from_be_bytes(id);
+        cursor += 4;
+
+        let name_len = slice[cursor] as usize;
+        cursor += 1;
+
+        if name_len > MAX_NAME_LEN {
+            return Err(format!("UserList name must be at most {} byte", MAX_NAME_LEN));
+        }
+
+        if slice_len < cursor + name_len + 1 {
+            return Err(String::from("UserList has incomplet data"));
+        }
+
+        let name = std::str::from_utf8(
+            &slice[cursor..cursor + name_len]
+        ).map_err(|_| String::from("UserList name is not valid UTF-8"))?.to_owned();
 
-            vec
-        })
+        cursor += name_len;
+
+        let bot = match slice[cursor] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("UserList has invalid bot flag: {:#04X}", other)),
+        };
+
+        cursor += 1;
+        users.push((id, name, bot));
+    }
+
+    Ok(users)
+}
+
+pub(super) fn users_into_vec(users: Vec<(u32, String, bool)>, mut vec: Vec<u8>) -> Result<Vec<u8>, String> {
+    let users_len = users.len();
+    if users_len > MAX_USER_LIST_LEN {
+        return Err(format!("UserList must hold at most {} users, found {}", MAX_USER_LIST_LEN, users_len));
     }
+
+    if let Some((_, user, _)) = users.iter().find(|(_, user, _)| user.len() > MAX_NAME_LEN) {
+        return Err(format!("UserList name must be at most {} byte, found {}", MAX_NAME_LEN, user.len()));
+    }
+
+    vec.extend_from_slice(&(users_len as u16).to_be_bytes());
+    Ok(users.into_iter().fold(vec, |mut vec, (id, user, bot)| {
+        vec.extend_from_slice(&id.to_be_bytes());
+
+        vec.push(user.len() as u8);
+        vec.extend(user.into_bytes());
+        vec.push(bot as u8);
+
+        vec
+    }))
 }
@@ -0,0 +1,55 @@
+use super::user_list::{users_frame_len, users_from_slice, users_into_vec, users_len};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserListChunk {
+    users: Vec<(u32, String, bool)>,
+    more: bool,
+}
+
+impl UserListChunk {
+    pub const ID: u8 = 0x11;
+
+    pub fn new(users: Vec<(u32, String, bool)>, more: bool) -> Self {
+        Self { users, more }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        // [more, list_len_up, list_len_down] => 3
+        if slice.len() < 3 {
+            return Err(String::from("UserListChunk must be at least 3 byte"));
+        }
+
+        let more = match slice[0] {
+            0x00 => false,
+            0x01 => true,
+            other => return Err(format!("UserListChunk has invalid continuation flag: {:#04X}", other)),
+        };
+
+        Ok(Self { users: users_from_slice(&slice[1..])?, more })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        Some(1 + users_frame_len(slice.get(1..)?)?)
+    }
+
+    pub fn users(&self) -> &Vec<(u32, String, bool)> {
+        &self.users
+    }
+
+    // true when other chunks follow this one
+    pub fn more(&self) -> bool {
+        self.more
+    }
+
+    pub fn msg_len(&self) -> usize {
+        4 + users_len(&self.users)
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.push(self.more as u8);
+        users_into_vec(self.users, vec)
+    }
+}
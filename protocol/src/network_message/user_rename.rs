@@ -0,0 +1,76 @@
+use crate::network::MAX_NAME_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRename {
+    // set by the server, clients send 0
+    id: u32,
+    name: String,
+}
+
+impl UserRename {
+    pub const ID: u8 = 0x1B;
+
+    pub fn new(id: u32, name: String) -> Self {
+        Self { id, name }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, String> {
+        let slice_len = slice.len();
+
+        // [id_p0, .., id_p3, len, char] => 6
+        if slice_len < 6 {
+            return Err(String::from("UserRename must be at least 6 byte"));
+        }
+
+        let mut id = [0; 4];
+        id.copy_from_slice(&slice[..4]);
+        let id = u32::from_be_bytes(id);
+
+        let name_len = slice[4] as usize;
+        if name_len > MAX_NAME_LEN {
+            return Err(format!("UserRename name must be at most {} byte", MAX_NAME_LEN));
+        }
+
+        if slice_len != 5 + name_len {
+            return Err(String::from("UserRename has incomplete data"));
+        }
+
+        let name = std::str::from_utf8(
+            &slice[5..]
+        ).map_err(|_| String::from("UserRename name is not valid UTF-8"))?.to_owned();
+
+        Ok(Self { id, name })
+    }
+
+    pub fn frame_len(slice: &[u8]) -> Option<usize> {
+        slice.get(4).map(|name_len| 5 + *name_len as usize)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn msg_len(&self) -> usize {
+        6 + self.name.len()
+    }
+
+    pub fn into_vec(self) -> Result<Vec<u8>, String> {
+        let name_len = self.name.len();
+        if name_len == 0 || name_len > MAX_NAME_LEN {
+            return Err(format!("UserRename name must be 1 to {} byte, found {}", MAX_NAME_LEN, name_len));
+        }
+
+        let mut vec = Vec::with_capacity(self.msg_len());
+
+        vec.push(Self::ID);
+        vec.extend_from_slice(&self.id.to_be_bytes());
+        vec.push(name_len as u8);
+        vec.extend(self.name.into_bytes());
+
+        Ok(vec)
+    }
+}
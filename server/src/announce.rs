@@ -0,0 +1,179 @@
+use protocol::multicast::{Announcement, MulticastMessage};
+use std::convert::TryInto;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use super::writer::Writer;
+
+pub type Users = Arc<RwLock<Vec<(u32, String, bool, Writer)>>>;
+
+// answers pings and tells the discovery groups about the server
+#[derive(Debug)]
+pub struct Announcer {
+    announcement: RwLock<Announcement>,
+    users: Users,
+    // socket joined to each group, with the group address
+    groups: Vec<(UdpSocket, SocketAddr)>,
+}
+
+impl Announcer {
+    pub fn new(announcement: Announcement, users: Users) -> Self {
+        Self { announcement: RwLock::new(announcement), users, groups: Vec::with_capacity(2) }
+    }
+
+    pub fn set_description(&self, description: String) {
+        self.announcement.write().unwrap().description = description;
+    }
+
+    pub fn add_group(&mut self, socket: UdpSocket, group: SocketAddr) {
+        self.groups.push((socket, group));
+    }
+
+    fn current(&self) -> Announcement {
+        let announcement = self.announcement.read().unwrap().clone();
+        let max = announcement.users.map_or(0, |(_, max)| max);
+        let current = self.users.read().unwrap().len().min(u16::MAX as usize) as u16;
+
+        Announcement { users: Some((current, max)), ..announcement }
+    }
+
+    fn send_all(&self, msg: MulticastMessage) {
+        let buf: Vec<_> = msg.into();
+
+        for (socket, group) in &self.groups {
+            if let Err(err) = socket.send_to(&buf, group) {
+                println!("Announce: {}: {}", group, err);
+            }
+        }
+    }
+
+    pub fn announce(&self) {
+        self.send_all(MulticastMessage::announce(self.current()));
+    }
+
+    // lets clients drop the server right away instead of waiting for it to expire
+    pub fn goodbye(&self) {
+        self.send_all(MulticastMessage::goodbye(self.current()));
+    }
+
+    // one thread per group answering pings
+    pub fn answer_pings(announcer: &Arc<Self>) -> Vec<JoinHandle<()>> {
+        announcer.groups.iter().enumerate().map(|(index, (_, group))| {
+            let announcer = announcer.clone();
+
+            std::thread::Builder::new().name(format!("Ping {}", group)).spawn(move || {
+                let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
+                let socket = &announcer.groups[index].0;
+                println!("{}: Ready", thread_name);
+                // the group also carries announcements
+                let mut buf = [0; 1024];
+
+                loop {
+                    let (len, addr) = match socket.recv_from(&mut buf) {
+                        Ok(infos) => infos,
+                        Err(err) => {
+                            println!("{}", err);
+                            break;
+                        }
+                    };
+
+                    let slice = &buf[..len];
+                    let msg: MulticastMessage = match slice.try_into() {
+                        Ok(ping) => ping,
+                        Err(err) => {
+                            println!("{}: {:?}", thread_name, err);
+                            continue;
+                        }
+                    };
+
+                    if msg.is_ping() {
+                        println!("{}: Ping from {}", thread_name, addr);
+
+                        // clients from before the extended layout only read a name and a port
+                        let current = announcer.current();
+                        let reply = match msg.is_legacy() {
+                            true => MulticastMessage::server_identity(current.name, current.port),
+                            false => MulticastMessage::announce(current),
+                        };
+
+                        let server_identity: Vec<_> = reply.into();
+                        if let Err(err) = socket.send_to(&server_identity, addr) {
+                            println!("{}: {}", thread_name, err);
+                        }
+                    }
+                    // announcements from other servers, and our own, are ignored
+                }
+            }).unwrap()
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Announcer;
+    use protocol::multicast::{Announcement, MulticastMessage};
+    use std::convert::TryInto;
+    use std::net::UdpSocket;
+    use std::sync::{Arc, RwLock};
+    use std::time::Duration;
+
+    fn socket() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        socket
+    }
+
+    fn receive(socket: &UdpSocket) -> MulticastMessage {
+        let mut buf = [0; 1024];
+        let len = socket.recv(&mut buf).unwrap();
+
+        buf[..len].try_into().unwrap()
+    }
+
+    fn announcer(group: &UdpSocket) -> Announcer {
+        let announcement = Announcement { users: Some((0, 20)), version: Some(7), ..Announcement::new(String::from("Lab"), 5359) };
+        let mut announcer = Announcer::new(announcement, Arc::new(RwLock::new(Vec::new())));
+        // the group stands in for a multicast one, it answers from the same socket
+        announcer.add_group(socket(), group.local_addr().unwrap());
+
+        announcer
+    }
+
+    #[test]
+    fn announces_and_says_goodbye() {
+        let group = socket();
+        let announcer = announcer(&group);
+
+        announcer.set_description(String::from("Second floor"));
+        announcer.announce();
+
+        let announced = receive(&group);
+        assert!(!announced.is_goodbye() && !announced.is_legacy());
+        let announcement = announced.content().clone().unwrap();
+        assert_eq!(announcement.description, "Second floor");
+        assert_eq!(announcement.users, Some((0, 20)));
+
+        announcer.goodbye();
+        assert!(receive(&group).is_goodbye());
+    }
+
+    #[test]
+    fn answers_each_ping_in_its_layout() {
+        let group = socket();
+        let announcer = Arc::new(announcer(&group));
+        let server = announcer.groups[0].0.local_addr().unwrap();
+        Announcer::answer_pings(&announcer);
+
+        let client = socket();
+
+        client.send_to(&Vec::from(MulticastMessage::ping()), server).unwrap();
+        let reply = receive(&client);
+        assert!(reply.is_legacy());
+        assert_eq!(reply.content().as_ref().map(|announcement| announcement.port), Some(5359));
+
+        client.send_to(&Vec::from(MulticastMessage::extended_ping()), server).unwrap();
+        let reply = receive(&client);
+        assert!(!reply.is_legacy());
+        assert_eq!(reply.content().as_ref().and_then(|announcement| announcement.version), Some(7));
+    }
+}
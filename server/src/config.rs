@@ -0,0 +1,284 @@
+use super::rate_limit::RateLimits;
+use super::storage::StorageLimits;
+use protocol::file::{self, HASH_LEN};
+use protocol::multicast::Discovery;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 5359;
+
+pub const USAGE: &str = "usage: server.exe --name NAME [--config PATH] [--bind ADDR]... [--port N] [--description TEXT] [--motd TEXT] [--rules TEXT] [--moderator IDENTITY]... [--max-users N] [--discovery BOOL] [--announce-secs N] [--advertise ADDR]... [--messages-per-sec N] [--bytes-per-sec N] [--ip-messages-per-sec N] [--ip-bytes-per-sec N] [--connections-per-min N] [--upload-bytes-per-sec N] [--download-bytes-per-sec N] [--read-markers-per-sec N] [--mute-after N] [--disconnect-after N] [--mute-secs N] [--storage-dir PATH] [--user-quota-mb N] [--global-quota-mb N] [--max-age-hours N] [--expire-every-mins N] [--multicast-group ADDR] [--multicast-port N] [--multicast-ttl N] [--multicast-interface ADDR] [--multicast-group-v6 ADDR] [--multicast-interface-v6 INDEX]
+
+Every flag can be given in the config file as `flag_name = value`, lists as `bind = [\"0.0.0.0\", \"::\"]`.
+Flags on the command line override the config file.
+A moderator's identity is printed by the console whenever they join.";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub name: String,
+    pub description: String,
+    pub motd: String,
+    pub rules: String,
+    // identities allowed to pin and unpin messages, names can be taken by anybody
+    pub moderators: Vec<[u8; HASH_LEN]>,
+    // empty listens on every IPv4 and IPv6 address
+    pub bind: Vec<IpAddr>,
+    // 0 picks a free port
+    pub port: u16,
+    // 0 is unlimited
+    pub max_users: u16,
+    pub discovery: bool,
+    pub announce_every: Duration,
+    pub advertised: Vec<IpAddr>,
+    pub multicast: Discovery,
+    pub limits: RateLimits,
+    pub storage_limits: StorageLimits,
+    pub storage_dir: PathBuf,
+}
+
+impl Config {
+    fn new() -> Result<Self, String> {
+        Ok(Self {
+            name: String::new(),
+            description: String::new(),
+            motd: String::new(),
+            rules: String::new(),
+            moderators: Vec::new(),
+            bind: Vec::new(),
+            port: DEFAULT_PORT,
+            max_users: 0,
+            discovery: true,
+            announce_every: Duration::from_secs(5),
+            advertised: Vec::new(),
+            multicast: Discovery::from_env()?,
+            limits: RateLimits::default(),
+            storage_limits: StorageLimits::default(),
+            storage_dir: std::env::temp_dir().join("messaging-storage"),
+        })
+    }
+
+    // defaults, then the config file, then the remaining flags
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::new()?;
+        let mut flags = Vec::new();
+        let mut args = args.peekable();
+
+        if args.peek().is_none() {
+            return Err(String::from(USAGE));
+        }
+
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{}: missing value", flag))?;
+
+            match flag.as_str() {
+                "--config" => config.load(&value)?,
+                _ => flags.push((flag, value)),
+            }
+        }
+
+        // lists given on the command line replace the ones from the file
+        for list in ["--moderator", "--bind", "--advertise"] {
+            if flags.iter().any(|(flag, _)| flag == list) {
+                config.set(list, None)?;
+            }
+        }
+
+        for (flag, value) in flags {
+            config.set(&flag, Some(&value))?;
+        }
+
+        if config.name.is_empty() {
+            return Err(format!("--name is required\n{}", USAGE));
+        }
+
+        // each listener would pick a port of its own, only one is announced
+        if config.port == 0 && config.bind.len() > 1 {
+            return Err(String::from("--port 0 needs a single --bind, give a port to listen on several addresses"));
+        }
+
+        Ok(config)
+    }
+
+    // TOML, `key = value` at the top level, values are strings, numbers, booleans or arrays of them
+    fn load(&mut self, path: &str) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        self.parse(&content).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    fn parse(&mut self, content: &str) -> Result<(), String> {
+        let table: toml::value::Table = toml::from_str(content).map_err(|err: toml::de::Error| err.to_string())?;
+
+        for (key, value) in table {
+            let flag = format!("--{}", key.replace('_', "-"));
+
+            if flag == "--config" {
+                return Err(String::from("config files can't include other config files"));
+            }
+
+            match value {
+                toml::Value::Array(items) => {
+                    self.set(&flag, None)?;
+
+                    for item in items {
+                        self.set(&flag, Some(&scalar(&key, item)?))?;
+                    }
+                }
+                value => self.set(&flag, Some(&scalar(&key, value)?))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    // lists grow with each value, no value clears them
+    fn set(&mut self, flag: &str, value: Option<&str>) -> Result<(), String> {
+        let value = match (flag, value) {
+            ("--moderator", None) => {
+                self.moderators.clear();
+                return Ok(());
+            }
+            ("--bind", None) => {
+                self.bind.clear();
+                return Ok(());
+            }
+            ("--advertise", None) => {
+                self.advertised.clear();
+                return Ok(());
+            }
+            (_, None) => return Err(format!("{}: expected a single value", flag)),
+            (_, Some(value)) => value,
+        };
+
+        let ip = |value: &str| value.parse::<IpAddr>().map_err(|err| format!("{}: {}", flag, err));
+
+        match flag {
+            "--name" => self.name = value.to_owned(),
+            "--description" => self.description = value.to_owned(),
+            "--motd" => self.motd = value.to_owned(),
+            "--rules" => self.rules = value.to_owned(),
+            "--moderator" => self.moderators.push(file::from_hex(value).ok_or_else(|| format!("{}: expected an identity of {} hex digits, found {}", flag, HASH_LEN * 2, value))?),
+            "--bind" => self.bind.push(ip(value)?),
+            "--port" => self.port = number(flag, value)?,
+            "--max-users" => self.max_users = number(flag, value)?,
+            "--discovery" => {
+                self.discovery = value.parse().map_err(|_| format!("{}: expected true or false, found {}", flag, value))?
+            }
+            // the announce thread would never sleep, --discovery false stops announcing
+            "--announce-secs" => match number(flag, value)? {
+                0 => return Err(format!("{}: must be at least 1", flag)),
+                secs => self.announce_every = Duration::from_secs(secs),
+            },
+            "--advertise" => self.advertised.push(ip(value)?),
+            "--storage-dir" => self.storage_dir = value.into(),
+            "--user-quota-mb" | "--global-quota-mb" | "--max-age-hours" | "--expire-every-mins" => self.storage_limits.set(flag, value)?,
+            flag if Discovery::is_flag(flag) => self.multicast.set(flag, value)?,
+            _ => self.limits.set(flag, value)?,
+        }
+
+        Ok(())
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{}: expected a number, found {}", flag, value))
+}
+
+// passed on as it would be written on the command line
+fn scalar(key: &str, value: toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        other => Err(format!("{}: expected a string, a number or a boolean, found {}", key, other.type_str())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, DEFAULT_PORT, HASH_LEN};
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    fn file(name: &str, content: &str) -> String {
+        let path = crate::test_dir(name).join("server.toml");
+        std::fs::write(&path, content).unwrap();
+
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn reads_flags() {
+        let moderator = "ab".repeat(HASH_LEN);
+        let config = Config::from_args(args(&["--name", "Lab", "--port", "6000", "--moderator", &moderator, "--messages-per-sec", "3"])).unwrap();
+
+        assert_eq!(config.name, "Lab");
+        assert_eq!(config.port, 6000);
+        assert_eq!(config.moderators, [[0xAB; HASH_LEN]]);
+        assert_eq!(config.limits.messages_per_sec, 3);
+    }
+
+    #[test]
+    fn reads_the_config_file() {
+        let path = file("config_file", r#"
+            # comments and escapes are TOML's
+            name = "Lab \"one\""
+            description = 'C:\shared'
+            max_users = 20
+            discovery = false
+            bind = ["127.0.0.1", "::1"]
+            user_quota_mb = 2
+        "#);
+
+        let config = Config::from_args(args(&["--config", &path])).unwrap();
+
+        assert_eq!(config.name, "Lab \"one\"");
+        assert_eq!(config.description, "C:\\shared");
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.max_users, 20);
+        assert!(!config.discovery);
+        assert_eq!(config.bind, ["127.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+        assert_eq!(config.storage_limits.user_quota, 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn reads_the_example() {
+        let path = file("config_example", include_str!("../example.toml"));
+
+        let config = Config::from_args(args(&["--config", &path])).unwrap();
+
+        assert_eq!(config.name, "Lab server");
+        assert_eq!(config.moderators.len(), 1);
+        assert_eq!(config.port, 5359);
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let path = file("config_override", "name = \"Lab\"\nannounce_secs = 10\nbind = [\"127.0.0.1\", \"::1\"]\n");
+
+        let config = Config::from_args(args(&["--bind", "0.0.0.0", "--config", &path, "--announce-secs", "2"])).unwrap();
+
+        assert_eq!(config.announce_every, Duration::from_secs(2));
+        assert_eq!(config.bind, ["0.0.0.0".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let invalid = |name: &str, content: &str| Config::from_args(args(&["--name", "Lab", "--config", &file(name, content)])).is_err();
+
+        assert!(invalid("config_syntax", "name = \"Lab"));
+        assert!(invalid("config_unknown", "colour = \"blue\""));
+        assert!(invalid("config_nested", "config = \"other.toml\""));
+        assert!(invalid("config_table", "[limits]\nmute_after = 2"));
+        assert!(invalid("config_announce", "announce_secs = 0"));
+
+        assert!(Config::from_args(args(&["--port", "6000"])).is_err());
+        assert!(Config::from_args(args(&["--name", "Lab", "--moderator", "Alice"])).is_err());
+        assert!(Config::from_args(args(&["--name", "Lab", "--port", "0", "--bind", "127.0.0.1", "--bind", "::1"])).is_err());
+        assert!(Config::from_args(args(&["--name", "Lab", "--port", "0", "--bind", "127.0.0.1"])).is_ok());
+    }
+}
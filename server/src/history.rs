@@ -0,0 +1,361 @@
+use protocol::network::NetworkMessage;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// results sent for a single search, whatever the client asks for
+pub const MAX_RESULTS: u16 = 50;
+
+#[derive(Debug)]
+struct Entry {
+    // of the record in the messages file
+    offset: u64,
+    time: u64,
+    from: String,
+}
+
+// every message sent on the server, with an inverted index of their words
+#[derive(Debug)]
+pub struct History {
+    dir: PathBuf,
+    messages: File,
+    index: File,
+    len: u64,
+    entries: BTreeMap<u32, Entry>,
+    // word to the ids of the messages containing it, ascending
+    words: HashMap<String, Vec<u32>>,
+}
+
+impl History {
+    const MESSAGES: &'static str = "messages";
+    const INDEX: &'static str = "index";
+
+    pub fn open(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let open = |name| OpenOptions::new().create(true).read(true).append(true).open(dir.join(name));
+        let mut history = Self {
+            messages: open(Self::MESSAGES)?,
+            index: open(Self::INDEX)?,
+            dir,
+            len: 0,
+            entries: BTreeMap::new(),
+            words: HashMap::new(),
+        };
+
+        history.load_messages()?;
+        history.load_index()?;
+
+        Ok(history)
+    }
+
+    // [id_p0, .., id_p3, time_p0, .., time_p7, from_len, from, content_len_up, content_len_down, content]
+    fn load_messages(&mut self) -> std::io::Result<()> {
+        let mut data = Vec::new();
+        (&self.messages).read_to_end(&mut data)?;
+
+        let mut offset = 0;
+        while let Some((id, time, from, content)) = read_record(&data[offset..]) {
+            let len = 15 + from.len() + content.len();
+            self.entries.insert(id, Entry { offset: offset as u64, time, from });
+            offset += len;
+        }
+
+        // a record cut short by a crash is dropped
+        if offset != data.len() {
+            println!("History: Dropping {} byte of incomplete messages", data.len() - offset);
+            self.messages.set_len(offset as u64)?;
+        }
+
+        self.len = offset as u64;
+        Ok(())
+    }
+
+    // [word] [id], one line per posting
+    fn load_index(&mut self) -> std::io::Result<()> {
+        let mut index = String::new();
+        (&self.index).read_to_string(&mut index)?;
+
+        for line in index.lines() {
+            match line.split_once(' ').and_then(|(word, id)| Some((word, id.parse().ok()?))) {
+                Some((word, id)) if self.entries.contains_key(&id) => self.words.entry(word.to_owned()).or_default().push(id),
+                _ => println!("History: Skipping index entry {}", line),
+            }
+        }
+
+        for ids in self.words.values_mut() {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+
+        // messages written after the index was last appended to
+        let last_indexed = self.words.values().filter_map(|ids| ids.last()).max().copied().unwrap_or(0);
+        let missing: Vec<_> = self.entries.range(last_indexed + 1..).map(|(id, _)| *id).collect();
+
+        for id in missing {
+            if let Some((_, _, content)) = read_at(&self.messages, self.entries[&id].offset) {
+                self.index(id, &content)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn latest(&self) -> Option<u32> {
+        self.entries.keys().next_back().copied()
+    }
+
+    fn next_id(&self) -> u32 {
+        self.entries.keys().next_back().map_or(1, |id| id.wrapping_add(1).max(1))
+    }
+
+    // gives the message its id, it is still sent when it can't be written
+    pub fn append(&mut self, from: &str, content: &str) -> u32 {
+        self.append_at(from, content, now())
+    }
+
+    fn append_at(&mut self, from: &str, content: &str, time: u64) -> u32 {
+        let id = self.next_id();
+
+        let mut record = Vec::with_capacity(15 + from.len() + content.len());
+        record.extend_from_slice(&id.to_be_bytes());
+        record.extend_from_slice(&time.to_be_bytes());
+        record.push(from.len() as u8);
+        record.extend_from_slice(from.as_bytes());
+        record.extend_from_slice(&(content.len() as u16).to_be_bytes());
+        record.extend_from_slice(content.as_bytes());
+
+        let written = self.messages.write_all(&record).and_then(|_| self.index(id, content));
+        if let Err(err) = written {
+            println!("History: {}: {}", self.dir.display(), err);
+        }
+
+        self.entries.insert(id, Entry { offset: self.len, time, from: from.to_owned() });
+        self.len += record.len() as u64;
+
+        id
+    }
+
+    fn index(&mut self, id: u32, content: &str) -> std::io::Result<()> {
+        let mut postings = String::new();
+
+        for word in words(content) {
+            postings.push_str(&format!("{} {}\n", word, id));
+            self.words.entry(word).or_default().push(id);
+        }
+
+        self.index.write_all(postings.as_bytes())
+    }
+
+    // only finds the matches, `Found::read` reads them once the history is unlocked
+    pub fn search(&self, query: &str, user: &str, before: u64, after: u64, limit: u16) -> Found {
+        let words: Vec<_> = words(query).into_iter().collect();
+
+        // every word must appear, starting from the rarest
+        let ids: Vec<u32> = match words.iter().map(|word| self.words.get(word)).collect::<Option<Vec<_>>>() {
+            None => Vec::new(),
+            Some(postings) if postings.is_empty() => self.entries.keys().copied().collect(),
+            Some(mut postings) => {
+                postings.sort_by_key(|ids| ids.len());
+
+                postings[0].iter()
+                    .filter(|id| postings[1..].iter().all(|ids| ids.binary_search(id).is_ok()))
+                    .copied()
+                    .collect()
+            }
+        };
+
+        let matches: Vec<_> = ids.into_iter()
+            .rev()
+            .filter(|id| {
+                let entry = &self.entries[id];
+
+                (user.is_empty() || entry.from.to_lowercase() == user.to_lowercase())
+                    && (before == 0 || entry.time < before)
+                    && (after == 0 || entry.time > after)
+            })
+            .collect();
+
+        Found {
+            path: self.dir.join(Self::MESSAGES),
+            offsets: matches.iter()
+                .take(limit.min(MAX_RESULTS) as usize)
+                .map(|id| (*id, self.entries[id].offset))
+                .collect(),
+            total: matches.len() as u32,
+        }
+    }
+}
+
+// matches of a search, newest first, with where their records start
+#[derive(Debug)]
+pub struct Found {
+    path: PathBuf,
+    offsets: Vec<(u32, u64)>,
+    total: u32,
+}
+
+impl Found {
+    // SearchResult for each match, then SearchEnd
+    pub fn read(self) -> Vec<NetworkMessage> {
+        // a handle of its own, the history's is moved by every append
+        let messages = match File::open(&self.path) {
+            Ok(messages) => Some(messages),
+            Err(err) => {
+                println!("History: {}: {}", self.path.display(), err);
+                None
+            }
+        };
+
+        let mut results: Vec<_> = messages.iter()
+            .flat_map(|messages| self.offsets.iter().filter_map(move |(id, offset)| {
+                let (time, from, content) = read_at(messages, *offset)?;
+                Some(NetworkMessage::search_result(*id, time, from, content))
+            }))
+            .collect();
+
+        results.push(NetworkMessage::search_end(self.total));
+        results
+    }
+}
+
+// (time, from, content) of the record starting at `offset`
+fn read_at(mut messages: &File, offset: u64) -> Option<(u64, String, String)> {
+    let mut header = [0; 13];
+
+    messages.seek(SeekFrom::Start(offset)).ok()?;
+    messages.read_exact(&mut header).ok()?;
+
+    let mut data = vec![0; 13 + header[12] as usize + 2];
+    data[..13].copy_from_slice(&header);
+    messages.read_exact(&mut data[13..]).ok()?;
+
+    let content_len = u16::from_be_bytes([data[data.len() - 2], data[data.len() - 1]]) as usize;
+    data.resize(data.len() + content_len, 0);
+    let end = data.len();
+    messages.read_exact(&mut data[end - content_len..]).ok()?;
+
+    read_record(&data).map(|(_, time, from, content)| (time, from, content))
+}
+
+fn read_record(data: &[u8]) -> Option<(u32, u64, String, String)> {
+    let id = u32::from_be_bytes(data.get(..4)?.try_into().ok()?);
+    let time = u64::from_be_bytes(data.get(4..12)?.try_into().ok()?);
+
+    let from_len = *data.get(12)? as usize;
+    let from = std::str::from_utf8(data.get(13..13 + from_len)?).ok()?.to_owned();
+
+    let cursor = 13 + from_len;
+    let content_len = u16::from_be_bytes(data.get(cursor..cursor + 2)?.try_into().ok()?) as usize;
+    let content = std::str::from_utf8(data.get(cursor + 2..cursor + 2 + content_len)?).ok()?.to_owned();
+
+    Some((id, time, from, content))
+}
+
+// lowercase runs of letters and digits, each once
+fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{words, History, MAX_RESULTS};
+    use protocol::network::NetworkMessage;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    // (id, from, content) of each result, then the total
+    fn results(history: &History, query: &str, user: &str, before: u64, after: u64, limit: u16) -> (Vec<(u32, String, String)>, u32) {
+        let mut results = history.search(query, user, before, after, limit).read();
+
+        let total = match results.pop() {
+            Some(NetworkMessage::SearchEnd(end)) => end.total(),
+            other => panic!("expected SearchEnd, found {:?}", other),
+        };
+
+        let results = results.into_iter().map(|msg| match msg {
+            NetworkMessage::SearchResult(result) => (result.message_id(), result.from().to_owned(), result.content().to_owned()),
+            other => panic!("expected SearchResult, found {}", other),
+        }).collect();
+
+        (results, total)
+    }
+
+    fn ids(found: (Vec<(u32, String, String)>, u32)) -> Vec<u32> {
+        found.0.into_iter().map(|(id, ..)| id).collect()
+    }
+
+    #[test]
+    fn splits_words() {
+        let words: Vec<_> = words("Hello, hello WORLD! l'été 42x").into_iter().collect();
+
+        assert_eq!(words, ["42x", "hello", "l", "world", "été"]);
+    }
+
+    #[test]
+    fn finds_messages_with_every_word() {
+        let mut history = History::open(crate::test_dir("history_words")).unwrap();
+        history.append("Alice", "The build is green");
+        history.append("Bob", "Build failed again");
+        history.append("Alice", "green tea anyone?");
+
+        assert_eq!(results(&history, "build", "", 0, 0, 10), (vec![
+            (2, String::from("Bob"), String::from("Build failed again")),
+            (1, String::from("Alice"), String::from("The build is green")),
+        ], 2));
+        assert_eq!(ids(results(&history, "GREEN build", "", 0, 0, 10)), [1]);
+        assert_eq!(ids(results(&history, "green", "alice", 0, 0, 10)), [3, 1]);
+        assert_eq!(ids(results(&history, "green", "Bob", 0, 0, 10)), Vec::<u32>::new());
+        assert_eq!(ids(results(&history, "purple", "", 0, 0, 10)), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn filters_by_time_and_limits() {
+        let mut history = History::open(crate::test_dir("history_time")).unwrap();
+        for time in [100, 200, 300, 400] {
+            history.append_at("Alice", "ping", time);
+        }
+
+        assert_eq!(ids(results(&history, "ping", "", 300, 0, 10)), [2, 1]);
+        assert_eq!(ids(results(&history, "ping", "", 0, 200, 10)), [4, 3]);
+        assert_eq!(ids(results(&history, "ping", "", 400, 100, 10)), [3, 2]);
+
+        // the total counts every match, not only those sent
+        assert_eq!(results(&history, "ping", "", 0, 0, 1).1, 4);
+        assert_eq!(ids(results(&history, "ping", "", 0, 0, 1)), [4]);
+        // an empty query lists everything
+        assert_eq!(ids(results(&history, "", "", 0, 0, 2)), [4, 3]);
+        assert!(results(&history, "", "", 0, 0, u16::MAX).0.len() <= MAX_RESULTS as usize);
+    }
+
+    #[test]
+    fn reopens_and_drops_cut_records() {
+        let dir = crate::test_dir("history_reopen");
+
+        let mut history = History::open(dir.clone()).unwrap();
+        history.append("Alice", "first words");
+        history.append("Bob", "second words");
+        drop(history);
+
+        // a crash in the middle of a record, and an index that missed the last message
+        OpenOptions::new().append(true).open(dir.join("messages")).unwrap().write_all(&[0, 0, 0, 3, 0]).unwrap();
+        let index = std::fs::read_to_string(dir.join("index")).unwrap();
+        let kept: String = index.lines().filter(|line| !line.ends_with(" 2")).map(|line| format!("{}\n", line)).collect();
+        std::fs::write(dir.join("index"), kept).unwrap();
+
+        let mut history = History::open(dir).unwrap();
+        assert_eq!(history.latest(), Some(2));
+        assert_eq!(ids(results(&history, "second", "", 0, 0, 10)), [2]);
+        assert_eq!(history.append("Alice", "third"), 3);
+        assert_eq!(ids(results(&history, "words", "", 0, 0, 10)), [2, 1]);
+    }
+}
@@ -0,0 +1,98 @@
+use protocol::multicast::SERVER_ID_LEN;
+use protocol::network::{capabilities, NetworkMessage};
+
+const CAPABILITIES: u32 = capabilities::FILE_TRANSFER;
+
+// what joining users are shown, owners edit it from the console
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    pub server_id: [u8; SERVER_ID_LEN],
+    pub name: String,
+    pub description: String,
+    pub motd: String,
+    pub rules: String,
+}
+
+impl ServerInfo {
+    pub fn message(&self) -> NetworkMessage {
+        NetworkMessage::server_info(
+            CAPABILITIES,
+            self.server_id,
+            self.name.to_owned(),
+            self.description.to_owned(),
+            self.motd.to_owned(),
+            self.rules.to_owned(),
+        )
+    }
+
+    // `\n` in the value starts a new line
+    pub fn edit(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let mut edited = self.clone();
+        let value = value.replace("\\n", "\n");
+
+        match field {
+            "description" => edited.description = value,
+            "motd" => edited.motd = value,
+            "rules" => edited.rules = value,
+            unknown => return Err(format!("Unknown field {}, expected description, motd or rules", unknown)),
+        }
+
+        // refuse edits that would no longer fit in a frame
+        edited.message().into_vec()?;
+        *self = edited;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServerInfo;
+    use protocol::network::{capabilities, NetworkMessage, MAX_FRAME_LEN};
+
+    fn info() -> ServerInfo {
+        ServerInfo {
+            server_id: [7; 16],
+            name: String::from("Lab"),
+            description: String::from("Second floor"),
+            motd: String::new(),
+            rules: String::new(),
+        }
+    }
+
+    #[test]
+    fn carries_every_field() {
+        let msg = match info().message() {
+            NetworkMessage::ServerInfo(msg) => msg,
+            other => panic!("unexpected {}", other),
+        };
+
+        assert!(msg.has(capabilities::FILE_TRANSFER));
+        assert_eq!(msg.server_id(), &[7; 16]);
+        assert_eq!(msg.name(), "Lab");
+        assert_eq!(msg.description(), "Second floor");
+    }
+
+    #[test]
+    fn edits_fields() {
+        let mut info = info();
+
+        info.edit("rules", "Be nice\\nNo spam").unwrap();
+        info.edit("motd", "Hi").unwrap();
+        assert_eq!(info.rules, "Be nice\nNo spam");
+        assert_eq!(info.motd, "Hi");
+
+        assert!(info.edit("name", "Other").is_err());
+        assert_eq!(info.name, "Lab");
+    }
+
+    #[test]
+    fn refuses_what_no_longer_fits() {
+        let mut info = info();
+
+        assert!(info.edit("description", &"a".repeat(256)).is_err());
+        assert!(info.edit("motd", &"a".repeat(MAX_FRAME_LEN)).is_err());
+        assert_eq!(info.description, "Second floor");
+        assert!(info.motd.is_empty());
+    }
+}
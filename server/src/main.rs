@@ -1,204 +1,703 @@
+mod announce;
+mod config;
+mod history;
+mod info;
+mod pins;
+mod read_markers;
+mod rate_limit;
+mod spool;
+mod storage;
+mod writer;
+
 use std::{
-    net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket},
-    io::{Read, Write},
-    str::FromStr,
+    net::{Ipv6Addr, SocketAddr, SocketAddrV6, TcpListener},
+    io::{BufRead, Read, Write},
+    path::Path,
 };
 use protocol::{
-    multicast::MulticastMessage,
-    network::NetworkMessage,
+    file,
+    mention,
+    multicast::{Announcement, SERVER_ID_LEN},
+    network::{refusal, FrameReader, NetworkMessage, PROTOCOL_VERSION},
 };
-use std::sync::{Arc, RwLock};
-use std::convert::TryInto;
+use announce::Announcer;
+use config::Config;
+use history::History;
+use info::ServerInfo;
+use pins::Pins;
+use read_markers::ReadMarkers;
+use rate_limit::{ConnectionLimiter, IpLimiter, Verdict};
+use spool::Spool;
+use storage::Storage;
+use writer::Writer;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 use rand::Rng;
+use socket2::{Domain, Protocol, Socket, Type};
+
+// read markers lost to a crash are at most this old
+const SAVE_READ_MARKERS_EVERY: std::time::Duration = std::time::Duration::from_secs(10);
 
 fn main() {
-    let name = match std::env::args().nth(1) {
-        None => {
-            println!("usage: server.exe <server_name>");
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("{}", err);
             return;
         }
-        Some(name) => name,
     };
 
-    let listener = TcpListener::bind("0.0.0.0:0").unwrap();
-    let port = listener.local_addr().unwrap().port();
+    let listeners = match bind_listeners(&config) {
+        Ok(listeners) => listeners,
+        Err(err) => {
+            println!("TCP: {}", err);
+            return;
+        }
+    };
 
-    let handle_ping = std::thread::Builder::new().name(String::from("Ping")).spawn(move || {
-        let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
-        let socket = UdpSocket::bind(format!("0.0.0.0:{}", protocol::network::MULTICAST_PORT)).unwrap();
-        socket.join_multicast_v4(
-            // multicast address must be between
-            // 224.x.x.x and 239.x.x.x => D class
-            &Ipv4Addr::from_str(protocol::network::MULTICAST_ADDRESS).unwrap(),
-            &Ipv4Addr::new(0, 0, 0, 0)
-        ).unwrap();
-
-        let server_identity: Vec<_> = MulticastMessage::server_identity(name.to_owned(), port).into();
-        println!("{}: Ready", thread_name);
-        let mut buf = [0; 32];
+    // every listener has the same port, see Config::from_args
+    let port = listeners[0].local_addr().unwrap().port();
+    let Config { name, description, moderators, max_users, announce_every, limits, storage_limits, storage_dir, .. } = config.clone();
 
-        loop {
-            let (len, addr) = match socket.recv_from(&mut buf) {
-                Ok(infos) => infos,
-                Err(err) => {
-                    println!("{}", err);
-                    break;
-                }
-            };
+    let server_id = load_server_id(&storage_dir);
+    let info = ServerInfo {
+        server_id,
+        name: name.to_owned(),
+        description: description.to_owned(),
+        motd: config.motd.to_owned(),
+        rules: config.rules.to_owned(),
+    };
 
-            let slice = &buf[..len];
-            let msg: MulticastMessage = match slice.try_into() {
-                Ok(ping) => ping,
-                Err(err) => {
-                    println!("{}: {:?}", thread_name, err);
-                    continue;
-                }
-            };
+    if let Err(err) = info.message().into_vec() {
+        println!("{}", err);
+        return;
+    }
 
-            if msg.is_ping() {
-                println!("{}: Ping from {}", thread_name, addr);
-                socket.send_to(&server_identity, addr).unwrap();
-            } else {
-                println!("{}: Unexpected {}", thread_name, msg);
+    let info = Arc::new(RwLock::new(info));
+    let addr = Arc::new(RwLock::new(Vec::<(u32, String, bool, Writer)>::with_capacity(50)));
+    let announcement = Announcement {
+        endpoints: config.advertised.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+        description,
+        users: Some((0, max_users)),
+        version: Some(PROTOCOL_VERSION),
+        server_id: Some(server_id),
+        ..Announcement::new(name.to_owned(), port)
+    };
+    let mut announcer = Announcer::new(announcement, addr.clone());
+
+    let discovery = config.multicast;
+
+    if config.discovery {
+        match discovery.listen() {
+            Ok(socket) => {
+                println!("Ping: Listening on {} via {}", discovery.group_addr(), discovery.interface);
+                announcer.add_group(socket, discovery.group_addr());
             }
+            Err(err) => println!("Ping: {}", err),
         }
+
+        // IPv6 discovery runs alongside, hosts without IPv6 keep IPv4 only
+        match discovery.listen_v6() {
+            Ok(socket) => {
+                println!("Ping IPv6: Listening on {}", discovery.group_addr_v6());
+                announcer.add_group(socket, discovery.group_addr_v6());
+            }
+            Err(err) => println!("Ping IPv6: {}", err),
+        }
+    }
+
+    let announcer = Arc::new(announcer);
+    let handle_pings = Announcer::answer_pings(&announcer);
+
+    let t_announcer = announcer.clone();
+    let handle_announce = std::thread::Builder::new().name(String::from("Announce")).spawn(move || {
+        // no group means discovery is off
+        if !config.discovery {
+            return;
+        }
+
+        loop {
+            t_announcer.announce();
+            std::thread::sleep(announce_every);
+        }
+    }).unwrap();
+
+    let read_markers = Arc::new(Mutex::new(ReadMarkers::open(storage_dir.join("read_markers")).unwrap()));
+
+    // saved now and then rather than on every marker, and once more when shutting down
+    let t_read_markers = read_markers.clone();
+    std::thread::Builder::new().name(String::from("Read markers")).spawn(move || loop {
+        std::thread::sleep(SAVE_READ_MARKERS_EVERY);
+        t_read_markers.lock().unwrap().save();
     }).unwrap();
 
-    let addr = Arc::new(RwLock::new(Vec::<(u32, String, TcpStream)>::with_capacity(50)));
+    // Ctrl+C and termination requests leave the same way as typing quit
+    let t_announcer = announcer.clone();
+    let t_read_markers = read_markers.clone();
+    if let Err(err) = ctrlc::set_handler(move || shutdown(&t_announcer, &t_read_markers, "Signal")) {
+        println!("Signal: {}", err);
+    }
+
     let (sender, receiver) = std::sync::mpsc::channel();
-    let t_addr = addr.clone();
 
-    let handle_tcp = std::thread::Builder::new().name(String::from("TCP")).spawn(move || {
+    // typing quit says goodbye before leaving, other commands edit the server info
+    let t_sender = sender.clone();
+    let t_info = info.clone();
+    let t_read_markers = read_markers.clone();
+    std::thread::Builder::new().name(String::from("Console")).spawn(move || {
         let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
-        let mut rng = rand::thread_rng();
 
-        loop {
-            let mut stream = match listener.accept() {
-                Ok((stream, _)) => stream,
-                Err(err) => {
-                    println!("{}: {}", thread_name, err);
-                    break;
-                }
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
             };
 
-            let mut buf = [0; 32];
-            stream.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            match line.trim().split_once(' ').unwrap_or((line.trim(), "")) {
+                ("quit", _) | ("exit", _) => shutdown(&announcer, &t_read_markers, &thread_name),
+                ("info", _) => println!("{}: {:?}", thread_name, t_info.read().unwrap()),
+                (field @ ("description" | "motd" | "rules"), value) => {
+                    let mut info = t_info.write().unwrap();
+
+                    match info.edit(field, value) {
+                        Ok(()) => {
+                            if field == "description" {
+                                announcer.set_description(info.description.to_owned());
+                            }
 
-            match stream.read(&mut buf) {
-                Ok(len) => {
-                    let msg = match NetworkMessage::from_slice(&buf[..len]) {
-                        Ok(msg) => msg,
-                        Err(err) => {
-                            println!("{}: {}", thread_name, err);
-                            continue;
+                            t_sender.send(info.message()).unwrap();
                         }
-                    };
+                        Err(err) => println!("{}: {}", thread_name, err),
+                    }
+                }
+                (other, _) => println!("{}: Unknown command {}, expected quit, info, description TEXT, motd TEXT or rules TEXT", thread_name, other),
+            }
+        }
+    }).unwrap();
+    let t_addr = addr.clone();
+    let pins = Arc::new(Mutex::new(Pins::default()));
+    // away message of everybody who is away, by user id
+    let away = Arc::new(Mutex::new(HashMap::<u32, String>::new()));
+    let history = Arc::new(Mutex::new(History::open(storage_dir.join("history")).unwrap()));
+    let ip_limiter = Arc::new(Mutex::new(IpLimiter::default()));
+    let storage = Arc::new(Mutex::new(Storage::open(storage_dir, storage_limits).unwrap()));
+    let spool = Spool::new(std::env::temp_dir().join("messaging-spool"), storage.clone()).unwrap();
+
+    let handle_expire = std::thread::Builder::new().name(String::from("Expire")).spawn(move || {
+        let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
+
+        loop {
+            let expired = storage.lock().unwrap().expire();
+            if expired > 0 {
+                println!("{}: Removed {} attachments", thread_name, expired);
+            }
+
+            let every = storage.lock().unwrap().limits().expire_every;
+            std::thread::sleep(every);
+        }
+    }).unwrap();
+
+    let handle_tcp: Vec<_> = listeners.into_iter().map(|listener| {
+        let t_addr = t_addr.clone();
+        let sender = sender.clone();
+        let ip_limiter = ip_limiter.clone();
+        let spool = spool.clone();
+        let info = info.clone();
+        let pins = pins.clone();
+        let away = away.clone();
+        let read_markers = read_markers.clone();
+        let history = history.clone();
+        let moderators = moderators.clone();
+        let local = listener.local_addr().unwrap();
+
+        std::thread::Builder::new().name(format!("TCP {}", local)).spawn(move || {
+            let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
+            let mut rng = rand::thread_rng();
+
+            loop {
+                let (mut stream, peer) = match listener.accept() {
+                    Ok(infos) => infos,
+                    Err(err) => {
+                        println!("{}: {}", thread_name, err);
+                        break;
+                    }
+                };
+
+                if let Err(retry_after) = ip_limiter.lock().unwrap().connection(peer.ip(), &limits) {
+                    println!("{}: Too many connection attempts from {}", thread_name, peer.ip());
+                    let msg = NetworkMessage::rate_limited(retry_after.as_millis() as u32, false).into_vec().unwrap();
+                    let _ = stream.write_all(&msg);
+                    continue;
+                }
 
-                    if let NetworkMessage::ClientIdentity(client) = msg {
-                        let addr_reader = t_addr.read().unwrap();
-                        let users = addr_reader.iter().fold(vec![], |mut vec, (id, name, _)| {
-                            vec.push((*id, name.to_owned()));
-                            vec
-                        });
+                let mut buf = [0; protocol::network::MAX_FRAME_LEN];
+                stream.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
 
-                        let id = loop {
-                            let new_id = rng.gen();
+                match stream.read(&mut buf) {
+                    Ok(len) => {
+                        let mut reader = FrameReader::new();
+                        reader.extend(&buf[..len]);
 
-                            if addr_reader.iter().all(|(registered_id, ..)| new_id != *registered_id) {
-                                break new_id;
+                        let msg = match reader.next_message() {
+                            Ok(Some(msg)) => msg,
+                            Ok(None) => {
+                                println!("{}: Incomplete ClientIdentity", thread_name);
+                                continue;
+                            }
+                            Err(err) => {
+                                println!("{}: {}", thread_name, err);
+                                continue;
                             }
                         };
 
-                        drop(addr_reader);
-                        stream.set_read_timeout(None).unwrap();
-                        let sender = sender.clone();
+                        if let NetworkMessage::ClientIdentity(client) = msg {
+                            let addr_reader = t_addr.read().unwrap();
 
-                        let msg = NetworkMessage::personal_id(id).into_vec();
-                        stream.write_all(&msg).unwrap();
+                            if max_users != 0 && addr_reader.len() >= max_users as usize {
+                                println!("{}: Server full, refusing {}", thread_name, client.name());
+                                // nothing else is written to it, the writer comes after
+                                let _ = stream.write_all(&NetworkMessage::refused(refusal::SERVER_FULL).into_vec().unwrap());
+                                continue;
+                            }
 
-                        std::thread::sleep(std::time::Duration::from_millis(125));
+                            let users = addr_reader.iter().fold(vec![], |mut vec, (id, name, bot, _)| {
+                                vec.push((*id, name.to_owned(), *bot));
+                                vec
+                            });
 
-                        let msg = NetworkMessage::user_list(users).into_vec();
-                        stream.write_all(&msg).unwrap();
+                            let id = loop {
+                                let new_id = rng.gen();
 
-                        sender.send(NetworkMessage::user_join(client.name().to_owned(), id)).unwrap();
-                        t_addr.write().unwrap().push((id, client.name().to_owned(), stream.try_clone().unwrap()));
+                                if addr_reader.iter().all(|(registered_id, ..)| new_id != *registered_id) {
+                                    break new_id;
+                                }
+                            };
 
-                        std::thread::Builder::new().name(format!("{}_thread", client.name())).spawn(move || {
-                            let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
-                            let mut buf = [0;1024];
+                            drop(addr_reader);
+                            stream.set_read_timeout(None).unwrap();
 
-                            loop {
-                                let len = match stream.read(&mut buf) {
-                                    Ok(len) => len,
-                                    Err(err) if err.kind() == std::io::ErrorKind::ConnectionReset => {
-                                        println!("{}: Closed by peer", thread_name);
-                                        break;
-                                    }
-                                    Err(err) => {
-                                        println!("{}: {}", thread_name, err);
-                                        break;
-                                    }
-                                };
-
-                                let slice = &buf[..len];
-                                let msg = match NetworkMessage::from_slice(slice) {
-                                    Ok(msg) => msg,
-                                    Err(err) => {
-                                        println!("{}: {}", thread_name, err);
-                                        break;
-                                    }
-                                };
+                            let writer = match stream.try_clone().and_then(|stream| Writer::spawn(stream, client.name(), limits.download_bytes_per_sec)) {
+                                Ok(writer) => writer,
+                                Err(err) => {
+                                    println!("{}: {}", thread_name, err);
+                                    continue;
+                                }
+                            };
+
+                            // follows the client across names, unlike its name
+                            let hash = file::hash(client.secret());
+                            let identity = file::to_hex(&hash);
+                            println!("{}: {} joined as {}", thread_name, client.name(), identity);
+                            let sender = sender.clone();
+                            let ip_limiter = ip_limiter.clone();
+                            let mut limiter = ConnectionLimiter::new(limits);
+                            let spool = spool.clone();
+                            let history = history.clone();
+                            let moderator = moderators.contains(&hash);
+
+                            writer.send(NetworkMessage::personal_id(id, moderator));
+
+                            // where everybody stopped reading, the newcomer included
+                            let markers: Vec<_> = {
+                                let mut read_markers = read_markers.lock().unwrap();
+                                let own = read_markers.join(id, &identity).map(|marker| NetworkMessage::read_marker(id, marker));
+
+                                users.iter()
+                                    .filter_map(|(user, ..)| Some(NetworkMessage::read_marker(*user, read_markers.get(*user)?)))
+                                    .chain(own)
+                                    .collect()
+                            };
 
-                                sender.send(msg).unwrap();
+                            for frame in NetworkMessage::user_list_frames(users) {
+                                writer.send(frame);
                             }
 
-                            sender.send(NetworkMessage::user_leave(id)).unwrap();
-                        }).unwrap();
-                    } else {
-                        println!("{}: Expected ClientIdentity, found {}", thread_name, msg);
+                            writer.send(info.read().unwrap().message());
+
+                            for pinned in pins.lock().unwrap().messages() {
+                                writer.send(pinned);
+                            }
+
+                            for (user, message) in away.lock().unwrap().iter() {
+                                writer.send(NetworkMessage::away(*user, true, message.to_owned()));
+                            }
+
+                            for marker in markers {
+                                writer.send(marker);
+                            }
+
+                            sender.send(NetworkMessage::user_join(client.name().to_owned(), id, client.bot())).unwrap();
+                            t_addr.write().unwrap().push((id, client.name().to_owned(), client.bot(), writer.clone()));
+
+                            std::thread::Builder::new().name(format!("{}_thread", client.name())).spawn(move || {
+                                let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
+                                let mut buf = [0; protocol::network::MAX_FRAME_LEN];
+                                let mut uploads = HashMap::new();
+
+                                'read: loop {
+                                    let len = match stream.read(&mut buf) {
+                                        Ok(0) => {
+                                            println!("{}: Closed by peer", thread_name);
+                                            break;
+                                        }
+                                        Ok(len) => len,
+                                        Err(err) if err.kind() == std::io::ErrorKind::ConnectionReset => {
+                                            println!("{}: Closed by peer", thread_name);
+                                            break;
+                                        }
+                                        Err(err) => {
+                                            println!("{}: {}", thread_name, err);
+                                            break;
+                                        }
+                                    };
+
+                                    reader.extend(&buf[..len]);
+
+                                    loop {
+                                        let msg = match reader.next_message() {
+                                            Ok(Some(msg)) => msg,
+                                            Ok(None) => break,
+                                            Err(err) => {
+                                                println!("{}: {}", thread_name, err);
+                                                break 'read;
+                                            }
+                                        };
+
+                                        // chunks of an upload in progress are paced by a byte bucket of their own,
+                                        // read markers by another one that drops them quietly
+                                        let verdict = match &msg {
+                                            NetworkMessage::FileChunk(chunk) if uploads.contains_key(&chunk.transfer_id()) => {
+                                                limiter.pace_upload(chunk.data().len());
+                                                Verdict::Accept
+                                            }
+                                            NetworkMessage::ReadMarker(_) if !limiter.read_marker() => continue,
+                                            NetworkMessage::ReadMarker(_) => Verdict::Accept,
+                                            _ => limiter.check(peer.ip(), msg.msg_len(), &mut ip_limiter.lock().unwrap()),
+                                        };
+
+                                        let replies = match verdict {
+                                            Verdict::Accept => match msg {
+                                                // uploads are per connection, ids of other senders' uploads are unknown here too
+                                                NetworkMessage::FileChunk(chunk) if !uploads.contains_key(&chunk.transfer_id()) => {
+                                                    println!("{}: Dropping a chunk of unknown upload {}", thread_name, chunk.transfer_id());
+                                                    vec![]
+                                                }
+                                                NetworkMessage::FileOffer(_)
+                                                | NetworkMessage::FileChunk(_)
+                                                | NetworkMessage::FileComplete(_)
+                                                | NetworkMessage::FileCancel(_)
+                                                | NetworkMessage::FileRequest(_) => spool.handle(id, &identity, &mut uploads, msg, &sender, &writer),
+                                                // sent as whoever is connected, whatever the client claims
+                                                NetworkMessage::Message(message) => {
+                                                    sender.send(NetworkMessage::message(0, id, message.content().to_owned(), Vec::new())).unwrap();
+                                                    vec![]
+                                                }
+                                                NetworkMessage::PrivateMessage(message) => {
+                                                    sender.send(NetworkMessage::private_message(id, message.to(), message.content().to_owned())).unwrap();
+                                                    vec![]
+                                                }
+                                                NetworkMessage::Away(away) => {
+                                                    sender.send(NetworkMessage::away(id, away.away(), away.message().to_owned())).unwrap();
+                                                    vec![]
+                                                }
+                                                NetworkMessage::ReadMarker(marker) => {
+                                                    sender.send(NetworkMessage::read_marker(id, marker.message_id())).unwrap();
+                                                    vec![]
+                                                }
+                                                NetworkMessage::UserRename(rename) if rename.name().trim().is_empty() => {
+                                                    println!("{}: Ignoring blank name", thread_name);
+                                                    vec![]
+                                                }
+                                                NetworkMessage::UserRename(rename) => {
+                                                    println!("{}: Renamed to {}", thread_name, rename.name());
+                                                    sender.send(NetworkMessage::user_rename(id, rename.name().to_owned())).unwrap();
+                                                    vec![]
+                                                }
+                                                NetworkMessage::Pin(_) | NetworkMessage::Unpin(_) if !moderator => {
+                                                    println!("{}: Not a moderator, ignoring {}", thread_name, msg);
+                                                    vec![]
+                                                }
+                                                // the records are read without holding up everybody else's messages
+                                                NetworkMessage::SearchRequest(request) => {
+                                                    let found = history.lock().unwrap().search(
+                                                        request.query(),
+                                                        request.user(),
+                                                        request.before(),
+                                                        request.after(),
+                                                        request.limit(),
+                                                    );
+
+                                                    found.read()
+                                                }
+                                                NetworkMessage::Pin(_) | NetworkMessage::Unpin(_) => {
+                                                    sender.send(msg).unwrap();
+                                                    vec![]
+                                                }
+                                                // ServerInfo, Pinned and the rest only ever come from the server
+                                                msg => {
+                                                    println!("{}: Ignoring {}, clients don't send it", thread_name, msg);
+                                                    vec![]
+                                                }
+                                            }
+                                            Verdict::Reject(retry_after) => vec![NetworkMessage::rate_limited(retry_after.as_millis() as u32, false)],
+                                            Verdict::Mute(remaining) => {
+                                                println!("{}: Muted for {}s", thread_name, remaining.as_secs());
+                                                vec![NetworkMessage::rate_limited(remaining.as_millis() as u32, true)]
+                                            }
+                                            Verdict::Disconnect => {
+                                                println!("{}: Disconnected for flooding", thread_name);
+                                                break 'read;
+                                            }
+                                        };
+
+                                        for reply in replies {
+                                            writer.send(reply);
+                                        }
+                                    }
+                                }
+
+                                writer.close();
+                                sender.send(NetworkMessage::user_leave(id)).unwrap();
+                            }).unwrap();
+                        } else {
+                            println!("{}: Expected ClientIdentity, found {}", thread_name, msg);
+                        }
                     }
+                    Err(ref err) if err.kind() == std::io::ErrorKind::TimedOut => {
+                        println!("{}: Expected ClientIdentity within second. Nothing happend", thread_name);
+                    }
+                    Err(err) => {
+                        println!("{}: {}", thread_name, err);
+                    }
+                };
+            }
+        }).unwrap()
+    }).collect();
+
+    while let Ok(msg) = receiver.recv() {
+        let msg = match msg {
+            NetworkMessage::Message(message) => {
+                let (from, mentions) = {
+                    let addr = addr.read().unwrap();
+                    let from = addr.iter()
+                        .find(|(user, ..)| *user == message.from())
+                        .map(|(_, name, ..)| name.to_owned())
+                        .unwrap_or_default();
+
+                    // only the users online when it is sent
+                    (from, mention::resolve(message.content(), addr.iter().map(|(id, name, ..)| (*id, name.as_str()))))
+                };
+
+                let id = history.lock().unwrap().append(&from, message.content());
+                pins.lock().unwrap().record(id, from, message.content().to_owned());
+
+                NetworkMessage::message(id, message.from(), message.content().to_owned(), mentions)
+            }
+            NetworkMessage::Pin(pin) => match pins.lock().unwrap().pin(pin.message_id()) {
+                Ok(pinned) => pinned,
+                Err(err) => {
+                    println!("Pins: {}", err);
+                    continue;
                 }
-                Err(ref err) if err.kind() == std::io::ErrorKind::TimedOut => {
-                    println!("{}: Expected ClientIdentity within second. Nothing happend", thread_name);
-                }
+            },
+            NetworkMessage::Unpin(unpin) => match pins.lock().unwrap().unpin(unpin.message_id()) {
+                Ok(unpin) => unpin,
                 Err(err) => {
-                    println!("{}: {}", thread_name, err);
+                    println!("Pins: {}", err);
+                    continue;
                 }
-            };
-        }
-    }).unwrap();
+            },
+            // two users with the same name could not be told apart, the second one is told why nothing changed
+            NetworkMessage::UserRename(rename) => {
+                let mut users = addr.write().unwrap();
 
-    while let Ok(msg) = receiver.recv() {
-        let buf = msg.clone().into_vec();
+                if users.iter().any(|(id, name, ..)| *id != rename.id() && name == rename.name()) {
+                    println!("Rename: {} is already taken", rename.name());
+
+                    // sending waits for room, not while everybody else waits on the lock
+                    let writer = users.iter().find(|(id, ..)| *id == rename.id()).map(|(.., writer)| writer.clone());
+                    drop(users);
+
+                    if let Some(writer) = writer {
+                        writer.send(NetworkMessage::refused(refusal::NAME_TAKEN));
+                    }
+
+                    continue;
+                }
+
+                if let Some((.., name, _, _)) = users.iter_mut().find(|(id, ..)| *id == rename.id()) {
+                    *name = rename.name().to_owned();
+                }
+
+                NetworkMessage::UserRename(rename)
+            }
+            NetworkMessage::Away(status) => {
+                match status.away() {
+                    true => away.lock().unwrap().insert(status.id(), status.message().to_owned()),
+                    false => away.lock().unwrap().remove(&status.id()),
+                };
+
+                NetworkMessage::Away(status)
+            }
+            // only shared when it moved forward, to a message that was sent
+            NetworkMessage::ReadMarker(marker) => {
+                let sent = history.lock().unwrap().latest().is_some_and(|latest| marker.message_id() <= latest);
+
+                match sent && read_markers.lock().unwrap().set(marker.user(), marker.message_id()) {
+                    true => NetworkMessage::ReadMarker(marker),
+                    false => continue,
+                }
+            }
+            msg => msg,
+        };
+
+        let buf: Arc<[u8]> = match msg.clone().into_vec() {
+            Ok(buf) => buf.into(),
+            Err(err) => {
+                println!("{}", err);
+                continue;
+            }
+        };
 
         match msg {
-            NetworkMessage::UserJoin(join) => for (id, _, stream) in addr.write().unwrap().iter_mut() {
+            NetworkMessage::UserJoin(join) => for (id, _, _, writer) in addr.read().unwrap().iter() {
                 if *id == join.id() {
                     continue;
                 }
 
-                stream.write_all(&buf).unwrap();
+                writer.broadcast(&buf);
             },
             NetworkMessage::UserLeave(leave) => {
                 let mut addr_lock = addr.write().unwrap();
 
-                let remove = addr_lock.iter().position(|(id, _, _)| {
+                let remove = addr_lock.iter().position(|(id, ..)| {
                     *id == leave.id()
                 }).unwrap();
                 addr_lock.remove(remove);
+                away.lock().unwrap().remove(&leave.id());
+                read_markers.lock().unwrap().leave(leave.id());
 
-                for (_, _, stream) in addr_lock.iter_mut() {
-                    stream.write_all(&buf).unwrap();
+                for (_, _, _, writer) in addr_lock.iter() {
+                    writer.broadcast(&buf);
                 }
             },
-            NetworkMessage::Message(_) => for (_, _, stream) in addr.write().unwrap().iter_mut() {
-                stream.write_all(&buf).unwrap();
+            NetworkMessage::Message(_)
+            | NetworkMessage::UserRename(_)
+            | NetworkMessage::Away(_)
+            | NetworkMessage::ReadMarker(_)
+            | NetworkMessage::Pinned(_)
+            | NetworkMessage::Unpin(_)
+            | NetworkMessage::ServerInfo(_) => for (_, _, _, writer) in addr.read().unwrap().iter() {
+                writer.broadcast(&buf);
             }
+            // echoed to the sender, who learns it went through
+            NetworkMessage::PrivateMessage(message) => for (id, _, _, writer) in addr.read().unwrap().iter() {
+                if *id == message.to() || *id == message.from() {
+                    writer.broadcast(&buf);
+                }
+            },
+            NetworkMessage::FileOffer(offer) => for (id, _, _, writer) in addr.read().unwrap().iter() {
+                if *id == offer.from() {
+                    continue;
+                }
+
+                writer.broadcast(&buf);
+            },
             _ => {}
         }
     }
 
-    handle_ping.join().unwrap();
-    handle_tcp.join().unwrap();
+    for handle_ping in handle_pings {
+        handle_ping.join().unwrap();
+    }
+    handle_announce.join().unwrap();
+    for handle_tcp in handle_tcp {
+        handle_tcp.join().unwrap();
+    }
+    handle_expire.join().unwrap();
+}
+
+// clients drop the server from their list right away instead of waiting for it to expire
+fn shutdown(announcer: &Announcer, read_markers: &Mutex<ReadMarkers>, thread_name: &str) -> ! {
+    println!("{}: Shutting down", thread_name);
+    announcer.goodbye();
+    read_markers.lock().unwrap().save();
+    std::process::exit(0);
+}
+
+// stable across restarts so clients can recognize the server on any address
+fn load_server_id(dir: &Path) -> [u8; SERVER_ID_LEN] {
+    let path = dir.join("server_id");
+
+    if let Ok(bytes) = std::fs::read(&path) {
+        if bytes.len() == SERVER_ID_LEN {
+            let mut server_id = [0; SERVER_ID_LEN];
+            server_id.copy_from_slice(&bytes);
+            return server_id;
+        }
+    }
+
+    let server_id: [u8; SERVER_ID_LEN] = rand::thread_rng().gen();
+    if let Err(err) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, server_id)) {
+        println!("{}: {}", path.display(), err);
+    }
+
+    server_id
+}
+
+fn bind_listeners(config: &Config) -> std::io::Result<Vec<TcpListener>> {
+    if config.bind.is_empty() {
+        return Ok(vec![bind_any(config.port)?]);
+    }
+
+    config.bind.iter().map(|ip| {
+        let addr = SocketAddr::new(*ip, config.port);
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+        // so 0.0.0.0 and :: can both be listed
+        if addr.is_ipv6() {
+            socket.set_only_v6(true)?;
+        }
+
+        socket.set_reuse_address(true)?;
+        socket.bind(&addr.into()).map_err(|err| std::io::Error::new(err.kind(), format!("{}: {}", addr, err)))?;
+        socket.listen(128)?;
+
+        let listener: TcpListener = socket.into();
+        println!("TCP: Listening on {}", listener.local_addr()?);
+
+        Ok(listener)
+    }).collect()
+}
+
+// dual stack so IPv6 clients found through discovery can connect too
+fn bind_any(port: u16) -> std::io::Result<TcpListener> {
+    let listener = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP)).and_then(|socket| {
+        socket.set_only_v6(false)?;
+        socket.bind(&SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, port, 0, 0)).into())?;
+        socket.listen(128)?;
+
+        Ok(socket.into())
+    });
+
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("TCP: IPv6 unavailable, {}", err);
+            TcpListener::bind(("0.0.0.0", port))?
+        }
+    };
+
+    println!("TCP: Listening on {}", listener.local_addr()?);
+    Ok(listener)
+}
+
+// an empty directory of its own for each test
+#[cfg(test)]
+fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("messaging-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
 }
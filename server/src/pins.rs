@@ -0,0 +1,108 @@
+use protocol::network::NetworkMessage;
+use std::collections::VecDeque;
+
+// messages older than this can't be pinned anymore
+pub const RECENT_MESSAGES: usize = 500;
+pub const MAX_PINNED: usize = 50;
+
+// pinned set of a channel, the server has a single one
+#[derive(Debug, Default)]
+pub struct Pins {
+    // (message id, author name, content), oldest first
+    recent: VecDeque<(u32, String, String)>,
+    pinned: Vec<(u32, String, String)>,
+}
+
+impl Pins {
+    pub fn record(&mut self, id: u32, from: String, content: String) {
+        if self.recent.len() == RECENT_MESSAGES {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back((id, from, content));
+    }
+
+    // the Pinned message to broadcast
+    pub fn pin(&mut self, id: u32) -> Result<NetworkMessage, String> {
+        if self.pinned.iter().any(|(pinned, ..)| *pinned == id) {
+            return Err(format!("Message {} is already pinned", id));
+        }
+
+        if self.pinned.len() >= MAX_PINNED {
+            return Err(format!("At most {} messages can be pinned", MAX_PINNED));
+        }
+
+        let (_, from, content) = self.recent.iter()
+            .find(|(recent, ..)| *recent == id)
+            .ok_or_else(|| format!("Message {} is unknown or too old to be pinned", id))?;
+
+        self.pinned.push((id, from.to_owned(), content.to_owned()));
+        Ok(NetworkMessage::pinned(id, from.to_owned(), content.to_owned()))
+    }
+
+    pub fn unpin(&mut self, id: u32) -> Result<NetworkMessage, String> {
+        let index = self.pinned.iter()
+            .position(|(pinned, ..)| *pinned == id)
+            .ok_or_else(|| format!("Message {} is not pinned", id))?;
+
+        self.pinned.remove(index);
+        Ok(NetworkMessage::unpin(id))
+    }
+
+    // sent on join, oldest pin first
+    pub fn messages(&self) -> Vec<NetworkMessage> {
+        self.pinned.iter()
+            .map(|(id, from, content)| NetworkMessage::pinned(*id, from.to_owned(), content.to_owned()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pins, MAX_PINNED, RECENT_MESSAGES};
+    use protocol::network::NetworkMessage;
+
+    fn recorded(count: u32) -> Pins {
+        let mut pins = Pins::default();
+
+        for id in 0..count {
+            pins.record(id, String::from("Alice"), format!("message {}", id));
+        }
+
+        pins
+    }
+
+    #[test]
+    fn pins_and_unpins() {
+        let mut pins = recorded(3);
+
+        assert_eq!(pins.pin(1).unwrap(), NetworkMessage::pinned(1, String::from("Alice"), String::from("message 1")));
+        assert!(pins.pin(1).is_err());
+        assert_eq!(pins.messages(), [NetworkMessage::pinned(1, String::from("Alice"), String::from("message 1"))]);
+
+        assert_eq!(pins.unpin(1).unwrap(), NetworkMessage::unpin(1));
+        assert!(pins.unpin(1).is_err());
+        assert!(pins.messages().is_empty());
+    }
+
+    #[test]
+    fn only_recent_messages() {
+        let mut pins = recorded(RECENT_MESSAGES as u32 + 1);
+
+        assert!(pins.pin(0).is_err());
+        assert!(pins.pin(1).is_ok());
+        assert!(pins.pin(RECENT_MESSAGES as u32 + 1).is_err());
+    }
+
+    #[test]
+    fn at_most_max_pinned() {
+        let mut pins = recorded(MAX_PINNED as u32 + 1);
+
+        for id in 0..MAX_PINNED as u32 {
+            pins.pin(id).unwrap();
+        }
+
+        assert!(pins.pin(MAX_PINNED as u32).is_err());
+        assert_eq!(pins.messages().len(), MAX_PINNED);
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimits {
+    pub messages_per_sec: u32,
+    pub bytes_per_sec: u32,
+    pub ip_messages_per_sec: u32,
+    pub ip_bytes_per_sec: u32,
+    pub connections_per_min: u32,
+    // file chunks have a bucket of their own, uploads are much larger than messages
+    pub upload_bytes_per_sec: u32,
+    // attachments sent to one client, shared between its downloads
+    pub download_bytes_per_sec: u32,
+    // clients send them on their own as messages come in, extra ones are dropped without a strike
+    pub read_markers_per_sec: u32,
+    // violations tolerated before muting, then before disconnecting
+    pub mute_after: u32,
+    pub disconnect_after: u32,
+    pub mute_duration: Duration,
+    // violations older than this are forgotten
+    pub strike_window: Duration,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            messages_per_sec: 5,
+            bytes_per_sec: 4096,
+            ip_messages_per_sec: 10,
+            ip_bytes_per_sec: 8192,
+            connections_per_min: 10,
+            upload_bytes_per_sec: 256 * 1024,
+            download_bytes_per_sec: 1024 * 1024,
+            read_markers_per_sec: 4,
+            mute_after: 5,
+            disconnect_after: 10,
+            mute_duration: Duration::from_secs(30),
+            strike_window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn set(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let value: u32 = value.parse().map_err(|_| format!("{}: expected a number, found {}", flag, value))?;
+
+        match flag {
+            "--messages-per-sec" => self.messages_per_sec = value,
+            "--bytes-per-sec" => self.bytes_per_sec = value,
+            "--ip-messages-per-sec" => self.ip_messages_per_sec = value,
+            "--ip-bytes-per-sec" => self.ip_bytes_per_sec = value,
+            "--connections-per-min" => self.connections_per_min = value,
+            "--upload-bytes-per-sec" => self.upload_bytes_per_sec = value,
+            "--download-bytes-per-sec" => self.download_bytes_per_sec = value,
+            "--read-markers-per-sec" => self.read_markers_per_sec = value,
+            "--mute-after" => self.mute_after = value,
+            "--disconnect-after" => self.disconnect_after = value,
+            "--mute-secs" => self.mute_duration = Duration::from_secs(value as u64),
+            unknown => return Err(format!("Unknown flag: {}", unknown)),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    // `refill` tokens are added every `period`, up to `capacity`
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        let capacity = capacity.max(1) as f64;
+
+        Self {
+            capacity,
+            refill: capacity / period.as_secs_f64(),
+            tokens: capacity,
+            last: Instant::now(),
+        }
+    }
+
+    pub fn per_second(capacity: u32) -> Self {
+        Self::new(capacity, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(capacity: u32) -> Self {
+        Self::new(capacity, Duration::from_secs(60))
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.refill).min(self.capacity);
+        self.last = now;
+    }
+
+    // a single request larger than the bucket is accepted once the bucket is full
+    fn cost(&self, amount: usize) -> f64 {
+        (amount as f64).min(self.capacity)
+    }
+
+    // on failure, returns how long to wait before `amount` tokens are available
+    pub fn check(&mut self, amount: usize) -> Result<(), Duration> {
+        self.refill();
+
+        let amount = self.cost(amount);
+        match self.tokens >= amount {
+            true => Ok(()),
+            false => Err(Duration::from_secs_f64((amount - self.tokens) / self.refill)),
+        }
+    }
+
+    // once `check` said they are there
+    pub fn take(&mut self, amount: usize) {
+        self.tokens -= self.cost(amount);
+    }
+
+    pub fn try_take(&mut self, amount: usize) -> Result<(), Duration> {
+        self.check(amount)?;
+        self.take(amount);
+
+        Ok(())
+    }
+}
+
+// an IP not heard from for this long has full buckets again, it is forgotten
+const IDLE: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct IpBuckets {
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    connections: TokenBucket,
+    last_seen: Instant,
+}
+
+#[derive(Debug)]
+pub struct IpLimiter {
+    ips: HashMap<IpAddr, IpBuckets>,
+    last_prune: Instant,
+}
+
+impl Default for IpLimiter {
+    fn default() -> Self {
+        Self { ips: HashMap::new(), last_prune: Instant::now() }
+    }
+}
+
+impl IpLimiter {
+    fn buckets(&mut self, ip: IpAddr, limits: &RateLimits) -> &mut IpBuckets {
+        let now = Instant::now();
+        if now.duration_since(self.last_prune) >= IDLE {
+            self.prune(now);
+        }
+
+        let buckets = self.ips.entry(ip).or_insert_with(|| IpBuckets {
+            messages: TokenBucket::per_second(limits.ip_messages_per_sec),
+            bytes: TokenBucket::per_second(limits.ip_bytes_per_sec),
+            connections: TokenBucket::per_minute(limits.connections_per_min),
+            last_seen: now,
+        });
+
+        buckets.last_seen = now;
+        buckets
+    }
+
+    fn prune(&mut self, now: Instant) {
+        self.ips.retain(|_, buckets| now.duration_since(buckets.last_seen) < IDLE);
+        self.last_prune = now;
+    }
+
+    pub fn connection(&mut self, ip: IpAddr, limits: &RateLimits) -> Result<(), Duration> {
+        self.buckets(ip, limits).connections.try_take(1)
+    }
+
+    pub fn check_message(&mut self, ip: IpAddr, len: usize, limits: &RateLimits) -> Result<(), Duration> {
+        let buckets = self.buckets(ip, limits);
+
+        buckets.messages.check(1).and_then(|_| buckets.bytes.check(len))
+    }
+
+    pub fn take_message(&mut self, ip: IpAddr, len: usize, limits: &RateLimits) {
+        let buckets = self.buckets(ip, limits);
+
+        buckets.messages.take(1);
+        buckets.bytes.take(len);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Reject(Duration),
+    Mute(Duration),
+    Disconnect,
+}
+
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    limits: RateLimits,
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    upload: TokenBucket,
+    read_markers: TokenBucket,
+    strikes: u32,
+    last_strike: Instant,
+    muted_until: Option<Instant>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            messages: TokenBucket::per_second(limits.messages_per_sec),
+            bytes: TokenBucket::per_second(limits.bytes_per_sec),
+            upload: TokenBucket::per_second(limits.upload_bytes_per_sec),
+            read_markers: TokenBucket::per_second(limits.read_markers_per_sec),
+            strikes: 0,
+            last_strike: Instant::now(),
+            muted_until: None,
+        }
+    }
+
+    pub fn check(&mut self, ip: IpAddr, len: usize, ip_limiter: &mut IpLimiter) -> Verdict {
+        let now = Instant::now();
+
+        if let Some(until) = self.muted_until {
+            if now < until {
+                return self.strike(now, until - now);
+            }
+
+            self.muted_until = None;
+        }
+
+        // every bucket is checked before any is taken from, a rejected message costs nothing
+        let allowed = self.messages.check(1)
+            .and_then(|_| self.bytes.check(len))
+            .and_then(|_| ip_limiter.check_message(ip, len, &self.limits));
+
+        match allowed {
+            Ok(()) => {
+                self.messages.take(1);
+                self.bytes.take(len);
+                ip_limiter.take_message(ip, len, &self.limits);
+
+                Verdict::Accept
+            }
+            Err(retry_after) => self.strike(now, retry_after),
+        }
+    }
+
+    // chunks of an upload are held back rather than dropped, a missing chunk would fail the whole upload
+    pub fn pace_upload(&mut self, len: usize) {
+        while let Err(wait) = self.upload.try_take(len) {
+            std::thread::sleep(wait);
+        }
+    }
+
+    // false when this one should be dropped, the next one moves the marker at least as far
+    pub fn read_marker(&mut self) -> bool {
+        self.read_markers.try_take(1).is_ok()
+    }
+
+    fn strike(&mut self, now: Instant, retry_after: Duration) -> Verdict {
+        if now.duration_since(self.last_strike) > self.limits.strike_window {
+            self.strikes = 0;
+        }
+
+        self.strikes += 1;
+        self.last_strike = now;
+
+        if self.strikes >= self.limits.disconnect_after {
+            Verdict::Disconnect
+        } else if self.strikes == self.limits.mute_after {
+            self.muted_until = Some(now + self.limits.mute_duration);
+            Verdict::Mute(self.limits.mute_duration)
+        } else if let Some(until) = self.muted_until {
+            Verdict::Mute(until - now)
+        } else {
+            Verdict::Reject(retry_after)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectionLimiter, IpLimiter, RateLimits, TokenBucket, Verdict, IDLE};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn bursts_up_to_capacity() {
+        let mut bucket = TokenBucket::per_second(3);
+
+        for _ in 0..3 {
+            assert!(bucket.try_take(1).is_ok());
+        }
+
+        let wait = bucket.try_take(1).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(334));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let mut bucket = TokenBucket::new(2, Duration::from_millis(20));
+
+        bucket.try_take(2).unwrap();
+        assert!(bucket.try_take(1).is_err());
+
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(bucket.try_take(1).is_ok());
+    }
+
+    #[test]
+    fn takes_oversized_requests_when_full() {
+        let mut bucket = TokenBucket::per_second(10);
+
+        assert!(bucket.try_take(100).is_ok());
+        assert!(bucket.try_take(1).is_err());
+    }
+
+    #[test]
+    fn rejected_messages_cost_nothing() {
+        let limits = RateLimits { messages_per_sec: 2, ip_bytes_per_sec: 10, ..RateLimits::default() };
+        let mut ip_limiter = IpLimiter::default();
+        let mut limiter = ConnectionLimiter::new(limits);
+
+        assert_eq!(limiter.check(IP, 10, &mut ip_limiter), Verdict::Accept);
+        assert!(matches!(limiter.check(IP, 10, &mut ip_limiter), Verdict::Reject(_)));
+
+        // the message token is still there for something that fits
+        assert_eq!(limiter.check(IP, 0, &mut ip_limiter), Verdict::Accept);
+    }
+
+    #[test]
+    fn mutes_then_disconnects() {
+        let limits = RateLimits { messages_per_sec: 1, mute_after: 2, disconnect_after: 3, ..RateLimits::default() };
+        let mut ip_limiter = IpLimiter::default();
+        let mut limiter = ConnectionLimiter::new(limits);
+
+        assert_eq!(limiter.check(IP, 1, &mut ip_limiter), Verdict::Accept);
+        assert!(matches!(limiter.check(IP, 1, &mut ip_limiter), Verdict::Reject(_)));
+        assert_eq!(limiter.check(IP, 1, &mut ip_limiter), Verdict::Mute(limits.mute_duration));
+        assert_eq!(limiter.check(IP, 1, &mut ip_limiter), Verdict::Disconnect);
+    }
+
+    #[test]
+    fn paces_uploads() {
+        let limits = RateLimits { upload_bytes_per_sec: 1000, ..RateLimits::default() };
+        let mut limiter = ConnectionLimiter::new(limits);
+
+        let start = Instant::now();
+        limiter.pace_upload(1000);
+        limiter.pace_upload(100);
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn drops_extra_read_markers() {
+        let limits = RateLimits { read_markers_per_sec: 2, ..RateLimits::default() };
+        let mut limiter = ConnectionLimiter::new(limits);
+
+        assert!(limiter.read_marker());
+        assert!(limiter.read_marker());
+        assert!(!limiter.read_marker());
+
+        // markers don't count against messages
+        assert_eq!(limiter.check(IP, 10, &mut IpLimiter::default()), Verdict::Accept);
+    }
+
+    #[test]
+    fn forgets_idle_ips() {
+        let limits = RateLimits::default();
+        let mut ip_limiter = IpLimiter::default();
+
+        ip_limiter.connection(IP, &limits).unwrap();
+        ip_limiter.connection(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), &limits).unwrap();
+        assert_eq!(ip_limiter.ips.len(), 2);
+
+        ip_limiter.prune(Instant::now() + IDLE);
+        assert!(ip_limiter.ips.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use protocol::file::{self, HASH_LEN};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+// last message each client has read, by identity so it is still there when they come back under any name
+#[derive(Debug)]
+pub struct ReadMarkers {
+    path: PathBuf,
+    markers: HashMap<String, u32>,
+    // identity of everybody online, by user id
+    online: HashMap<u32, String>,
+    // changed since the last save
+    dirty: bool,
+}
+
+impl ReadMarkers {
+    // [message id] [identity], one line per client
+    pub fn open(path: PathBuf) -> std::io::Result<Self> {
+        let markers = match fs::read_to_string(&path) {
+            Ok(markers) => markers,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        // markers kept by name before identities are dropped too
+        let markers = markers.lines().filter_map(|line| {
+            let marker = line.split_once(' ')
+                .filter(|(_, identity)| file::from_hex::<HASH_LEN>(identity).is_some())
+                .and_then(|(id, identity)| Some((identity.to_owned(), id.parse().ok()?)));
+
+            if marker.is_none() {
+                println!("Read markers: Skipping {}", line);
+            }
+
+            marker
+        }).collect();
+
+        Ok(Self { path, markers, online: HashMap::new(), dirty: false })
+    }
+
+    // returns where they stopped reading last time
+    pub fn join(&mut self, user: u32, identity: &str) -> Option<u32> {
+        self.online.insert(user, identity.to_owned());
+        self.get(user)
+    }
+
+    pub fn leave(&mut self, user: u32) {
+        self.online.remove(&user);
+    }
+
+    pub fn get(&self, user: u32) -> Option<u32> {
+        self.online.get(&user).and_then(|identity| self.markers.get(identity)).copied()
+    }
+
+    // markers only move forward, returns whether this one did, it is written with the next save
+    pub fn set(&mut self, user: u32, message_id: u32) -> bool {
+        let identity = match self.online.get(&user) {
+            Some(identity) => identity,
+            None => return false,
+        };
+
+        if self.markers.get(identity).is_some_and(|marker| *marker >= message_id) {
+            return false;
+        }
+
+        self.markers.insert(identity.to_owned(), message_id);
+        self.dirty = true;
+
+        true
+    }
+
+    // written next to the file then moved over it, a crash midway leaves the previous markers
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        let markers: String = self.markers.iter().map(|(identity, id)| format!("{} {}\n", id, identity)).collect();
+        let temp = self.path.with_extension("tmp");
+
+        match fs::write(&temp, markers).and_then(|_| fs::rename(&temp, &self.path)) {
+            Ok(()) => self.dirty = false,
+            Err(err) => println!("Read markers: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadMarkers;
+    use std::fs;
+
+    const ALICE: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    const BOB: &str = "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn only_move_forward() {
+        let path = crate::test_dir("read_markers_forward").join("read_markers");
+        let mut markers = ReadMarkers::open(path).unwrap();
+
+        assert_eq!(markers.join(1, ALICE), None);
+        assert!(markers.set(1, 5));
+        assert!(!markers.set(1, 3));
+        assert!(!markers.set(1, 5));
+        assert!(markers.set(1, 8));
+        assert_eq!(markers.get(1), Some(8));
+
+        // nobody online under that id
+        assert!(!markers.set(2, 1));
+    }
+
+    #[test]
+    fn follow_the_identity() {
+        let path = crate::test_dir("read_markers_identity").join("read_markers");
+        let mut markers = ReadMarkers::open(path).unwrap();
+
+        markers.join(1, ALICE);
+        markers.join(2, BOB);
+        markers.set(1, 4);
+        markers.leave(1);
+
+        assert_eq!(markers.get(1), None);
+        assert_eq!(markers.get(2), None);
+        // back under another user id, whatever the name
+        assert_eq!(markers.join(3, ALICE), Some(4));
+    }
+
+    #[test]
+    fn saved_only_when_asked() {
+        let path = crate::test_dir("read_markers_save").join("read_markers");
+        let mut markers = ReadMarkers::open(path.clone()).unwrap();
+
+        markers.join(1, ALICE);
+        markers.set(1, 7);
+        assert!(!path.exists());
+
+        markers.save();
+        assert!(!path.with_extension("tmp").exists());
+
+        let mut reopened = ReadMarkers::open(path).unwrap();
+        assert_eq!(reopened.join(1, ALICE), Some(7));
+    }
+
+    #[test]
+    fn skips_bad_lines() {
+        let path = crate::test_dir("read_markers_bad").join("read_markers");
+        fs::write(&path, format!("3 {}\nnot a marker\n4 alice\nx {}\n", ALICE, BOB)).unwrap();
+
+        let mut markers = ReadMarkers::open(path).unwrap();
+
+        assert_eq!(markers.join(1, ALICE), Some(3));
+        assert_eq!(markers.join(2, BOB), None);
+    }
+}
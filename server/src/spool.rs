@@ -0,0 +1,305 @@
+use protocol::file::{self, HASH_LEN, MAX_FILE_SIZE};
+use protocol::network::NetworkMessage;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use super::storage::Storage;
+use super::writer::{Writer, MAX_DOWNLOADS};
+
+// simultaneous uploads allowed per connection
+pub const MAX_UPLOADS: usize = 2;
+
+#[derive(Debug)]
+pub struct Upload {
+    size: u64,
+    hash: [u8; HASH_LEN],
+    name: String,
+    mime: String,
+    path: PathBuf,
+    file: File,
+    written: u64,
+}
+
+impl Upload {
+    pub fn create(dir: &Path, file_name: String, size: u64, hash: [u8; HASH_LEN], name: String, mime: String) -> Result<Self, String> {
+        if size > MAX_FILE_SIZE {
+            return Err(format!("File must be at most {} byte, found {}", MAX_FILE_SIZE, size));
+        }
+
+        let path = dir.join(file_name);
+        let file = File::create(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        Ok(Self { size, hash, name, mime, path, file, written: 0 })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hash(&self) -> [u8; HASH_LEN] {
+        self.hash
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn mime(&self) -> &String {
+        &self.mime
+    }
+
+    pub fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), String> {
+        if offset != self.written {
+            return Err(format!("Expected chunk at offset {}, found {}", self.written, offset));
+        }
+
+        if self.written + data.len() as u64 > self.size {
+            return Err(format!("Upload exceeds the announced size of {} byte", self.size));
+        }
+
+        self.file.write_all(data).map_err(|err| err.to_string())?;
+        self.written += data.len() as u64;
+
+        Ok(())
+    }
+
+    // read back the spooled file once every chunk has been received
+    pub fn finish(&mut self) -> Result<Vec<u8>, String> {
+        if self.written != self.size {
+            return Err(format!("Upload is incomplete: {} of {} byte", self.written, self.size));
+        }
+
+        self.file.flush().map_err(|err| err.to_string())?;
+
+        let mut data = Vec::with_capacity(self.size as usize);
+        File::open(&self.path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|err| err.to_string())?;
+
+        if file::hash(&data) != self.hash {
+            return Err(String::from("Upload does not match the announced hash"));
+        }
+
+        Ok(data)
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+    storage: Arc<Mutex<Storage>>,
+}
+
+impl Spool {
+    pub fn new(dir: PathBuf, storage: Arc<Mutex<Storage>>) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir, storage })
+    }
+
+    // handle file transfer frames sent by `user`, whose uploads count against `owner`'s quota,
+    // returns the replies to send back, downloads go out through `writer` at its own pace
+    pub fn handle(&self, user: u32, owner: &str, uploads: &mut HashMap<u32, Upload>, msg: NetworkMessage, sender: &Sender<NetworkMessage>, writer: &Writer) -> Vec<NetworkMessage> {
+        match msg {
+            NetworkMessage::FileOffer(offer) => {
+                let transfer_id = offer.transfer_id();
+
+                let upload = match uploads.len() {
+                    len if len >= MAX_UPLOADS => Err(format!("Too many uploads, at most {} at once", MAX_UPLOADS)),
+                    _ if uploads.contains_key(&transfer_id) => Err(String::from("Transfer id already in use")),
+                    _ => Upload::create(
+                        &self.dir,
+                        format!("{}_{}.part", user, transfer_id),
+                        offer.size(),
+                        *offer.hash(),
+                        offer.name().to_owned(),
+                        offer.mime().to_owned(),
+                    ),
+                };
+
+                match upload {
+                    Ok(upload) => {
+                        uploads.insert(transfer_id, upload);
+                        vec![]
+                    }
+                    Err(err) => {
+                        println!("Upload {} from {}: {}", transfer_id, user, err);
+                        vec![NetworkMessage::file_cancel(transfer_id, user)]
+                    }
+                }
+            }
+            NetworkMessage::FileChunk(chunk) => {
+                let transfer_id = chunk.transfer_id();
+                let upload = match uploads.get_mut(&transfer_id) {
+                    Some(upload) => upload,
+                    None => return vec![],
+                };
+
+                match upload.write(chunk.offset(), chunk.data()) {
+                    Ok(()) => vec![],
+                    Err(err) => {
+                        println!("Upload {} from {}: {}", transfer_id, user, err);
+                        uploads.remove(&transfer_id);
+                        vec![NetworkMessage::file_cancel(transfer_id, user)]
+                    }
+                }
+            }
+            NetworkMessage::FileComplete(complete) => {
+                let transfer_id = complete.transfer_id();
+                let mut upload = match uploads.remove(&transfer_id) {
+                    Some(upload) => upload,
+                    None => return vec![],
+                };
+
+                let stored = upload.finish().and_then(|data| {
+                    self.storage.lock().unwrap().store(owner, &upload.hash(), &data)
+                });
+
+                if let Err(err) = stored {
+                    println!("Upload {} from {}: {}", transfer_id, user, err);
+                    return vec![NetworkMessage::file_cancel(transfer_id, user)];
+                }
+
+                // others fetch the content by hash whenever they want it
+                sender.send(NetworkMessage::file_offer(
+                    transfer_id,
+                    user,
+                    upload.size(),
+                    upload.hash(),
+                    upload.name().to_owned(),
+                    upload.mime().to_owned(),
+                )).unwrap();
+
+                vec![NetworkMessage::file_complete(transfer_id, user)]
+            }
+            NetworkMessage::FileCancel(cancel) => {
+                uploads.remove(&cancel.transfer_id());
+                vec![]
+            }
+            NetworkMessage::FileRequest(request) => {
+                let transfer_id = request.transfer_id();
+                let data = match self.storage.lock().unwrap().load(request.hash()) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        println!("Request {} from {}: {}", transfer_id, user, err);
+                        return vec![NetworkMessage::file_cancel(transfer_id, user)];
+                    }
+                };
+
+                match writer.download(transfer_id, user, data) {
+                    true => vec![],
+                    false => {
+                        println!("Request {} from {}: Too many downloads, at most {} at once", transfer_id, user, MAX_DOWNLOADS);
+                        vec![NetworkMessage::file_cancel(transfer_id, user)]
+                    }
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spool, Upload, MAX_UPLOADS};
+    use crate::storage::{Storage, StorageLimits};
+    use crate::writer::Writer;
+    use protocol::file;
+    use protocol::network::NetworkMessage;
+    use std::collections::HashMap;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    fn spool(name: &str) -> Spool {
+        let dir = crate::test_dir(name);
+        let storage = Storage::open(dir.join("storage"), StorageLimits::default()).unwrap();
+
+        Spool::new(dir.join("spool"), Arc::new(Mutex::new(storage))).unwrap()
+    }
+
+    // the client end has to stay open for the writer to have somewhere to write
+    fn writer() -> (Writer, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        (Writer::spawn(server, "test", u32::MAX).unwrap(), client)
+    }
+
+    #[test]
+    fn checks_chunks() {
+        let dir = crate::test_dir("spool_chunks");
+        let data = b"attachment";
+        let upload = |hash| Upload::create(&dir, String::from("upload.part"), data.len() as u64, hash, String::from("a.txt"), String::from("text/plain")).unwrap();
+
+        let mut valid = upload(file::hash(data));
+        assert!(valid.write(4, &data[4..]).is_err());
+        valid.write(0, &data[..4]).unwrap();
+        assert!(valid.finish().is_err());
+        valid.write(4, &data[4..]).unwrap();
+        assert!(valid.write(data.len() as u64, b"more").is_err());
+        assert_eq!(valid.finish().unwrap(), data);
+        drop(valid);
+        assert!(!dir.join("upload.part").exists());
+
+        let mut tampered = upload(file::hash(b"something else"));
+        tampered.write(0, data).unwrap();
+        assert!(tampered.finish().is_err());
+    }
+
+    #[test]
+    fn stores_finished_uploads_and_offers_them() {
+        let spool = spool("spool_upload");
+        let (writer, _client) = writer();
+        let (sender, receiver) = mpsc::channel();
+        let mut uploads = HashMap::new();
+        let data = b"attachment";
+        let hash = file::hash(data);
+        let mut handle = |msg| spool.handle(1, "alice", &mut uploads, msg, &sender, &writer);
+
+        let offer = NetworkMessage::file_offer(3, 1, data.len() as u64, hash, String::from("a.txt"), String::from("text/plain"));
+        assert!(handle(offer.clone()).is_empty());
+        assert!(handle(NetworkMessage::file_chunk(3, 0, data.to_vec())).is_empty());
+        assert_eq!(handle(NetworkMessage::file_complete(3, 1)), [NetworkMessage::file_complete(3, 1)]);
+
+        assert_eq!(receiver.try_recv().unwrap(), offer);
+        assert_eq!(spool.storage.lock().unwrap().load(&hash).unwrap(), data);
+
+        // fetched by hash once stored, unknown hashes are turned down
+        assert!(handle(NetworkMessage::file_request(4, hash)).is_empty());
+        assert_eq!(handle(NetworkMessage::file_request(5, file::hash(b"other"))), [NetworkMessage::file_cancel(5, 1)]);
+    }
+
+    #[test]
+    fn cancels_what_it_cannot_take() {
+        let spool = spool("spool_cancel");
+        let (writer, _client) = writer();
+        let (sender, _receiver) = mpsc::channel();
+        let mut uploads = HashMap::new();
+        let offer = |id| NetworkMessage::file_offer(id, 1, 4, file::hash(b"data"), String::from("a.txt"), String::from("text/plain"));
+
+        for id in 0..MAX_UPLOADS as u32 {
+            assert!(spool.handle(1, "alice", &mut uploads, offer(id), &sender, &writer).is_empty());
+        }
+
+        let id = MAX_UPLOADS as u32;
+        assert_eq!(spool.handle(1, "alice", &mut uploads, offer(id), &sender, &writer), [NetworkMessage::file_cancel(id, 1)]);
+
+        // a chunk past the announced size drops the upload
+        assert_eq!(spool.handle(1, "alice", &mut uploads, NetworkMessage::file_chunk(0, 0, b"too long".to_vec()), &sender, &writer), [NetworkMessage::file_cancel(0, 1)]);
+        assert_eq!(uploads.len(), MAX_UPLOADS - 1);
+
+        assert!(spool.handle(1, "alice", &mut uploads, NetworkMessage::file_cancel(1, 1), &sender, &writer).is_empty());
+        assert!(uploads.is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use protocol::file::{self, HASH_LEN};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy)]
+pub struct StorageLimits {
+    pub user_quota: u64,
+    pub global_quota: u64,
+    pub max_age: Duration,
+    pub expire_every: Duration,
+}
+
+impl Default for StorageLimits {
+    fn default() -> Self {
+        Self {
+            user_quota: 64 * 1024 * 1024,
+            global_quota: 1024 * 1024 * 1024,
+            max_age: Duration::from_secs(7 * 24 * 3600),
+            expire_every: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl StorageLimits {
+    pub fn set(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        let value: u64 = value.parse().map_err(|_| format!("{}: expected a number, found {}", flag, value))?;
+        let scaled = |unit: u64| value.checked_mul(unit).ok_or_else(|| format!("{}: {} is too large", flag, value));
+
+        match flag {
+            "--user-quota-mb" => self.user_quota = scaled(1024 * 1024)?,
+            "--global-quota-mb" => self.global_quota = scaled(1024 * 1024)?,
+            "--max-age-hours" => self.max_age = Duration::from_secs(scaled(3600)?),
+            // the expiry thread would never sleep
+            "--expire-every-mins" if value == 0 => return Err(format!("{}: must be at least 1", flag)),
+            "--expire-every-mins" => self.expire_every = Duration::from_secs(scaled(60)?),
+            unknown => return Err(format!("Unknown flag: {}", unknown)),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    size: u64,
+    owner: String,
+    stored: u64,
+}
+
+// attachments are stored under the hex encoded SHA-256 of their content
+#[derive(Debug)]
+pub struct Storage {
+    dir: PathBuf,
+    limits: StorageLimits,
+    entries: HashMap<[u8; HASH_LEN], Entry>,
+}
+
+impl Storage {
+    const INDEX: &'static str = "index";
+
+    pub fn open(dir: PathBuf, limits: StorageLimits) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let index = fs::read_to_string(dir.join(Self::INDEX)).unwrap_or_default();
+        let mut entries = HashMap::new();
+
+        // [hash] [size] [stored] [owner]
+        for line in index.lines() {
+            let mut fields = line.splitn(4, ' ');
+            let entry = (|| {
+                let hash = file::from_hex(fields.next()?)?;
+                let size = fields.next()?.parse().ok()?;
+                let stored = fields.next()?.parse().ok()?;
+                let owner = fields.next()?.to_owned();
+
+                Some((hash, Entry { size, owner, stored }))
+            })();
+
+            match entry {
+                Some((hash, entry)) if dir.join(file::to_hex(&hash)).is_file() => {
+                    entries.insert(hash, entry);
+                }
+                _ => println!("Storage: Skipping index entry {}", line),
+            }
+        }
+
+        Ok(Self { dir, limits, entries })
+    }
+
+    pub fn limits(&self) -> &StorageLimits {
+        &self.limits
+    }
+
+    fn usage(&self, owner: Option<&str>) -> u64 {
+        self.entries.values()
+            .filter(|entry| match owner {
+                Some(owner) => entry.owner == owner,
+                None => true,
+            })
+            .map(|entry| entry.size)
+            .sum()
+    }
+
+    // `owner` is whoever uploaded it, by identity so a new name doesn't mean a new quota
+    pub fn store(&mut self, owner: &str, hash: &[u8; HASH_LEN], data: &[u8]) -> Result<(), String> {
+        // identical content is only kept once, storing it again extends its lifetime
+        if let Some(entry) = self.entries.get_mut(hash) {
+            entry.stored = now();
+            return self.save_index();
+        }
+
+        // the owner ends each line of the index
+        let owner = owner.replace(|c: char| c.is_control(), " ");
+
+        let size = data.len() as u64;
+        if self.usage(Some(&owner)) + size > self.limits.user_quota {
+            return Err(format!("{} exceeds its quota of {} byte", owner, self.limits.user_quota));
+        }
+
+        if self.usage(None) + size > self.limits.global_quota {
+            return Err(format!("Storage is full, quota of {} byte reached", self.limits.global_quota));
+        }
+
+        let path = self.dir.join(file::to_hex(hash));
+        fs::write(&path, data).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        self.entries.insert(*hash, Entry { size, owner, stored: now() });
+        self.save_index()
+    }
+
+    pub fn load(&self, hash: &[u8; HASH_LEN]) -> Result<Vec<u8>, String> {
+        if !self.entries.contains_key(hash) {
+            return Err(format!("Unknown attachment {}", file::to_hex(hash)));
+        }
+
+        let path = self.dir.join(file::to_hex(hash));
+        fs::read(&path).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // remove attachments older than max_age, returns how many were removed
+    pub fn expire(&mut self) -> usize {
+        let limit = now().saturating_sub(self.limits.max_age.as_secs());
+        let expired: Vec<_> = self.entries.iter()
+            .filter(|(_, entry)| entry.stored < limit)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        for hash in &expired {
+            self.entries.remove(hash);
+            let _ = fs::remove_file(self.dir.join(file::to_hex(hash)));
+        }
+
+        if !expired.is_empty() {
+            if let Err(err) = self.save_index() {
+                println!("Storage: {}", err);
+            }
+        }
+
+        expired.len()
+    }
+
+    fn save_index(&self) -> Result<(), String> {
+        let index: String = self.entries.iter().map(|(hash, entry)| {
+            format!("{} {} {} {}\n", file::to_hex(hash), entry.size, entry.stored, entry.owner)
+        }).collect();
+
+        let path = self.dir.join(Self::INDEX);
+        fs::write(&path, index).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Storage, StorageLimits};
+    use protocol::file;
+    use std::time::Duration;
+
+    fn limits() -> StorageLimits {
+        StorageLimits { user_quota: 10, global_quota: 15, ..StorageLimits::default() }
+    }
+
+    #[test]
+    fn stores_and_reloads() {
+        let dir = crate::test_dir("storage_reload");
+        let data = b"attachment";
+        let hash = file::hash(data);
+
+        let mut storage = Storage::open(dir.clone(), limits()).unwrap();
+        storage.store("alice", &hash, data).unwrap();
+        assert_eq!(storage.load(&hash).unwrap(), data);
+
+        let storage = Storage::open(dir, limits()).unwrap();
+        assert_eq!(storage.load(&hash).unwrap(), data);
+        assert!(storage.load(&file::hash(b"other")).is_err());
+    }
+
+    #[test]
+    fn quotas_are_per_owner_and_global() {
+        let mut storage = Storage::open(crate::test_dir("storage_quota"), limits()).unwrap();
+
+        storage.store("alice", &file::hash(b"12345678"), b"12345678").unwrap();
+        assert!(storage.store("alice", &file::hash(b"abc"), b"abc").is_err());
+
+        // stored once, whoever sends it again
+        storage.store("bob", &file::hash(b"12345678"), b"12345678").unwrap();
+
+        storage.store("bob", &file::hash(b"abcdefg"), b"abcdefg").unwrap();
+        assert!(storage.store("carol", &file::hash(b"x"), b"x").is_err());
+    }
+
+    #[test]
+    fn expires_old_attachments() {
+        let mut storage = Storage::open(crate::test_dir("storage_expire"), limits()).unwrap();
+        let (old, new) = (file::hash(b"old"), file::hash(b"new"));
+
+        storage.store("alice", &old, b"old").unwrap();
+        storage.store("alice", &new, b"new").unwrap();
+        storage.entries.get_mut(&old).unwrap().stored -= limits().max_age.as_secs() + 1;
+
+        assert_eq!(storage.expire(), 1);
+        assert!(storage.load(&old).is_err());
+        assert!(storage.load(&new).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_flags() {
+        let mut limits = StorageLimits::default();
+
+        limits.set("--user-quota-mb", "3").unwrap();
+        assert_eq!(limits.user_quota, 3 * 1024 * 1024);
+        limits.set("--expire-every-mins", "2").unwrap();
+        assert_eq!(limits.expire_every, Duration::from_secs(120));
+
+        assert!(limits.set("--expire-every-mins", "0").is_err());
+        assert!(limits.set("--global-quota-mb", &u64::MAX.to_string()).is_err());
+        assert!(limits.set("--max-age-hours", "-1").is_err());
+    }
+}
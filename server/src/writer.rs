@@ -0,0 +1,244 @@
+use protocol::file::CHUNK_LEN;
+use protocol::network::NetworkMessage;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+use super::rate_limit::TokenBucket;
+
+// frames waiting for a client, one that falls this far behind on broadcasts is dropped
+const QUEUE_LEN: usize = 256;
+
+// attachments sent to one client at once
+pub const MAX_DOWNLOADS: usize = 4;
+
+// a client that stops reading doesn't hold its writer forever
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+enum Outgoing {
+    Frame(Arc<[u8]>),
+    Download(Download),
+}
+
+#[derive(Debug)]
+struct Download {
+    transfer_id: u32,
+    from: u32,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+// the only way frames reach a client, its thread owns the socket so frames never interleave
+#[derive(Debug, Clone)]
+pub struct Writer {
+    sender: SyncSender<Outgoing>,
+    stream: Arc<TcpStream>,
+    downloads: Arc<AtomicUsize>,
+}
+
+impl Writer {
+    // downloads share `download_bytes_per_sec`, frames are never held back for them
+    pub fn spawn(stream: TcpStream, name: &str, download_bytes_per_sec: u32) -> std::io::Result<Self> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        let writer = Self { sender, stream: Arc::new(stream.try_clone()?), downloads: Arc::new(AtomicUsize::new(0)) };
+
+        let downloads = writer.downloads.clone();
+        let bucket = TokenBucket::per_second(download_bytes_per_sec);
+        std::thread::Builder::new().name(format!("{}_writer", name)).spawn(move || {
+            let thread_name = std::thread::current().name().unwrap_or("Unknown").to_owned();
+
+            if let Err(err) = run(stream, receiver, bucket, &downloads) {
+                println!("{}: {}", thread_name, err);
+            }
+        })?;
+
+        Ok(writer)
+    }
+
+    // waits for room, replies are worth slowing down their own sender for
+    pub fn send(&self, msg: NetworkMessage) {
+        match msg.into_vec() {
+            Ok(buf) => {
+                let _ = self.sender.send(Outgoing::Frame(buf.into()));
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    // never waits, everybody else would wait with it
+    pub fn broadcast(&self, buf: &Arc<[u8]>) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(Outgoing::Frame(buf.clone())) {
+            println!("Writer: {} is too far behind, closing", self.peer());
+            self.close();
+        }
+    }
+
+    // false when the client already has MAX_DOWNLOADS going
+    pub fn download(&self, transfer_id: u32, from: u32, data: Vec<u8>) -> bool {
+        let started = self.downloads.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |downloads| {
+            (downloads < MAX_DOWNLOADS).then(|| downloads + 1)
+        });
+
+        started.is_ok() && self.sender.send(Outgoing::Download(Download { transfer_id, from, data, offset: 0 })).is_ok()
+    }
+
+    // the reader and the writer both stop on their next read or write
+    pub fn close(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn peer(&self) -> String {
+        self.stream.peer_addr().map_or_else(|_| String::from("Unknown"), |peer| peer.to_string())
+    }
+}
+
+// frames go out as they come, downloads take turns a chunk at a time whenever the bucket allows
+fn run(mut stream: TcpStream, receiver: Receiver<Outgoing>, mut bucket: TokenBucket, downloads: &AtomicUsize) -> std::io::Result<()> {
+    let mut pending = VecDeque::new();
+
+    loop {
+        let wait = match pending.front() {
+            None => None,
+            Some(download) => bucket.check(next_chunk(download).len()).err(),
+        };
+
+        let outgoing = match (pending.is_empty(), wait) {
+            (true, _) => match receiver.recv() {
+                Ok(outgoing) => Some(outgoing),
+                Err(_) => return Ok(()),
+            },
+            (false, Some(wait)) => match receiver.recv_timeout(wait) {
+                Ok(outgoing) => Some(outgoing),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            },
+            (false, None) => receiver.try_recv().ok(),
+        };
+
+        match outgoing {
+            Some(Outgoing::Frame(buf)) => stream.write_all(&buf)?,
+            Some(Outgoing::Download(download)) => pending.push_back(download),
+            None if wait.is_none() => {
+                let mut download = pending.pop_front().unwrap();
+                let data = next_chunk(&download);
+                bucket.take(data.len());
+
+                let chunk = NetworkMessage::file_chunk(download.transfer_id, download.offset as u64, data.to_vec());
+                stream.write_all(&chunk.into_vec().unwrap())?;
+                download.offset += data.len();
+
+                match download.offset < download.data.len() {
+                    true => pending.push_back(download),
+                    false => {
+                        let complete = NetworkMessage::file_complete(download.transfer_id, download.from);
+                        stream.write_all(&complete.into_vec().unwrap())?;
+                        downloads.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }
+            }
+            None => (),
+        }
+    }
+}
+
+fn next_chunk(download: &Download) -> &[u8] {
+    let end = (download.offset + CHUNK_LEN).min(download.data.len());
+    &download.data[download.offset..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Writer, MAX_DOWNLOADS};
+    use protocol::file::CHUNK_LEN;
+    use protocol::network::{FrameReader, NetworkMessage};
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+    use std::time::{Duration, Instant};
+
+    fn pair(download_bytes_per_sec: u32) -> (Writer, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        (Writer::spawn(server, "test", download_bytes_per_sec).unwrap(), client)
+    }
+
+    fn read(client: &mut TcpStream, count: usize) -> Vec<NetworkMessage> {
+        let mut reader = FrameReader::new();
+        let mut buf = [0; 4096];
+        let mut messages = Vec::new();
+
+        while messages.len() < count {
+            let len = client.read(&mut buf).unwrap();
+            assert!(len > 0, "closed after {} messages", messages.len());
+            reader.extend(&buf[..len]);
+
+            while let Some(msg) = reader.next_message().unwrap() {
+                messages.push(msg);
+            }
+        }
+
+        messages
+    }
+
+    #[test]
+    fn frames_stay_whole_between_chunks() {
+        let (writer, mut client) = pair(u32::MAX);
+        let data: Vec<u8> = (0..CHUNK_LEN * 3 + 10).map(|byte| byte as u8).collect();
+
+        assert!(writer.download(7, 1, data.clone()));
+        for _ in 0..20 {
+            writer.send(NetworkMessage::away(1, true, String::from("later")));
+        }
+
+        let messages = read(&mut client, 25);
+        let mut received = Vec::new();
+
+        for msg in &messages {
+            match msg {
+                NetworkMessage::FileChunk(chunk) => {
+                    assert_eq!(chunk.offset() as usize, received.len());
+                    received.extend_from_slice(chunk.data());
+                }
+                NetworkMessage::FileComplete(complete) => {
+                    assert_eq!(complete.transfer_id(), 7);
+                    assert_eq!(received, data);
+                }
+                NetworkMessage::Away(_) => (),
+                other => panic!("unexpected {}", other),
+            }
+        }
+
+        assert!(matches!(messages.iter().rfind(|msg| !matches!(msg, NetworkMessage::Away(_))), Some(NetworkMessage::FileComplete(_))));
+    }
+
+    #[test]
+    fn paces_downloads() {
+        let (writer, mut client) = pair(CHUNK_LEN as u32 * 4);
+
+        let start = Instant::now();
+        assert!(writer.download(1, 1, vec![0; CHUNK_LEN * 6]));
+        read(&mut client, 7);
+
+        // the first 4 chunks are the burst, the last 2 wait for the bucket
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn limits_downloads() {
+        let (writer, _client) = pair(1);
+
+        for transfer_id in 0..MAX_DOWNLOADS as u32 {
+            assert!(writer.download(transfer_id, 1, vec![0; CHUNK_LEN * 2]));
+        }
+
+        assert!(!writer.download(99, 1, vec![0; 1]));
+    }
+}